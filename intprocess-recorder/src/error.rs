@@ -0,0 +1,149 @@
+use std::{error, fmt};
+
+use crate::output::OutputId;
+use crate::settings::{Container, Encoder};
+
+/// Everything that can go wrong inside the recorder.
+///
+/// Serializable so it can be sent from the recorder process to the host unchanged.
+#[cfg_attr(feature = "specta", derive(specta::Type))]
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// libobs (or the recorder) has not been initialized yet
+    NotInitialized,
+    AlreadyInitialized,
+    /// libobs currently can't be reinitialized after it has been shut down
+    AlreadyShutDown,
+    /// libobs was initialized in another thread
+    WrongThread,
+    /// there are still `InpRecorder` handles alive
+    RecorderInUse,
+    /// `Exit` was sent before `Shutdown`
+    NotShutDown,
+    /// `Shutdown` or `Exit` was sent to a recorder daemon while other hosts are still attached
+    InUse,
+    StartupFailed,
+    /// one of the objects created during initialization could not be found
+    MissingObject(String),
+    VideoNotSet,
+    VideoResetFailed,
+    AudioResetFailed,
+    /// no encoder is available on the current adapter
+    NoEncoderAvailable,
+    /// the requested encoder is not available on the current adapter
+    EncoderUnavailable(Encoder),
+    /// libobs failed to create the video encoder
+    EncoderCreationFailed(Encoder),
+    /// settings can't be changed while recording
+    Recording,
+    /// the output could not be started - contains the last error of the output if libobs provided one
+    OutputStartFailed(Option<String>),
+    /// no output with this id has been added to this recorder (the main output can't be removed)
+    UnknownOutput(OutputId),
+    /// libobs failed to create an output
+    OutputCreationFailed,
+    /// `start_replay_buffer` was called without configuring a replay buffer in the settings
+    ReplayBufferNotConfigured,
+    /// `save_replay` was called while the replay buffer is not running
+    ReplayBufferNotActive,
+    /// libobs did not accept the request to save the replay buffer
+    ReplaySaveFailed,
+    /// the recording can only be paused and resumed while it is running
+    NotRecording,
+    /// the output does not support pausing
+    PauseUnsupported,
+    /// pausing pauses the encoders all outputs share, so no other output can run while the recording is paused
+    SharedEncoders,
+    /// libobs failed to pause or resume the output - contains the last error of the output if libobs provided one
+    PauseFailed(Option<String>),
+    /// the container can't store the video of the encoder (e.g. AV1 in FLV)
+    IncompatibleContainer {
+        encoder: Encoder,
+        container: Container,
+    },
+    /// the output path has the extension of another container
+    ExtensionMismatch {
+        path: String,
+        container: Container,
+    },
+    /// this libobs can't write the container (hybrid MP4 needs libobs 30.2 or newer)
+    ContainerUnavailable(Container),
+    /// `split_recording` was called without configuring splitting in the settings
+    SplitNotConfigured,
+    /// the output path contains an unknown placeholder or an unmatched brace
+    InvalidPathTemplate(String),
+    /// the directory of the output path could not be created
+    DirectoryCreationFailed(String),
+    /// every file name the path can get a counter for is taken
+    NoUnusedPath(String),
+    /// the file to remux does not exist
+    FileNotFound(String),
+    /// libobs failed to remux the file
+    RemuxFailed(String),
+    /// the file would have been remuxed into itself
+    RemuxSameFile(String),
+    /// remuxing on stop only remuxes a single file, so it can't be combined with splitting
+    RemuxSplitRecording,
+    /// the thread of a `ThreadedRecorder` could not be spawned
+    ThreadSpawnFailed(String),
+    /// the thread of a `ThreadedRecorder` has panicked, so the recorder is unusable
+    ThreadGone,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::NotInitialized => f.write_str("libobs has not been initialized yet"),
+            Error::AlreadyInitialized => f.write_str("libobs has already been initialized"),
+            Error::AlreadyShutDown => f.write_str("libobs has already been shut down"),
+            Error::WrongThread => f.write_str("wrong thread - libobs was initialized in another thread"),
+            Error::RecorderInUse => f.write_str("libobs can't be shut down due to existing Recorder instances"),
+            Error::NotShutDown => f.write_str("recorder is not shut down"),
+            Error::InUse => f.write_str("recorder is still used by other hosts"),
+            Error::StartupFailed => f.write_str("libobs startup failed"),
+            Error::MissingObject(name) => write!(f, "got nullpointer instead of {name}"),
+            Error::VideoNotSet => f.write_str("Error video was not set! Maybe Recorder was not initialized?"),
+            Error::VideoResetFailed => f.write_str("error on libobs reset video"),
+            Error::AudioResetFailed => f.write_str("error on libobs reset audio"),
+            Error::NoEncoderAvailable => f.write_str("no encoder available"),
+            Error::EncoderUnavailable(encoder) => write!(f, "encoder not available: {encoder:?}"),
+            Error::EncoderCreationFailed(encoder) => write!(f, "unable to create video encoder: {encoder:?}"),
+            Error::Recording => f.write_str("can't change settings while recording"),
+            Error::OutputStartFailed(Some(e)) => write!(f, "failed to start recording: {e}"),
+            Error::OutputStartFailed(None) => f.write_str("failed to start recording: no error message"),
+            Error::UnknownOutput(output) => write!(f, "unknown output: {output}"),
+            Error::OutputCreationFailed => f.write_str("unable to create output"),
+            Error::ReplayBufferNotConfigured => f.write_str("no replay buffer configured"),
+            Error::ReplayBufferNotActive => f.write_str("the replay buffer is not running"),
+            Error::ReplaySaveFailed => f.write_str("unable to save the replay buffer"),
+            Error::NotRecording => f.write_str("not recording"),
+            Error::PauseUnsupported => f.write_str("the output does not support pausing"),
+            Error::SharedEncoders => {
+                f.write_str("the encoders are shared with another output that is running or paused")
+            }
+            Error::PauseFailed(Some(e)) => write!(f, "failed to pause or resume the recording: {e}"),
+            Error::PauseFailed(None) => f.write_str("failed to pause or resume the recording: no error message"),
+            Error::IncompatibleContainer { encoder, container } => {
+                write!(f, "{container:?} can't store the video of {encoder:?}")
+            }
+            Error::ExtensionMismatch { path, container } => {
+                write!(f, "{path} does not have the extension of {container:?}")
+            }
+            Error::ContainerUnavailable(container) => write!(f, "container not available: {container:?}"),
+            Error::SplitNotConfigured => f.write_str("splitting is not configured"),
+            Error::InvalidPathTemplate(path) => write!(f, "invalid placeholder in output path: {path}"),
+            Error::DirectoryCreationFailed(path) => write!(f, "unable to create directory: {path}"),
+            Error::NoUnusedPath(path) => write!(f, "no unused file name left for {path}"),
+            Error::FileNotFound(path) => write!(f, "file not found: {path}"),
+            Error::RemuxFailed(path) => write!(f, "unable to remux {path}"),
+            Error::RemuxSameFile(path) => write!(f, "can't remux {path} into itself"),
+            Error::RemuxSplitRecording => f.write_str("a recording that is split can't be remuxed on stop"),
+            Error::ThreadSpawnFailed(e) => write!(f, "unable to spawn the libobs thread: {e}"),
+            Error::ThreadGone => f.write_str("the libobs thread has panicked"),
+        }
+    }
+}
+
+impl error::Error for Error {}
+
+pub type Result<T> = std::result::Result<T, Error>;
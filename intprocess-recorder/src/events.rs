@@ -0,0 +1,90 @@
+use crate::output::OutputId;
+
+/// Things that happen inside libobs without being triggered by a command.
+#[cfg_attr(feature = "specta", derive(specta::Type))]
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum RecorderEvent {
+    /// the output has started writing to the file
+    RecordingStarted,
+    /// the output has stopped, either because it was told to or on its own (e.g. disk full)
+    RecordingStopped { reason: StopReason, error: Option<String> },
+    /// the video encoder failed - always followed by `RecordingStopped` (or `OutputStopped`)
+    EncoderError { error: Option<String> },
+    /// an output added with `add_output` has started writing, the main output reports `RecordingStarted` instead
+    OutputStarted { output: OutputId },
+    /// an output added with `add_output` has stopped, the main output reports `RecordingStopped` instead
+    OutputStopped {
+        output: OutputId,
+        reason: StopReason,
+        error: Option<String>,
+    },
+    /// the replay buffer has written what it holds to `path` after being told to with `save_replay`
+    ReplaySaved { path: String },
+    /// a recording that is split into several files has finished the file at `path`
+    ///
+    /// `next` is the file the recording continues in, it is `None` for the last file, which is reported right before
+    /// `RecordingStopped`. Only emitted for the main output and only if splitting is configured.
+    SegmentFinished { path: String, next: Option<String> },
+    /// a remux started with `start_remux` (or on stop) has written `percent` of `output`
+    RemuxProgress { output: String, percent: u8 },
+    /// a remux started with `start_remux` (or on stop) has ended, `output` is only complete if it was a `success`
+    RemuxFinished {
+        input: String,
+        output: String,
+        success: bool,
+    },
+    /// the game capture has hooked into the window
+    SourceHooked,
+    /// the game capture has lost the window (e.g. because it was closed)
+    SourceLost,
+    /// frames have been dropped by the output or skipped because rendering lagged behind since the last event
+    DroppedFrames { dropped: u32, lagged: u32 },
+    /// the recorder process exited unexpectedly and has been replaced by a new one
+    ///
+    /// Only emitted on the host side by a `Recorder` with a restart policy, never by the recorder process itself.
+    /// `interrupted_recording` is the (renamed) file of the recording that was running when the process exited.
+    Restarted {
+        exit_code: Option<i32>,
+        restarts: u32,
+        interrupted_recording: Option<String>,
+    },
+    /// the recorder process has missed `missed_heartbeats` heartbeats in a row (or libobs stopped rendering frames)
+    ///
+    /// Only emitted on the host side by a link with a watchdog, never by the recorder process itself.
+    /// `killed` is `true` if the watchdog has killed the process because of it.
+    Unresponsive { missed_heartbeats: u32, killed: bool },
+}
+
+/// The `code` libobs reports when an output stops.
+#[cfg_attr(feature = "specta", derive(specta::Type))]
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StopReason {
+    /// stopped normally
+    Success,
+    BadPath,
+    ConnectFailed,
+    InvalidStream,
+    Error,
+    Disconnected,
+    Unsupported,
+    NoSpace,
+    EncodeError,
+    Unknown(i32),
+}
+
+impl From<i32> for StopReason {
+    fn from(code: i32) -> Self {
+        match code {
+            0 => Self::Success,
+            libobs_sys::OBS_OUTPUT_BAD_PATH => Self::BadPath,
+            libobs_sys::OBS_OUTPUT_CONNECT_FAILED => Self::ConnectFailed,
+            libobs_sys::OBS_OUTPUT_INVALID_STREAM => Self::InvalidStream,
+            libobs_sys::OBS_OUTPUT_ERROR => Self::Error,
+            libobs_sys::OBS_OUTPUT_DISCONNECTED => Self::Disconnected,
+            libobs_sys::OBS_OUTPUT_UNSUPPORTED => Self::Unsupported,
+            libobs_sys::OBS_OUTPUT_NO_SPACE => Self::NoSpace,
+            libobs_sys::OBS_OUTPUT_ENCODE_ERROR => Self::EncodeError,
+            code => Self::Unknown(code),
+        }
+    }
+}
@@ -1,4 +1,10 @@
+mod error;
+pub mod events;
+pub mod marker;
+pub mod output;
 mod recorder;
 pub mod settings;
+pub mod status;
 
-pub use recorder::InpRecorder;
+pub use error::{Error, Result};
+pub use recorder::{backend, InpRecorder, ThreadedRecorder};
@@ -0,0 +1,53 @@
+use std::path::Path;
+use std::time::Duration;
+
+/// A moment of the recording marked with `add_marker`, e.g. a kill.
+#[cfg_attr(feature = "specta", derive(specta::Type))]
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Marker {
+    pub label: String,
+    /// running time of the recording, i.e. without the time it spent paused
+    pub time: Duration,
+}
+
+// what goes into the sidecar, durations in milliseconds are easier to read for other programs
+#[derive(serde::Serialize)]
+struct Sidecar<'a> {
+    recording: &'a str,
+    // running time of the whole recording, where the last marker ends
+    duration_ms: u128,
+    markers: Vec<SidecarMarker<'a>>,
+}
+
+#[derive(serde::Serialize)]
+struct SidecarMarker<'a> {
+    label: &'a str,
+    time_ms: u128,
+}
+
+/// File the markers of a recording to `recording_path` get written to, e.g. `game.markers.json` for `game.mkv`.
+pub(crate) fn sidecar_path(recording_path: &str) -> String {
+    Path::new(recording_path)
+        .with_extension("markers.json")
+        .to_string_lossy()
+        .to_string()
+}
+
+/// JSON that lists `markers` along with the name and the running time (`duration`) of the recording they belong to.
+pub(crate) fn sidecar(recording_path: &str, duration: Duration, markers: &[Marker]) -> serde_json::Result<String> {
+    let sidecar = Sidecar {
+        recording: Path::new(recording_path)
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or(recording_path),
+        duration_ms: duration.as_millis(),
+        markers: markers
+            .iter()
+            .map(|marker| SidecarMarker {
+                label: &marker.label,
+                time_ms: marker.time.as_millis(),
+            })
+            .collect(),
+    };
+    serde_json::to_string_pretty(&sidecar)
+}
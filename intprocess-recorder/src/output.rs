@@ -0,0 +1,85 @@
+//! Outputs that share the encoders of the recorder, e.g. a second recording next to the main one.
+
+use std::fmt;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use crate::events::{RecorderEvent, StopReason};
+
+/// Identifies an output of the recorder.
+///
+/// `OutputId::MAIN` is the output every recorder starts with, which is controlled by `start_recording` and
+/// `stop_recording`. Every other output is added with `add_output` and gets an id that is unique within the process.
+#[cfg_attr(feature = "specta", derive(specta::Type))]
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct OutputId(u32);
+
+impl OutputId {
+    pub const MAIN: OutputId = OutputId(0);
+
+    pub(crate) fn next() -> Self {
+        static NEXT: AtomicU32 = AtomicU32::new(1);
+        Self(NEXT.fetch_add(1, Ordering::Relaxed))
+    }
+
+    #[must_use]
+    pub fn is_main(self) -> bool {
+        self == Self::MAIN
+    }
+
+    /// Name of the libobs object, unique as long as the id is.
+    pub(crate) fn object_name(self) -> String {
+        format!("output{}", self.0)
+    }
+
+    /// Round trips through the `void *` parameter of a libobs signal callback.
+    pub(crate) fn to_raw(self) -> usize {
+        self.0 as usize
+    }
+
+    pub(crate) fn from_raw(raw: usize) -> Self {
+        Self(raw as u32)
+    }
+}
+
+impl fmt::Display for OutputId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_main() {
+            f.write_str("main output")
+        } else {
+            write!(f, "output {}", self.0)
+        }
+    }
+}
+
+/// What an output added with `add_output` does with the encoded video and audio.
+#[cfg_attr(feature = "specta", derive(specta::Type))]
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum OutputKind {
+    /// writes everything into the file at `path`, just like the main output
+    Recording { path: String },
+}
+
+impl OutputKind {
+    pub(crate) fn type_id(&self) -> &'static str {
+        match self {
+            OutputKind::Recording { .. } => "ffmpeg_muxer",
+        }
+    }
+}
+
+/// The main output keeps reporting `RecordingStarted`, so hosts that only know one output don't notice a difference.
+pub(crate) fn started_event(output: OutputId) -> RecorderEvent {
+    if output.is_main() {
+        RecorderEvent::RecordingStarted
+    } else {
+        RecorderEvent::OutputStarted { output }
+    }
+}
+
+pub(crate) fn stopped_event(output: OutputId, reason: StopReason, error: Option<String>) -> RecorderEvent {
+    if output.is_main() {
+        RecorderEvent::RecordingStopped { reason, error }
+    } else {
+        RecorderEvent::OutputStopped { output, reason, error }
+    }
+}
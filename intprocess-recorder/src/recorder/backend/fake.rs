@@ -0,0 +1,767 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use super::{report_remux, Backend, Data, EventHandler, StopHandler, VideoInfo};
+use crate::events::{RecorderEvent, StopReason};
+use crate::output::{self, OutputId};
+use crate::settings::Adapter;
+
+/// Handle of an object created by a [`FakeBackend`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct FakeId(usize);
+
+/// A call of the [`FakeBackend`] that should fail.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Failure {
+    Startup,
+    ResetVideo,
+    ResetAudio,
+    /// creating an encoder with this id returns nothing
+    EncoderCreation(String),
+    /// starting the output fails with this error
+    OutputStart(Option<String>),
+    /// stopping the output reports `StopReason::Error` with this error, as if the file could not be finalised
+    OutputStop(Option<String>),
+    /// outputs of this type (e.g. `replay_buffer`) can't be paused
+    PauseUnsupported(String),
+    /// pausing or resuming an output fails
+    Pause,
+    /// creating a directory fails
+    CreateDirectory,
+    /// remuxing fails halfway through
+    Remux,
+}
+
+/// An in-memory libobs that records every call.
+///
+/// Clones share their state, so a test can keep a clone around to inspect what the recorder did with it.
+/// Starting and stopping the output reports the same events libobs does, synchronously on the calling thread.
+#[derive(Clone, Default)]
+pub struct FakeBackend {
+    state: Arc<Mutex<State>>,
+}
+
+struct State {
+    calls: Vec<String>,
+    failures: Vec<Failure>,
+    adapter: Adapter,
+    encoder_types: Vec<String>,
+    output_types: Vec<String>,
+    video: Option<VideoInfo>,
+    frames: u32,
+    objects: Vec<Object>,
+    output_sources: BTreeMap<u32, FakeId>,
+    handler: Option<Arc<Mutex<EventHandler>>>,
+    // oldest first
+    stop_handlers: Vec<(FakeId, StopHandler)>,
+    // set by `delay_stops`, holds the stops that have not been reported yet
+    delayed_stops: Option<Vec<Stop>>,
+    // files written by outputs, by `write_file` (along with the contents) or added by the test, and directories created
+    files: BTreeMap<String, String>,
+    directories: BTreeSet<String>,
+}
+
+struct Object {
+    id: String,
+    name: String,
+    settings: Data,
+    refs: u32,
+    active: bool,
+    paused: bool,
+    last_error: Option<String>,
+    // frames written while active
+    frames: u32,
+    // set while the signals of an output are connected
+    signals: Option<OutputId>,
+    replay_signals: bool,
+    // file an output that splits files is writing to and how many files it has written
+    segment: Option<String>,
+    segments: u32,
+}
+
+// what stopping an output reports
+struct Stop {
+    output: FakeId,
+    reason: StopReason,
+    error: Option<String>,
+    signals: Option<OutputId>,
+    segment: Option<String>,
+    // `output_time` when it stopped
+    time: Duration,
+}
+
+impl Default for State {
+    fn default() -> Self {
+        Self {
+            calls: Vec::new(),
+            failures: Vec::new(),
+            adapter: Adapter::new(0, "Fake Adapter".into()),
+            encoder_types: vec!["obs_x264".into(), "ffmpeg_aac".into()],
+            output_types: vec!["ffmpeg_muxer".into(), "replay_buffer".into(), "mp4_output".into()],
+            video: None,
+            frames: 0,
+            objects: Vec::new(),
+            output_sources: BTreeMap::new(),
+            handler: None,
+            stop_handlers: Vec::new(),
+            delayed_stops: None,
+            files: BTreeMap::new(),
+            directories: BTreeSet::new(),
+        }
+    }
+}
+
+impl State {
+    fn create(&mut self, call: &str, id: &str, name: &str, settings: &Data) -> FakeId {
+        self.calls.push(format!("{call}({name})"));
+        self.objects.push(Object {
+            id: id.to_string(),
+            name: name.to_string(),
+            settings: settings.clone(),
+            refs: 1,
+            active: false,
+            paused: false,
+            last_error: None,
+            frames: 0,
+            signals: None,
+            replay_signals: false,
+            segment: None,
+            segments: 0,
+        });
+        FakeId(self.objects.len() - 1)
+    }
+
+    fn by_name(&mut self, call: &str, name: &str) -> Option<FakeId> {
+        self.calls.push(format!("{call}({name})"));
+        let index = self.objects.iter().rposition(|o| o.name == name && o.refs > 0)?;
+        self.objects[index].refs += 1;
+        Some(FakeId(index))
+    }
+
+    fn record(&mut self, call: &str, object: FakeId) -> &mut Object {
+        let object = &mut self.objects[object.0];
+        self.calls.push(format!("{call}({})", object.name));
+        object
+    }
+
+    fn release(&mut self, call: &str, object: FakeId) {
+        let object = self.record(call, object);
+        assert!(object.refs > 0, "{} released more often than referenced", object.name);
+        object.refs -= 1;
+    }
+
+    fn live(&self, name: &str) -> Option<&Object> {
+        self.objects.iter().rev().find(|o| o.name == name && o.refs > 0)
+    }
+}
+
+impl FakeBackend {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replaces the only adapter, its type is derived from the name like for a real one.
+    pub fn set_adapter(&self, name: &str) {
+        self.state().adapter = Adapter::new(0, name.into());
+    }
+
+    /// Replaces the registered encoder types (`obs_x264` and `ffmpeg_aac` by default).
+    pub fn set_encoder_types(&self, ids: &[&str]) {
+        self.state().encoder_types = ids.iter().map(ToString::to_string).collect();
+    }
+
+    /// Replaces the registered output types (`ffmpeg_muxer`, `replay_buffer` and `mp4_output` by default).
+    pub fn set_output_types(&self, ids: &[&str]) {
+        self.state().output_types = ids.iter().map(ToString::to_string).collect();
+    }
+
+    /// Makes the call fail until [`recover`](Self::recover) gets called.
+    pub fn fail(&self, failure: Failure) {
+        self.state().failures.push(failure);
+    }
+
+    pub fn recover(&self, failure: &Failure) {
+        self.state().failures.retain(|f| f != failure);
+    }
+
+    /// Stops the first active output like libobs does on its own, e.g. when the disk is full.
+    pub fn stop_output(&self, reason: StopReason, error: Option<String>) {
+        let active = self.state().objects.iter().position(|o| o.active);
+        if let Some(index) = active {
+            self.stop(FakeId(index), reason, error);
+        }
+    }
+
+    /// Holds back what stopping an output reports, its stop handler included, until [`report_stops`](Self::report_stops).
+    ///
+    /// The output is no longer active in the meantime, like one whose muxer is still finishing its file.
+    pub fn delay_stops(&self) {
+        self.state().delayed_stops.get_or_insert_with(Vec::new);
+    }
+
+    /// Reports the stops held back since [`delay_stops`](Self::delay_stops) and reports the next ones right away again.
+    pub fn report_stops(&self) {
+        let stops = self.state().delayed_stops.take().unwrap_or_default();
+        for stop in stops {
+            self.report_stop(stop);
+        }
+    }
+
+    /// Advances the frame counters as if libobs had rendered `frames` (and written them, if an output is active and not paused).
+    ///
+    /// Nothing gets rendered on its own, so the counters stand still like those of a stuck graphics thread.
+    pub fn render_frames(&self, frames: u32) {
+        let mut state = self.state();
+        state.frames += frames;
+        for output in state.objects.iter_mut().filter(|o| o.active && !o.paused) {
+            output.frames += frames;
+        }
+    }
+
+    /// Makes `file_exists` report the file as existing, like the files outputs have written.
+    pub fn add_file(&self, path: &str) {
+        self.state().files.insert(path.to_string(), String::new());
+    }
+
+    /// Contents of a file written with `write_file`, files written by outputs are empty.
+    #[must_use]
+    pub fn file(&self, path: &str) -> Option<String> {
+        self.state().files.get(path).cloned()
+    }
+
+    /// Directories created so far.
+    #[must_use]
+    pub fn directories(&self) -> Vec<String> {
+        self.state().directories.iter().cloned().collect()
+    }
+
+    /// All calls so far, e.g. `"output_start(output)"` or `"reset_video"`.
+    #[must_use]
+    pub fn calls(&self) -> Vec<String> {
+        self.state().calls.clone()
+    }
+
+    pub fn clear_calls(&self) {
+        self.state().calls.clear();
+    }
+
+    /// Settings of the newest object with this name that has not been released.
+    #[must_use]
+    pub fn settings(&self, name: &str) -> Option<Data> {
+        self.state().live(name).map(|o| o.settings.clone())
+    }
+
+    /// Type id (e.g. `obs_x264`) of the newest object with this name that has not been released.
+    #[must_use]
+    pub fn type_id(&self, name: &str) -> Option<String> {
+        self.state().live(name).map(|o| o.id.clone())
+    }
+
+    /// How many references to objects with this name are held.
+    #[must_use]
+    pub fn references(&self, name: &str) -> u32 {
+        self.state()
+            .objects
+            .iter()
+            .filter(|o| o.name == name)
+            .map(|o| o.refs)
+            .sum()
+    }
+
+    /// Name of the output whose signals are reported as the events of `id`.
+    #[must_use]
+    pub fn output_name(&self, id: OutputId) -> Option<String> {
+        let state = self.state();
+        let output = state.objects.iter().find(|o| o.signals == Some(id) && o.refs > 0)?;
+        Some(output.name.clone())
+    }
+
+    /// Name of the source assigned to an output channel.
+    #[must_use]
+    pub fn output_source(&self, channel: u32) -> Option<String> {
+        let state = self.state();
+        state
+            .output_sources
+            .get(&channel)
+            .map(|id| state.objects[id.0].name.clone())
+    }
+
+    #[must_use]
+    pub fn video(&self) -> Option<VideoInfo> {
+        self.state().video
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn fails(&self, failure: &Failure) -> bool {
+        self.state().failures.contains(failure)
+    }
+
+    fn stop(&self, output: FakeId, reason: StopReason, error: Option<String>) {
+        let mut state = self.state();
+        let object = &mut state.objects[output.0];
+        if !object.active {
+            return;
+        }
+        object.active = false;
+        object.paused = false;
+        object.last_error.clone_from(&error);
+        let signals = object.signals;
+        let segment = object.segment.take();
+        drop(state);
+        let stop = Stop {
+            output,
+            reason,
+            error,
+            signals,
+            segment,
+            time: self.output_time(output),
+        };
+
+        let mut state = self.state();
+        if let Some(delayed) = &mut state.delayed_stops {
+            delayed.push(stop);
+            return;
+        }
+        drop(state);
+        self.report_stop(stop);
+    }
+
+    fn report_stop(&self, stop: Stop) {
+        let Stop {
+            output,
+            reason,
+            error,
+            signals,
+            segment,
+            time,
+        } = stop;
+        let mut state = self.state();
+        let handler = state.handler.clone();
+        let stop_handler = state
+            .stop_handlers
+            .iter()
+            .position(|&(queued, _)| queued == output)
+            .map(|index| state.stop_handlers.remove(index).1);
+        drop(state);
+
+        if let (Some(handler), Some(id)) = (&handler, signals) {
+            let handler = handler.lock().unwrap_or_else(|e| e.into_inner());
+            if reason == StopReason::EncodeError {
+                handler(RecorderEvent::EncoderError { error: error.clone() });
+            }
+            if let (Some(path), OutputId::MAIN) = (segment, id) {
+                handler(RecorderEvent::SegmentFinished { path, next: None });
+            }
+        }
+        // may use the fake (and report events) itself
+        if let Some(stop_handler) = stop_handler {
+            stop_handler(time);
+        }
+        if let (Some(handler), Some(id)) = (handler, signals) {
+            handler.lock().unwrap_or_else(|e| e.into_inner())(output::stopped_event(id, reason, error));
+        }
+    }
+
+    fn emit(&self, event: RecorderEvent) {
+        let handler = self.state().handler.clone();
+        if let Some(handler) = handler {
+            handler.lock().unwrap_or_else(|e| e.into_inner())(event);
+        }
+    }
+}
+
+impl Backend for FakeBackend {
+    type Output = FakeId;
+    type Encoder = FakeId;
+    type Source = FakeId;
+
+    fn startup(&self, _locale: &str) -> bool {
+        self.state().calls.push("startup".into());
+        !self.fails(&Failure::Startup)
+    }
+
+    fn shutdown(&self) {
+        let mut state = self.state();
+        state.calls.push("shutdown".into());
+        state.handler = None;
+        state.stop_handlers.clear();
+    }
+
+    fn add_data_path(&self, _path: &str) {
+        self.state().calls.push("add_data_path".into());
+    }
+
+    fn load_modules(&self, _bin_path: &str, _data_path: &str) {
+        self.state().calls.push("load_modules".into());
+    }
+
+    fn reset_audio(&self) -> bool {
+        self.state().calls.push("reset_audio".into());
+        !self.fails(&Failure::ResetAudio)
+    }
+
+    fn reset_video(&self, video: VideoInfo) -> bool {
+        self.state().calls.push("reset_video".into());
+        if self.fails(&Failure::ResetVideo) {
+            return false;
+        }
+        self.state().video = Some(video);
+        true
+    }
+
+    fn video_info(&self) -> Option<VideoInfo> {
+        self.state().video
+    }
+
+    fn total_frames(&self) -> u32 {
+        self.state().frames
+    }
+
+    fn lagged_frames(&self) -> u32 {
+        0
+    }
+
+    fn adapters(&self) -> Vec<Adapter> {
+        vec![self.state().adapter.clone()]
+    }
+
+    fn encoder_types(&self) -> Vec<String> {
+        self.state().encoder_types.clone()
+    }
+
+    fn output_types(&self) -> Vec<String> {
+        self.state().output_types.clone()
+    }
+
+    fn output_create(&self, id: &str, name: &str, settings: &Data) -> Option<Self::Output> {
+        Some(self.state().create("output_create", id, name, settings))
+    }
+
+    fn output_by_name(&self, name: &str) -> Option<Self::Output> {
+        self.state().by_name("output_by_name", name)
+    }
+
+    fn output_update(&self, output: Self::Output, settings: &Data) {
+        self.state().record("output_update", output).settings.apply(settings);
+    }
+
+    fn output_set_video_encoder(&self, output: Self::Output, encoder: Self::Encoder) {
+        let mut state = self.state();
+        let call = format!(
+            "output_set_video_encoder({}, {})",
+            state.objects[output.0].name, state.objects[encoder.0].name
+        );
+        state.calls.push(call);
+    }
+
+    fn output_set_audio_encoder(&self, output: Self::Output, encoder: Self::Encoder) {
+        let mut state = self.state();
+        let call = format!(
+            "output_set_audio_encoder({}, {})",
+            state.objects[output.0].name, state.objects[encoder.0].name
+        );
+        state.calls.push(call);
+    }
+
+    fn output_start(&self, output: Self::Output) -> bool {
+        let error = self.state().failures.iter().find_map(|f| match f {
+            Failure::OutputStart(error) => Some(error.clone()),
+            _ => None,
+        });
+
+        let mut state = self.state();
+        let output = state.record("output_start", output);
+        if let Some(error) = error {
+            output.last_error = error;
+            return false;
+        }
+        output.active = true;
+        output.last_error = None;
+        output.frames = 0;
+        output.segments = 1;
+        output.segment = match output.settings.get_bool("split_file") {
+            Some(true) => output.settings.get_string("path").map(ToString::to_string),
+            _ => None,
+        };
+        let signals = output.signals;
+        // a replay buffer has no path, it only writes files when saving
+        if let Some(path) = output.settings.get_string("path").map(ToString::to_string) {
+            state.files.insert(path, String::new());
+        }
+        drop(state);
+
+        if let Some(id) = signals {
+            self.emit(output::started_event(id));
+        }
+        true
+    }
+
+    fn output_stop(&self, output: Self::Output) {
+        self.state().record("output_stop", output);
+        let error = self.state().failures.iter().find_map(|failure| match failure {
+            Failure::OutputStop(error) => Some(error.clone()),
+            _ => None,
+        });
+        match error {
+            Some(error) => self.stop(output, StopReason::Error, error),
+            None => self.stop(output, StopReason::Success, None),
+        }
+    }
+
+    fn output_force_stop(&self, output: Self::Output) {
+        self.state().record("output_force_stop", output);
+        self.stop(output, StopReason::Success, None);
+    }
+
+    fn output_active(&self, output: Self::Output) -> bool {
+        self.state().objects[output.0].active
+    }
+
+    fn output_can_pause(&self, output: Self::Output) -> bool {
+        let type_id = self.state().objects[output.0].id.clone();
+        !self.fails(&Failure::PauseUnsupported(type_id))
+    }
+
+    fn output_pause(&self, output: Self::Output, pause: bool) -> bool {
+        let fails = self.fails(&Failure::Pause);
+        let mut state = self.state();
+        let output = state.record(if pause { "output_pause" } else { "output_resume" }, output);
+        if fails || !output.active {
+            return false;
+        }
+        output.paused = pause;
+        true
+    }
+
+    fn output_paused(&self, output: Self::Output) -> bool {
+        self.state().objects[output.0].paused
+    }
+
+    fn output_last_error(&self, output: Self::Output) -> Option<String> {
+        self.state().objects[output.0].last_error.clone()
+    }
+
+    fn output_total_frames(&self, output: Self::Output) -> u32 {
+        self.state().objects[output.0].frames
+    }
+
+    fn output_frames_dropped(&self, _output: Self::Output) -> u32 {
+        0
+    }
+
+    fn output_release(&self, output: Self::Output) {
+        self.state().release("output_release", output);
+    }
+
+    fn replay_buffer_save(&self, output: Self::Output) -> bool {
+        // reports the replay as saved right away, without expanding the format like libobs does
+        let mut state = self.state();
+        let output = state.record("replay_buffer_save", output);
+        if !output.active || !output.replay_signals {
+            return true;
+        }
+        let setting = |name| output.settings.get_string(name).unwrap_or_default().to_string();
+        let path = format!(
+            "{}/{}.{}",
+            setting("directory"),
+            setting("format"),
+            setting("extension")
+        );
+        state.files.insert(path.clone(), String::new());
+        drop(state);
+
+        self.emit(RecorderEvent::ReplaySaved { path });
+        true
+    }
+
+    fn output_split_file(&self, output: Self::Output) -> bool {
+        // names the next file after the format without expanding it, followed by a counter to keep the names apart
+        let mut state = self.state();
+        let output = state.record("output_split_file", output);
+        if output.settings.get_bool("split_file") != Some(true) {
+            return false;
+        }
+        let Some(path) = output.segment.take() else {
+            return true;
+        };
+        output.segments += 1;
+        let setting = |name| output.settings.get_string(name).unwrap_or_default().to_string();
+        let next = format!(
+            "{}/{}_{}.{}",
+            setting("directory"),
+            setting("format"),
+            output.segments,
+            setting("extension")
+        );
+        output.segment = Some(next.clone());
+        let main = output.signals == Some(OutputId::MAIN);
+        state.files.insert(next.clone(), String::new());
+        drop(state);
+
+        if main {
+            self.emit(RecorderEvent::SegmentFinished { path, next: Some(next) });
+        }
+        true
+    }
+
+    fn output_add_chapter(&self, output: Self::Output, name: &str) -> bool {
+        // like libobs, only the hybrid MP4 output writes chapters
+        let mut state = self.state();
+        let output = state.record("output_add_chapter", output);
+        let supported = output.id == "mp4_output";
+        if supported {
+            state.calls.push(format!("chapter({name})"));
+        }
+        supported
+    }
+
+    fn file_exists(&self, path: &str) -> bool {
+        self.state().files.contains_key(path)
+    }
+
+    fn create_directory(&self, path: &str) -> bool {
+        let fails = self.fails(&Failure::CreateDirectory);
+        let mut state = self.state();
+        state.calls.push(format!("create_directory({path})"));
+        if fails {
+            return false;
+        }
+        state.directories.insert(path.to_string());
+        true
+    }
+
+    fn write_file(&self, path: &str, contents: &str) -> bool {
+        let mut state = self.state();
+        state.calls.push(format!("write_file({path})"));
+        state.files.insert(path.to_string(), contents.to_string());
+        true
+    }
+
+    fn remux(&self, input: &str, output: &str, progress: &mut dyn FnMut(f32)) -> bool {
+        // only files the fake knows about can be read
+        self.state().calls.push(format!("remux({input}, {output})"));
+        if !self.file_exists(input) {
+            return false;
+        }
+        progress(0.0);
+        progress(50.0);
+        if self.fails(&Failure::Remux) {
+            return false;
+        }
+        progress(100.0);
+        self.add_file(output);
+        true
+    }
+
+    fn start_remux(&self, input: &str, output: &str) {
+        // remuxes right away instead of in the background
+        report_remux(
+            input,
+            output,
+            |progress| self.remux(input, output, progress),
+            |event| self.emit(event),
+        );
+    }
+
+    fn video_encoder_create(&self, id: &str, name: &str, settings: &Data) -> Option<Self::Encoder> {
+        if self.fails(&Failure::EncoderCreation(id.to_string())) {
+            self.state().calls.push(format!("video_encoder_create({name})"));
+            return None;
+        }
+        Some(self.state().create("video_encoder_create", id, name, settings))
+    }
+
+    fn audio_encoder_create(&self, id: &str, name: &str, settings: &Data) -> Option<Self::Encoder> {
+        if self.fails(&Failure::EncoderCreation(id.to_string())) {
+            self.state().calls.push(format!("audio_encoder_create({name})"));
+            return None;
+        }
+        Some(self.state().create("audio_encoder_create", id, name, settings))
+    }
+
+    fn encoder_by_name(&self, name: &str) -> Option<Self::Encoder> {
+        self.state().by_name("encoder_by_name", name)
+    }
+
+    fn encoder_set_video(&self, encoder: Self::Encoder) {
+        self.state().record("encoder_set_video", encoder);
+    }
+
+    fn encoder_set_audio(&self, encoder: Self::Encoder) {
+        self.state().record("encoder_set_audio", encoder);
+    }
+
+    fn encoder_release(&self, encoder: Self::Encoder) {
+        self.state().release("encoder_release", encoder);
+    }
+
+    fn source_create(&self, id: &str, name: &str, settings: &Data) -> Option<Self::Source> {
+        Some(self.state().create("source_create", id, name, settings))
+    }
+
+    fn source_by_name(&self, name: &str) -> Option<Self::Source> {
+        self.state().by_name("source_by_name", name)
+    }
+
+    fn source_update(&self, source: Self::Source, settings: &Data) {
+        self.state().record("source_update", source).settings.apply(settings);
+    }
+
+    fn set_output_source(&self, channel: u32, source: Option<Self::Source>) {
+        let mut state = self.state();
+        state.calls.push(format!("set_output_source({channel})"));
+        match source {
+            Some(source) => state.output_sources.insert(channel, source),
+            None => state.output_sources.remove(&channel),
+        };
+    }
+
+    fn source_release(&self, source: Self::Source) {
+        self.state().release("source_release", source);
+    }
+
+    fn connect_signals(&self, outputs: &[Self::Output], _video_source: Self::Source) {
+        let mut state = self.state();
+        state.calls.push("connect_signals".into());
+        for output in outputs {
+            state.objects[output.0].signals = Some(OutputId::MAIN);
+        }
+    }
+
+    fn connect_output_signals(&self, output: Self::Output, id: OutputId) {
+        self.state().record("connect_output_signals", output).signals = Some(id);
+    }
+
+    fn disconnect_output_signals(&self, output: Self::Output, _id: OutputId) {
+        self.state().record("disconnect_output_signals", output).signals = None;
+    }
+
+    fn connect_replay_signals(&self, output: Self::Output) {
+        self.state().record("connect_replay_signals", output).replay_signals = true;
+    }
+
+    fn disconnect_replay_signals(&self, output: Self::Output) {
+        self.state().record("disconnect_replay_signals", output).replay_signals = false;
+    }
+
+    fn set_event_handler(&self, handler: Option<EventHandler>) {
+        self.state().handler = handler.map(|handler| Arc::new(Mutex::new(handler)));
+    }
+
+    fn output_queue_stop_handler(&self, output: Self::Output, handler: StopHandler) {
+        // runs the handler right away when the output stops, before the stop is reported
+        self.state().stop_handlers.push((output, handler));
+    }
+
+    fn output_unqueue_stop_handler(&self, output: Self::Output) {
+        let mut state = self.state();
+        if let Some(index) = state.stop_handlers.iter().rposition(|&(queued, _)| queued == output) {
+            drop(state.stop_handlers.remove(index));
+        }
+    }
+}
@@ -0,0 +1,380 @@
+use std::ffi::CStr;
+use std::os::raw::{c_char, c_int};
+use std::ptr::{null_mut, NonNull};
+
+use libobs_sys::{obs_encoder, obs_output, obs_source};
+
+use super::{Backend, Data, EventHandler, StopHandler, VideoInfo};
+use crate::output::OutputId;
+use crate::recorder::get::Get;
+use crate::recorder::obs_data::ObsData;
+use crate::recorder::{jobs, logging, remux, signals};
+use crate::settings::{Adapter, AdapterId, Framerate, Resolution};
+
+#[cfg(target_os = "windows")]
+const GRAPHICS_MODULE: &str = "libobs-d3d11.dll";
+#[cfg(not(target_os = "windows"))]
+const GRAPHICS_MODULE: &str = "libobs-opengl.dll";
+
+/// The real libobs.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Libobs;
+
+impl Backend for Libobs {
+    type Output = NonNull<obs_output>;
+    type Encoder = NonNull<obs_encoder>;
+    type Source = NonNull<obs_source>;
+
+    fn startup(&self, locale: &str) -> bool {
+        logging::install_log_handler();
+        let mut get = Get::new();
+        unsafe { libobs_sys::obs_startup(get.c_str(locale), null_mut(), null_mut()) }
+    }
+
+    fn shutdown(&self) {
+        // remuxes report their end as events and stop handlers may start them, so they have to end before the event
+        // handler is gone
+        jobs::cancel_all();
+        signals::disconnect();
+        unsafe { libobs_sys::obs_shutdown() };
+    }
+
+    fn add_data_path(&self, path: &str) {
+        let mut get = Get::new();
+        unsafe { libobs_sys::obs_add_data_path(get.c_str(path)) };
+    }
+
+    fn load_modules(&self, bin_path: &str, data_path: &str) {
+        let mut get = Get::new();
+        unsafe {
+            libobs_sys::obs_add_module_path(get.c_str(bin_path), get.c_str(data_path));
+            libobs_sys::obs_load_all_modules();
+            libobs_sys::obs_post_load_modules();
+            libobs_sys::obs_log_loaded_modules();
+        }
+    }
+
+    fn reset_audio(&self) -> bool {
+        let ai = libobs_sys::obs_audio_info {
+            samples_per_sec: 44100,
+            speakers: libobs_sys::speaker_layout_SPEAKERS_STEREO,
+        };
+        unsafe { libobs_sys::obs_reset_audio(&ai) }
+    }
+
+    fn reset_video(&self, video: VideoInfo) -> bool {
+        let mut get = Get::new();
+        let mut ovi = libobs_sys::obs_video_info {
+            adapter: AdapterId::default(),
+            graphics_module: get.c_str(GRAPHICS_MODULE),
+            fps_num: video.framerate.num(),
+            fps_den: video.framerate.den(),
+            base_width: video.input_resolution.width(),
+            base_height: video.input_resolution.height(),
+            output_width: video.output_resolution.width(),
+            output_height: video.output_resolution.height(),
+            output_format: libobs_sys::video_format_VIDEO_FORMAT_NV12,
+            gpu_conversion: true,
+            colorspace: libobs_sys::video_colorspace_VIDEO_CS_709,
+            range: libobs_sys::video_range_type_VIDEO_RANGE_DEFAULT,
+            scale_type: libobs_sys::obs_scale_type_OBS_SCALE_LANCZOS,
+        };
+
+        // OBS_VIDEO_SUCCESS is 0, so casting it to c_int should be fine
+        unsafe { libobs_sys::obs_reset_video(&mut ovi) == libobs_sys::OBS_VIDEO_SUCCESS as c_int }
+    }
+
+    fn video_info(&self) -> Option<VideoInfo> {
+        let mut ovi = libobs_sys::obs_video_info::default();
+        if !unsafe { libobs_sys::obs_get_video_info(&mut ovi) } {
+            return None;
+        }
+
+        Some(VideoInfo {
+            input_resolution: Resolution::new(ovi.base_width, ovi.base_height),
+            output_resolution: Resolution::new(ovi.output_width, ovi.output_height),
+            framerate: Framerate::new(ovi.fps_num, ovi.fps_den),
+        })
+    }
+
+    fn total_frames(&self) -> u32 {
+        unsafe { libobs_sys::obs_get_total_frames() }
+    }
+
+    fn lagged_frames(&self) -> u32 {
+        unsafe { libobs_sys::obs_get_lagged_frames() }
+    }
+
+    fn adapters(&self) -> Vec<Adapter> {
+        let mut adapters: Vec<Adapter> = Vec::new();
+
+        unsafe extern "C" fn callback(
+            vec: *mut ::std::os::raw::c_void,
+            name: *const ::std::os::raw::c_char,
+            id: u32,
+        ) -> bool {
+            let adapters = &mut *(vec as *mut Vec<Adapter>);
+            adapters.push(Adapter::new(id, CStr::from_ptr(name).to_string_lossy().to_string()));
+
+            true
+        }
+
+        unsafe {
+            libobs_sys::obs_enter_graphics();
+            libobs_sys::gs_enum_adapters(
+                Some(callback),
+                &mut adapters as *mut Vec<Adapter> as *mut ::std::os::raw::c_void,
+            );
+            libobs_sys::obs_leave_graphics();
+        }
+
+        adapters
+    }
+
+    fn encoder_types(&self) -> Vec<String> {
+        let mut n = 0;
+        let mut types = Vec::new();
+        let mut ptr: *const c_char = unsafe { std::mem::zeroed() };
+        while unsafe { libobs_sys::obs_enum_encoder_types(n, &mut ptr) } {
+            n += 1;
+            if let Ok(id) = unsafe { CStr::from_ptr(ptr) }.to_str() {
+                types.push(id.to_string());
+            }
+        }
+        types
+    }
+
+    fn output_types(&self) -> Vec<String> {
+        let mut n = 0;
+        let mut types = Vec::new();
+        let mut ptr: *const c_char = unsafe { std::mem::zeroed() };
+        while unsafe { libobs_sys::obs_enum_output_types(n, &mut ptr) } {
+            n += 1;
+            if let Ok(id) = unsafe { CStr::from_ptr(ptr) }.to_str() {
+                types.push(id.to_string());
+            }
+        }
+        types
+    }
+
+    fn output_create(&self, id: &str, name: &str, settings: &Data) -> Option<Self::Output> {
+        let mut get = Get::new();
+        let data = ObsData::from(settings);
+        NonNull::new(unsafe {
+            libobs_sys::obs_output_create(get.c_str(id), get.c_str(name), data.as_ptr(), null_mut())
+        })
+    }
+
+    fn output_by_name(&self, name: &str) -> Option<Self::Output> {
+        let mut get = Get::new();
+        NonNull::new(unsafe { libobs_sys::obs_get_output_by_name(get.c_str(name)) })
+    }
+
+    fn output_update(&self, output: Self::Output, settings: &Data) {
+        let data = ObsData::from(settings);
+        unsafe { libobs_sys::obs_output_update(output.as_ptr(), data.as_ptr()) };
+    }
+
+    fn output_set_video_encoder(&self, output: Self::Output, encoder: Self::Encoder) {
+        unsafe { libobs_sys::obs_output_set_video_encoder(output.as_ptr(), encoder.as_ptr()) };
+    }
+
+    fn output_set_audio_encoder(&self, output: Self::Output, encoder: Self::Encoder) {
+        unsafe {
+            libobs_sys::obs_output_set_audio_encoder(
+                output.as_ptr(),
+                encoder.as_ptr(),
+                0, // ignored since we only have 1 output
+            )
+        };
+    }
+
+    fn output_start(&self, output: Self::Output) -> bool {
+        unsafe { libobs_sys::obs_output_start(output.as_ptr()) }
+    }
+
+    fn output_stop(&self, output: Self::Output) {
+        unsafe { libobs_sys::obs_output_stop(output.as_ptr()) };
+    }
+
+    fn output_force_stop(&self, output: Self::Output) {
+        unsafe { libobs_sys::obs_output_force_stop(output.as_ptr()) };
+    }
+
+    fn output_active(&self, output: Self::Output) -> bool {
+        unsafe { libobs_sys::obs_output_active(output.as_ptr()) }
+    }
+
+    fn output_can_pause(&self, output: Self::Output) -> bool {
+        unsafe { libobs_sys::obs_output_can_pause(output.as_ptr()) }
+    }
+
+    fn output_pause(&self, output: Self::Output, pause: bool) -> bool {
+        unsafe { libobs_sys::obs_output_pause(output.as_ptr(), pause) }
+    }
+
+    fn output_paused(&self, output: Self::Output) -> bool {
+        unsafe { libobs_sys::obs_output_paused(output.as_ptr()) }
+    }
+
+    fn output_last_error(&self, output: Self::Output) -> Option<String> {
+        signals::output_last_error(output.as_ptr())
+    }
+
+    fn output_total_frames(&self, output: Self::Output) -> u32 {
+        unsafe { libobs_sys::obs_output_get_total_frames(output.as_ptr()) }.unsigned_abs()
+    }
+
+    fn output_frames_dropped(&self, output: Self::Output) -> u32 {
+        unsafe { libobs_sys::obs_output_get_frames_dropped(output.as_ptr()) }.unsigned_abs()
+    }
+
+    fn output_release(&self, output: Self::Output) {
+        unsafe { libobs_sys::obs_output_release(output.as_ptr()) };
+    }
+
+    fn replay_buffer_save(&self, output: Self::Output) -> bool {
+        unsafe { signals::replay_buffer_save(output.as_ptr()) }
+    }
+
+    fn output_split_file(&self, output: Self::Output) -> bool {
+        unsafe { signals::split_file(output.as_ptr()) }
+    }
+
+    fn output_add_chapter(&self, output: Self::Output, name: &str) -> bool {
+        unsafe { signals::add_chapter(output.as_ptr(), name) }
+    }
+
+    fn file_exists(&self, path: &str) -> bool {
+        std::path::Path::new(path).exists()
+    }
+
+    fn create_directory(&self, path: &str) -> bool {
+        match std::fs::create_dir_all(path) {
+            Ok(()) => true,
+            Err(e) => {
+                log::error!("unable to create {path}: {e}");
+                false
+            }
+        }
+    }
+
+    fn write_file(&self, path: &str, contents: &str) -> bool {
+        match std::fs::write(path, contents) {
+            Ok(()) => true,
+            Err(e) => {
+                log::error!("unable to write {path}: {e}");
+                false
+            }
+        }
+    }
+
+    fn remux(&self, input: &str, output: &str, progress: &mut dyn FnMut(f32)) -> bool {
+        remux::remux(input, output, progress)
+    }
+
+    fn start_remux(&self, input: &str, output: &str) {
+        remux::spawn(input.to_string(), output.to_string());
+    }
+
+    fn video_encoder_create(&self, id: &str, name: &str, settings: &Data) -> Option<Self::Encoder> {
+        let mut get = Get::new();
+        let data = ObsData::from(settings);
+        NonNull::new(unsafe {
+            libobs_sys::obs_video_encoder_create(get.c_str(id), get.c_str(name), data.as_ptr(), null_mut())
+        })
+    }
+
+    fn audio_encoder_create(&self, id: &str, name: &str, settings: &Data) -> Option<Self::Encoder> {
+        let mut get = Get::new();
+        let data = ObsData::from(settings);
+        NonNull::new(unsafe {
+            libobs_sys::obs_audio_encoder_create(get.c_str(id), get.c_str(name), data.as_ptr(), 0, null_mut())
+        })
+    }
+
+    fn encoder_by_name(&self, name: &str) -> Option<Self::Encoder> {
+        let mut get = Get::new();
+        NonNull::new(unsafe { libobs_sys::obs_get_encoder_by_name(get.c_str(name)) })
+    }
+
+    fn encoder_set_video(&self, encoder: Self::Encoder) {
+        unsafe { libobs_sys::obs_encoder_set_video(encoder.as_ptr(), libobs_sys::obs_get_video()) };
+    }
+
+    fn encoder_set_audio(&self, encoder: Self::Encoder) {
+        unsafe { libobs_sys::obs_encoder_set_audio(encoder.as_ptr(), libobs_sys::obs_get_audio()) };
+    }
+
+    fn encoder_release(&self, encoder: Self::Encoder) {
+        unsafe { libobs_sys::obs_encoder_release(encoder.as_ptr()) };
+    }
+
+    fn source_create(&self, id: &str, name: &str, settings: &Data) -> Option<Self::Source> {
+        let mut get = Get::new();
+        let data = ObsData::from(settings);
+        NonNull::new(unsafe {
+            libobs_sys::obs_source_create(get.c_str(id), get.c_str(name), data.as_ptr(), null_mut())
+        })
+    }
+
+    fn source_by_name(&self, name: &str) -> Option<Self::Source> {
+        let mut get = Get::new();
+        NonNull::new(unsafe { libobs_sys::obs_get_source_by_name(get.c_str(name)) })
+    }
+
+    fn source_update(&self, source: Self::Source, settings: &Data) {
+        let data = ObsData::from(settings);
+        unsafe { libobs_sys::obs_source_update(source.as_ptr(), data.as_ptr()) };
+    }
+
+    fn set_output_source(&self, channel: u32, source: Option<Self::Source>) {
+        let source = source.map_or(null_mut(), NonNull::as_ptr);
+        unsafe { libobs_sys::obs_set_output_source(channel, source) };
+    }
+
+    fn source_release(&self, source: Self::Source) {
+        unsafe { libobs_sys::obs_source_release(source.as_ptr()) };
+    }
+
+    fn connect_signals(&self, outputs: &[Self::Output], video_source: Self::Source) {
+        // the recorder keeps a reference to all of these objects until libobs shuts down
+        let outputs = outputs.iter().map(|output| output.as_ptr()).collect();
+        unsafe { signals::connect(outputs, video_source.as_ptr()) };
+    }
+
+    fn connect_output_signals(&self, output: Self::Output, id: OutputId) {
+        unsafe { signals::connect_output(output.as_ptr(), id) };
+    }
+
+    fn disconnect_output_signals(&self, output: Self::Output, id: OutputId) {
+        unsafe { signals::disconnect_output(output.as_ptr(), id) };
+    }
+
+    fn connect_replay_signals(&self, output: Self::Output) {
+        unsafe { signals::connect_replay(output.as_ptr()) };
+    }
+
+    fn disconnect_replay_signals(&self, output: Self::Output) {
+        unsafe { signals::disconnect_replay(output.as_ptr()) };
+    }
+
+    fn set_event_handler(&self, handler: Option<EventHandler>) {
+        signals::set_event_handler(handler);
+    }
+
+    fn output_queue_stop_handler(&self, output: Self::Output, handler: StopHandler) {
+        signals::queue_stop_handler(output.as_ptr(), handler);
+    }
+
+    fn output_unqueue_stop_handler(&self, output: Self::Output) {
+        signals::unqueue_stop_handler(output.as_ptr());
+    }
+
+    fn allocations(&self) -> i64 {
+        #[allow(clippy::useless_conversion)]
+        unsafe {
+            libobs_sys::bnum_allocs().into()
+        }
+    }
+}
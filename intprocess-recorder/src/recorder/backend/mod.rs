@@ -0,0 +1,261 @@
+//! Everything `InpRecorder` needs from libobs, behind a trait.
+//!
+//! [`Libobs`] is the real implementation. With the `fake` feature [`FakeBackend`] is available as well, an in-memory
+//! stand-in that records every call and can be told to fail, so the recorder logic can be tested without libobs.
+
+use std::time::Duration;
+
+use crate::events::RecorderEvent;
+use crate::output::OutputId;
+use crate::settings::{Adapter, Framerate, Resolution};
+
+#[cfg(feature = "fake")]
+pub use fake::{Failure, FakeBackend, FakeId};
+pub use libobs::Libobs;
+
+#[cfg(feature = "fake")]
+mod fake;
+mod libobs;
+
+pub type EventHandler = Box<dyn Fn(RecorderEvent) + Send>;
+/// Gets called with the time the output has been writing for, see `Backend::output_time`.
+pub type StopHandler = Box<dyn FnOnce(Duration) + Send>;
+
+/// The libobs functions used by `InpRecorder`.
+///
+/// Objects are identified by the handle types, which are only valid for as long as they have not been released.
+/// Functions that create or look up an object return `None` if libobs did not return one.
+pub trait Backend: Clone + Send + 'static {
+    type Output: Copy;
+    type Encoder: Copy;
+    type Source: Copy;
+
+    // lifecycle
+    fn startup(&self, locale: &str) -> bool;
+    fn shutdown(&self);
+    fn add_data_path(&self, path: &str);
+    fn load_modules(&self, bin_path: &str, data_path: &str);
+    /// only call this function once on startup
+    /// resetting audio after initialisation crashes libobs
+    fn reset_audio(&self) -> bool;
+    fn reset_video(&self, video: VideoInfo) -> bool;
+    fn video_info(&self) -> Option<VideoInfo>;
+    /// frames rendered since startup
+    fn total_frames(&self) -> u32;
+    /// frames that were not rendered in time since startup
+    fn lagged_frames(&self) -> u32;
+
+    // enumeration
+    fn adapters(&self) -> Vec<Adapter>;
+    /// ids of all registered encoder types
+    fn encoder_types(&self) -> Vec<String>;
+    /// ids of all registered output types
+    fn output_types(&self) -> Vec<String>;
+
+    // output
+    fn output_create(&self, id: &str, name: &str, settings: &Data) -> Option<Self::Output>;
+    fn output_by_name(&self, name: &str) -> Option<Self::Output>;
+    fn output_update(&self, output: Self::Output, settings: &Data);
+    fn output_set_video_encoder(&self, output: Self::Output, encoder: Self::Encoder);
+    fn output_set_audio_encoder(&self, output: Self::Output, encoder: Self::Encoder);
+    fn output_start(&self, output: Self::Output) -> bool;
+    fn output_stop(&self, output: Self::Output);
+    fn output_force_stop(&self, output: Self::Output);
+    fn output_active(&self, output: Self::Output) -> bool;
+    fn output_can_pause(&self, output: Self::Output) -> bool;
+    /// pauses (or resumes with `pause` set to `false`) the output and its encoders
+    fn output_pause(&self, output: Self::Output, pause: bool) -> bool;
+    fn output_paused(&self, output: Self::Output) -> bool;
+    fn output_last_error(&self, output: Self::Output) -> Option<String>;
+    fn output_total_frames(&self, output: Self::Output) -> u32;
+    /// time the output has been writing for, i.e. its frames at the frame rate of the video, which leaves out pauses
+    fn output_time(&self, output: Self::Output) -> Duration {
+        let Some(video) = self.video_info() else {
+            return Duration::ZERO;
+        };
+        let framerate = video.framerate;
+        let nanos = u128::from(self.output_total_frames(output)) * 1_000_000_000 * u128::from(framerate.den())
+            / u128::from(framerate.num());
+        Duration::from_nanos(u64::try_from(nanos).unwrap_or(u64::MAX))
+    }
+    fn output_frames_dropped(&self, output: Self::Output) -> u32;
+    fn output_release(&self, output: Self::Output);
+    /// Asks a `replay_buffer` output to write its contents to a file, returns `false` if it can't be asked.
+    fn replay_buffer_save(&self, output: Self::Output) -> bool;
+    /// Asks the output to continue in a new file, returns `false` if it has not been set up to split files.
+    fn output_split_file(&self, output: Self::Output) -> bool;
+    /// adds a chapter at the current time of the output, fails if its muxer does not write chapters
+    fn output_add_chapter(&self, output: Self::Output, name: &str) -> bool;
+
+    // files
+    fn file_exists(&self, path: &str) -> bool;
+    /// creates the directory along with all missing parents
+    fn create_directory(&self, path: &str) -> bool;
+    fn write_file(&self, path: &str, contents: &str) -> bool;
+    /// Copies the streams of `input` into `output`, `progress` gets called with the percentage done.
+    fn remux(&self, input: &str, output: &str, progress: &mut dyn FnMut(f32)) -> bool;
+    /// Remuxes in the background, reporting the progress and the result with `report_remux`.
+    ///
+    /// `shutdown` interrupts the remuxes that are still running, which then report that they failed.
+    fn start_remux(&self, input: &str, output: &str);
+
+    // encoders
+    fn video_encoder_create(&self, id: &str, name: &str, settings: &Data) -> Option<Self::Encoder>;
+    fn audio_encoder_create(&self, id: &str, name: &str, settings: &Data) -> Option<Self::Encoder>;
+    fn encoder_by_name(&self, name: &str) -> Option<Self::Encoder>;
+    /// connects the encoder to the main video pipeline
+    fn encoder_set_video(&self, encoder: Self::Encoder);
+    /// connects the encoder to the main audio pipeline
+    fn encoder_set_audio(&self, encoder: Self::Encoder);
+    fn encoder_release(&self, encoder: Self::Encoder);
+
+    // sources
+    fn source_create(&self, id: &str, name: &str, settings: &Data) -> Option<Self::Source>;
+    fn source_by_name(&self, name: &str) -> Option<Self::Source>;
+    fn source_update(&self, source: Self::Source, settings: &Data);
+    /// `None` clears the channel
+    fn set_output_source(&self, channel: u32, source: Option<Self::Source>);
+    fn source_release(&self, source: Self::Source);
+
+    // events
+    /// Starts reporting the signals of the main `outputs` (one per output type) and `video_source` to the event handler.
+    ///
+    /// This includes `RecorderEvent::SegmentFinished` for outputs that split files.
+    fn connect_signals(&self, outputs: &[Self::Output], video_source: Self::Source);
+    /// Starts reporting the start and stop signals of an additional output as events of `id`.
+    fn connect_output_signals(&self, output: Self::Output, id: OutputId);
+    fn disconnect_output_signals(&self, output: Self::Output, id: OutputId);
+    /// Starts reporting the files written by a `replay_buffer` output as `RecorderEvent::ReplaySaved`.
+    fn connect_replay_signals(&self, output: Self::Output);
+    fn disconnect_replay_signals(&self, output: Self::Output);
+    /// Replaces the event handler, `None` removes it.
+    fn set_event_handler(&self, handler: Option<EventHandler>);
+    /// Queues `handler` for the next time `output` stops, on its own or not, that the handlers queued before it are
+    /// not waiting for. It gets the `output_time` the output ended at.
+    ///
+    /// The handler runs on a thread of its own, so the stop may be reported before it is done. `shutdown` waits for
+    /// it.
+    fn output_queue_stop_handler(&self, output: Self::Output, handler: StopHandler);
+    /// Drops the handler queued last for `output`, e.g. because the output did not start after all.
+    fn output_unqueue_stop_handler(&self, output: Self::Output);
+
+    /// number of memory allocations libobs currently holds, only used for debug logging
+    fn allocations(&self) -> i64 {
+        0
+    }
+}
+
+/// Runs `remux` and reports its progress in whole percent and its result as events.
+pub(crate) fn report_remux(
+    input: &str,
+    output: &str,
+    remux: impl FnOnce(&mut dyn FnMut(f32)) -> bool,
+    emit: impl Fn(RecorderEvent),
+) {
+    let mut reported = None;
+    let success = remux(&mut |percent| {
+        // libobs reports after every packet
+        let percent = percent.clamp(0.0, 100.0) as u8;
+        if reported != Some(percent) {
+            reported = Some(percent);
+            emit(RecorderEvent::RemuxProgress {
+                output: output.to_string(),
+                percent,
+            });
+        }
+    });
+    emit(RecorderEvent::RemuxFinished {
+        input: input.to_string(),
+        output: output.to_string(),
+        success,
+    });
+}
+
+/// Video settings that can change while libobs is running.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct VideoInfo {
+    pub input_resolution: Resolution,
+    pub output_resolution: Resolution,
+    pub framerate: Framerate,
+}
+
+/// Settings of a libobs object.
+///
+/// Unlike `obs_data` this is plain Rust data, the backend converts it when passing it to libobs.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Data {
+    values: Vec<(String, Value)>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    String(String),
+    Int(i64),
+    Bool(bool),
+}
+
+impl Data {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_string(&mut self, name: impl Into<String>, value: impl Into<String>) {
+        self.set(name.into(), Value::String(value.into()));
+    }
+
+    pub fn set_int(&mut self, name: impl Into<String>, value: impl Into<i64>) {
+        self.set(name.into(), Value::Int(value.into()));
+    }
+
+    pub fn set_bool(&mut self, name: impl Into<String>, value: impl Into<bool>) {
+        self.set(name.into(), Value::Bool(value.into()));
+    }
+
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.values.iter().find(|(n, _)| n == name).map(|(_, v)| v)
+    }
+
+    #[must_use]
+    pub fn get_string(&self, name: &str) -> Option<&str> {
+        match self.get(name) {
+            Some(Value::String(value)) => Some(value),
+            _ => None,
+        }
+    }
+
+    #[must_use]
+    pub fn get_int(&self, name: &str) -> Option<i64> {
+        match self.get(name) {
+            Some(Value::Int(value)) => Some(*value),
+            _ => None,
+        }
+    }
+
+    #[must_use]
+    pub fn get_bool(&self, name: &str) -> Option<bool> {
+        match self.get(name) {
+            Some(Value::Bool(value)) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Merges `other` into `self`, like `obs_*_update` does.
+    pub fn apply(&mut self, other: &Data) {
+        for (name, value) in other.iter() {
+            self.set(name.to_string(), value.clone());
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Value)> {
+        self.values.iter().map(|(n, v)| (n.as_str(), v))
+    }
+
+    fn set(&mut self, name: String, value: Value) {
+        match self.values.iter_mut().find(|(n, _)| *n == name) {
+            Some((_, v)) => *v = value,
+            None => self.values.push((name, value)),
+        }
+    }
+}
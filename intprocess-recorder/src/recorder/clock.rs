@@ -0,0 +1,26 @@
+use std::time::{Duration, Instant};
+
+/// Keeps track of how long the recording has been paused, libobs only knows once a pause has ended.
+#[derive(Copy, Clone, Debug, Default)]
+pub(crate) struct PauseClock {
+    paused_since: Option<Instant>,
+    // pauses that have ended
+    paused: Duration,
+}
+
+impl PauseClock {
+    pub(crate) fn pause(&mut self) {
+        self.paused_since.get_or_insert_with(Instant::now);
+    }
+
+    pub(crate) fn resume(&mut self) {
+        if let Some(since) = self.paused_since.take() {
+            self.paused += since.elapsed();
+        }
+    }
+
+    /// Time spent paused so far, including the pause that is still going on.
+    pub(crate) fn paused(&self) -> Duration {
+        self.paused + self.paused_since.map_or(Duration::ZERO, |since| since.elapsed())
+    }
+}
@@ -0,0 +1,49 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::thread::{self, JoinHandle};
+
+// threads started with `spawn`, they have to end before libobs shuts down
+static JOBS: Mutex<Vec<JoinHandle<()>>> = Mutex::new(Vec::new());
+// makes the running jobs give up where they can, set while `cancel_all` waits for them
+static CANCELLED: AtomicBool = AtomicBool::new(false);
+
+/// Runs `job` on a thread of its own named `name`, e.g. to keep work that blocks away from the threads of libobs.
+///
+/// Returns `false` if the thread could not be spawned, `job` is dropped then.
+pub(crate) fn spawn(name: &str, job: impl FnOnce() + Send + 'static) -> bool {
+    match thread::Builder::new().name(name.into()).spawn(job) {
+        Ok(thread) => {
+            let mut jobs = JOBS.lock().unwrap_or_else(|e| e.into_inner());
+            jobs.retain(|job| !job.is_finished());
+            jobs.push(thread);
+            true
+        }
+        Err(e) => {
+            log::error!("unable to spawn a {name} thread: {e}");
+            false
+        }
+    }
+}
+
+/// Whether the running jobs should give up, because libobs is about to shut down.
+pub(crate) fn cancelled() -> bool {
+    CANCELLED.load(Ordering::Acquire)
+}
+
+/// Makes the running jobs give up and waits for them, including the jobs they spawn in the meantime.
+pub(crate) fn cancel_all() {
+    loop {
+        let jobs = std::mem::take(&mut *JOBS.lock().unwrap_or_else(|e| e.into_inner()));
+        if jobs.is_empty() {
+            break;
+        }
+        CANCELLED.store(true, Ordering::Release);
+        for job in jobs {
+            let name = job.thread().name().unwrap_or_default().to_string();
+            if job.join().is_err() {
+                log::error!("a {name} thread panicked");
+            }
+        }
+    }
+    CANCELLED.store(false, Ordering::Release);
+}
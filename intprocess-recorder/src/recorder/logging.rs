@@ -0,0 +1,46 @@
+use std::ffi::{c_char, c_int, c_void, CStr};
+
+// struct dstr from util/dstr.h - only opaque in the bindings
+#[repr(C)]
+struct Dstr {
+    array: *mut c_char,
+    len: usize,
+    capacity: usize,
+}
+
+// util/dstr.h is not part of the bindings, but the function is exported by libobs
+extern "C" {
+    fn dstr_vprintf(dst: *mut Dstr, format: *const c_char, args: libobs_sys::va_list);
+}
+
+/// Routes all libobs log messages into the `log` crate instead of libobs printing them to stdout.
+///
+/// Has to be called before `obs_startup` to catch every message.
+pub(crate) fn install_log_handler() {
+    unsafe { libobs_sys::base_set_log_handler(Some(log_handler), std::ptr::null_mut()) };
+}
+
+unsafe extern "C" fn log_handler(lvl: c_int, msg: *const c_char, args: libobs_sys::va_list, _: *mut c_void) {
+    let level = match lvl {
+        l if l <= libobs_sys::LOG_ERROR => log::Level::Error,
+        l if l <= libobs_sys::LOG_WARNING => log::Level::Warn,
+        l if l <= libobs_sys::LOG_INFO => log::Level::Info,
+        _ => log::Level::Debug,
+    };
+    if level > log::max_level() {
+        return;
+    }
+
+    let mut message = Dstr {
+        array: std::ptr::null_mut(),
+        len: 0,
+        capacity: 0,
+    };
+    dstr_vprintf(&mut message, msg, args);
+    if message.array.is_null() {
+        return;
+    }
+
+    log::log!(target: "libobs", level, "{}", CStr::from_ptr(message.array).to_string_lossy());
+    libobs_sys::bfree(message.array.cast());
+}
@@ -1,37 +1,51 @@
-use std::cell::Cell;
-use std::ffi::CStr;
-use std::os::raw::{c_char, c_int};
-use std::ptr::{null_mut, NonNull};
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeMap;
+use std::path::{Component, Path, PathBuf};
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::OnceLock;
+use std::sync::{Arc, Mutex, OnceLock};
 use std::thread::{self, ThreadId};
-use std::time::Duration;
-
-use crate::settings::{Adapter, AdapterId, AudioSource, Encoder, Framerate, RateControl, RecorderSettings, Resolution};
-use get::Get;
-use obs_data::ObsData;
-
+use std::time::{Duration, SystemTime};
+
+use crate::error::{Error, Result};
+use crate::events::RecorderEvent;
+use crate::marker::{self, Marker};
+use crate::output::{OutputId, OutputKind};
+use crate::settings::{
+    unused_path, Adapter, AdapterId, AudioSource, Container, Encoder, Framerate, PathTemplate, RateControl,
+    RecorderSettings, ReplayBuffer, Resolution, Split, TemplateValues, Window,
+};
+use crate::status::{FrameCounters, Status};
+use backend::{Backend, Data, Libobs, StopHandler, VideoInfo};
+use clock::PauseClock;
+
+pub mod backend;
+mod clock;
 mod get;
-pub(crate) mod obs_data;
+mod jobs;
+mod logging;
+mod obs_data;
+mod remux;
+mod signals;
+mod threaded;
 
-#[cfg(target_os = "windows")]
-const GRAPHICS_MODULE: &str = "libobs-d3d11.dll";
-#[cfg(not(target_os = "windows"))]
-const GRAPHICS_MODULE: &str = "libobs-opengl.dll";
+pub use threaded::ThreadedRecorder;
 
 // default asset paths
 const DEFAULT_LIBOBS_DATA_PATH: &str = "./data/libobs/";
 const DEFAULT_PLUGIN_BIN_PATH: &str = "./obs-plugins/64bit/";
 const DEFAULT_PLUGIN_DATA_PATH: &str = "./data/obs-plugins/%module%/";
-
-// define null terminated libobs object names for ffi
-const OUTPUT: *const i8 = c"output".as_ptr().cast();
-const VIDEO_ENCODER: *const i8 = c"video_encoder".as_ptr().cast();
-const AUDIO_ENCODER: *const i8 = c"audio_encoder".as_ptr().cast();
-const VIDEO_SOURCE: *const i8 = c"video_source".as_ptr().cast();
-const AUDIO_SOURCE1: *const i8 = c"audio_source1".as_ptr().cast();
-const AUDIO_SOURCE2: *const i8 = c"audio_source2".as_ptr().cast();
-const AUDIO_SOURCE3: *const i8 = c"audio_source3".as_ptr().cast();
+const DEFAULT_OUTPUT_PATH: &str = "./recording.mp4";
+
+// libobs object names
+const OUTPUT: &str = "output";
+const HYBRID_OUTPUT: &str = "hybrid_output";
+const REPLAY_BUFFER: &str = "replay_buffer";
+const VIDEO_ENCODER: &str = "video_encoder";
+const AUDIO_ENCODER: &str = "audio_encoder";
+const VIDEO_SOURCE: &str = "video_source";
+const AUDIO_SOURCE1: &str = "audio_source1";
+const AUDIO_SOURCE2: &str = "audio_source2";
+const AUDIO_SOURCE3: &str = "audio_source3";
 
 // libobs output channel assignments
 const VIDEO_CHANNEL: u32 = 0;
@@ -49,19 +63,54 @@ static LIBOBS_SHUTDOWN: AtomicBool = AtomicBool::new(false);
 thread_local! {
     static REF_COUNT: Cell<u32> = const { Cell::new(0) };
     static CURRENT_ENCODER: Cell<Encoder> = const { Cell::new(Encoder::OBS_X264) };
+    static MAIN_RECORDING: RefCell<MainRecording> = RefCell::default();
+}
+
+// the running (or last) recording of the main outputs, which all handles share like the outputs themselves
+#[derive(Default)]
+struct MainRecording {
+    // file it writes to
+    path: String,
+    pause_clock: PauseClock,
+    // shared with its stop handler, which writes them next to the file
+    markers: Arc<Mutex<Vec<Marker>>>,
+}
+
+// what `configure` leaves for `start_recording` to fill in
+#[derive(Clone)]
+struct OutputTemplate {
+    path: PathTemplate,
+    window: Window,
+    // depends on the output path
+    split: Option<Split>,
+    remux_on_stop: bool,
 }
 
 type PhantomUnsync = std::marker::PhantomData<Cell<()>>;
 type PhantomUnsend = std::marker::PhantomData<*mut ()>;
 
-pub struct InpRecorder {
-    output: NonNull<libobs_sys::obs_output>,
-    video_encoder: Cell<NonNull<libobs_sys::obs_encoder>>,
-    audio_encoder: NonNull<libobs_sys::obs_encoder>,
-    video_source: NonNull<libobs_sys::obs_source>,
-    audio_source1: NonNull<libobs_sys::obs_source>,
-    audio_source2: NonNull<libobs_sys::obs_source>,
-    audio_source3: NonNull<libobs_sys::obs_source>,
+pub struct InpRecorder<B: Backend = Libobs> {
+    backend: B,
+    // writes every container except hybrid MP4
+    output: B::Output,
+    // writes hybrid MP4, `None` if libobs does not support it
+    hybrid_output: Option<B::Output>,
+    // outputs added with `add_output`, they belong to this handle and are released with it
+    outputs: BTreeMap<OutputId, B::Output>,
+    // one of `outputs`, created by the first `start_replay_buffer`
+    replay_buffer: Option<OutputId>,
+    // from the last `configure`, `None` if the settings had no replay buffer
+    replay_buffer_settings: Cell<Option<ReplayBuffer>>,
+    // of the main output, from the last `configure`
+    container: Cell<Container>,
+    // `None` until the first `configure`
+    output_template: RefCell<Option<OutputTemplate>>,
+    video_encoder: Cell<B::Encoder>,
+    audio_encoder: B::Encoder,
+    video_source: B::Source,
+    audio_source1: B::Source,
+    audio_source2: B::Source,
+    audio_source3: B::Source,
 
     _phantom: std::marker::PhantomData<(PhantomUnsend, PhantomUnsync)>,
 }
@@ -78,454 +127,722 @@ impl InpRecorder {
         libobs_data_path: Option<&str>,
         plugin_bin_path: Option<&str>,
         plugin_data_path: Option<&str>,
-    ) -> Result<(), &'static str> {
+    ) -> Result<()> {
         // libobs currently cant be reinitialized after being shutdown
         // I assume this is a limitation of libobs
         if LIBOBS_SHUTDOWN.load(Ordering::Acquire) {
-            return Err("libobs has already been shut down");
+            return Err(Error::AlreadyShutDown);
         }
 
         if LIBOBS_THREAD.get().is_some() {
-            return Err("libobs has already been initialized");
+            return Err(Error::AlreadyInitialized);
         }
 
         LIBOBS_THREAD.get_or_init(|| {
-            if let Err(e) = Self::init_internal(libobs_data_path, plugin_bin_path, plugin_data_path) {
-                println!("Error initializing libobs: {e}");
+            if let Err(e) = Self::init_internal(&Libobs, libobs_data_path, plugin_bin_path, plugin_data_path) {
+                log::error!("Error initializing libobs: {e}");
                 panic!("Error initializing libobs: {e}");
             }
 
             thread::current().id()
         });
 
-        println!("libobs {} initialized", libobs_sys::VERSION);
+        log::info!("libobs {} initialized", libobs_sys::VERSION);
 
         Ok(())
     }
 
+    pub fn get_handle() -> Result<Self> {
+        Self::check_thread_initialized()?;
+        Self::from_backend(Libobs)
+    }
+
+    pub fn shutdown() -> Result<()> {
+        Self::check_thread_initialized()?;
+
+        if LIBOBS_SHUTDOWN.load(Ordering::Acquire) {
+            return Ok(());
+        }
+
+        if REF_COUNT.get() > 0 {
+            return Err(Error::RecorderInUse);
+        }
+
+        Libobs.shutdown();
+        LIBOBS_SHUTDOWN.store(true, Ordering::Release);
+
+        Ok(())
+    }
+
+    fn check_thread_initialized() -> Result<()> {
+        match LIBOBS_THREAD.get() {
+            Some(thread_id) if thread_id == &thread::current().id() => Ok(()),
+            Some(_) => Err(Error::WrongThread),
+            None => Err(Error::NotInitialized),
+        }
+    }
+}
+
+#[cfg(feature = "fake")]
+impl InpRecorder<backend::FakeBackend> {
+    /// Runs the initialization sequence against a fake libobs and returns a handle to it.
+    ///
+    /// Unlike the real libobs a fake is not a singleton, so this is not tied to a thread and can be called any number
+    /// of times.
+    pub fn fake(backend: &backend::FakeBackend) -> Result<Self> {
+        Self::init_internal(backend, None, None, None)?;
+        Self::from_backend(backend.clone())
+    }
+    /// Another handle to a fake that has already been initialized with `fake`, like `get_handle`.
+    pub fn fake_handle(backend: &backend::FakeBackend) -> Result<Self> {
+        Self::from_backend(backend.clone())
+    }
+}
+
+impl<B: Backend> InpRecorder<B> {
     fn init_internal(
+        backend: &B,
         libobs_data_path: Option<&str>,
         plugin_bin_path: Option<&str>,
         plugin_data_path: Option<&str>,
-    ) -> Result<(), &'static str> {
+    ) -> Result<()> {
         // set defaults in case no arguments were provided
         let libobs_data_path = libobs_data_path.unwrap_or(DEFAULT_LIBOBS_DATA_PATH);
         let plugin_bin_path = plugin_bin_path.unwrap_or(DEFAULT_PLUGIN_BIN_PATH);
         let plugin_data_path = plugin_data_path.unwrap_or(DEFAULT_PLUGIN_DATA_PATH);
 
         // INITIALIZE
-        let mut get = Get::new();
-
-        if unsafe { !libobs_sys::obs_startup(get.c_str("en-US"), null_mut(), null_mut()) } {
-            return Err("libobs startup failed");
+        if !backend.startup("en-US") {
+            return Err(Error::StartupFailed);
         }
 
         let default_fps = Framerate::new(30, 1);
         let default_size = Resolution::new(1920, 1080);
-        unsafe { libobs_sys::obs_add_data_path(get.c_str(libobs_data_path)) };
-        Self::reset_video(default_size, default_size, default_fps).expect("unable to initialize video");
-        Self::reset_audio().expect("unable to initialize audio");
-
-        unsafe {
-            libobs_sys::obs_add_module_path(get.c_str(plugin_bin_path), get.c_str(plugin_data_path));
-            libobs_sys::obs_load_all_modules();
-            libobs_sys::obs_post_load_modules();
-            libobs_sys::obs_log_loaded_modules();
-        }
-
-        // CREATE OUTPUT
-        let mut data = ObsData::new();
-        data.set_string("path", "./recording.mp4");
-        let output =
-            unsafe { libobs_sys::obs_output_create(get.c_str("ffmpeg_muxer"), OUTPUT, data.as_ptr(), null_mut()) };
+        backend.add_data_path(libobs_data_path);
+        Self::reset_video(backend, default_size, default_size, default_fps).expect("unable to initialize video");
+        Self::reset_audio(backend).expect("unable to initialize audio");
+
+        backend.load_modules(plugin_bin_path, plugin_data_path);
+
+        // CREATE OUTPUTS
+        let mut data = Data::new();
+        data.set_string("path", DEFAULT_OUTPUT_PATH);
+        let output = backend
+            .output_create("ffmpeg_muxer", OUTPUT, &data)
+            .ok_or_else(|| Error::MissingObject("output".into()))?;
+        // the type of an output can't be changed, so hybrid MP4 needs an output of its own
+        let hybrid = Container::HYBRID_MP4.output_type();
+        let hybrid_output = if backend.output_types().iter().any(|id| id == hybrid) {
+            backend.output_create(hybrid, HYBRID_OUTPUT, &data)
+        } else {
+            None
+        };
+        let main_outputs: Vec<B::Output> = std::iter::once(output).chain(hybrid_output).collect();
 
         // choose 'best' encoder
-        let encoders = Self::get_available_encoders_internal();
+        let encoders = Self::get_available_encoders_internal(backend);
         if encoders.is_empty() {
-            return Err("no encoder available");
+            return Err(Error::NoEncoderAvailable);
         }
         let current_encoder = *encoders.first().unwrap();
         Self::set_current_encoder(current_encoder);
 
         // CREATE VIDEO ENCODER
-        let mut get = Get::new();
-        let data: ObsData = current_encoder.settings(RateControl::default());
-        let video_encoder = unsafe {
-            libobs_sys::obs_video_encoder_create(
-                get.c_str(current_encoder.id()),
-                VIDEO_ENCODER,
-                data.as_ptr(),
-                null_mut(),
-            )
-        };
-        unsafe {
-            libobs_sys::obs_encoder_set_video(video_encoder, libobs_sys::obs_get_video());
-            libobs_sys::obs_output_set_video_encoder(output, video_encoder);
+        let data = current_encoder.settings(RateControl::default());
+        let video_encoder = backend
+            .video_encoder_create(current_encoder.id(), VIDEO_ENCODER, &data)
+            .ok_or(Error::EncoderCreationFailed(current_encoder))?;
+        backend.encoder_set_video(video_encoder);
+        for &output in &main_outputs {
+            backend.output_set_video_encoder(output, video_encoder);
         }
 
         // CREATE VIDEO SOURCE
-        let mut data = ObsData::new();
+        let mut data = Data::new();
         data.set_string("capture_mode", "window");
         data.set_string("window", "");
         data.set_bool("capture_cursor", true);
-        let video_source = unsafe {
-            libobs_sys::obs_source_create(
-                get.c_str("game_capture"),
-                VIDEO_SOURCE,
-                data.as_ptr(),
-                std::ptr::null_mut(),
-            )
-        };
-        unsafe { libobs_sys::obs_set_output_source(VIDEO_CHANNEL, video_source) };
+        let video_source = backend
+            .source_create("game_capture", VIDEO_SOURCE, &data)
+            .ok_or_else(|| Error::MissingObject("video source".into()))?;
+        backend.set_output_source(VIDEO_CHANNEL, Some(video_source));
+        backend.connect_signals(&main_outputs, video_source);
 
         // CREATE AUDIO ENCODER
-        let mut data = ObsData::new();
+        let mut data = Data::new();
         data.set_int("bitrate", 160);
-        let audio_encoder = unsafe {
-            libobs_sys::obs_audio_encoder_create(get.c_str("ffmpeg_aac"), AUDIO_ENCODER, data.as_ptr(), 0, null_mut())
-        };
-        unsafe {
-            libobs_sys::obs_encoder_set_audio(audio_encoder, libobs_sys::obs_get_audio());
-            libobs_sys::obs_output_set_audio_encoder(
-                output,
-                audio_encoder,
-                0, // ignored since we only have 1 output
-            );
+        let audio_encoder = backend
+            .audio_encoder_create("ffmpeg_aac", AUDIO_ENCODER, &data)
+            .ok_or_else(|| Error::MissingObject("audio encoder".into()))?;
+        backend.encoder_set_audio(audio_encoder);
+        for &output in &main_outputs {
+            backend.output_set_audio_encoder(output, audio_encoder);
         }
 
         // CREATE AUDIO SOURCE 1
-        unsafe {
-            libobs_sys::obs_source_create(
-                get.c_str("wasapi_process_output_capture"),
-                AUDIO_SOURCE1,
-                null_mut(),
-                null_mut(),
-            )
-        };
+        backend.source_create("wasapi_process_output_capture", AUDIO_SOURCE1, &Data::new());
 
         // CREATE AUDIO SOURCE 2
-        let mut data = ObsData::new();
+        let mut data = Data::new();
         data.set_string("device_id", "default");
-        let audio_source2 = unsafe {
-            libobs_sys::obs_source_create(
-                get.c_str("wasapi_output_capture"),
-                AUDIO_SOURCE2,
-                data.as_ptr(),
-                null_mut(),
-            )
-        };
-        unsafe { libobs_sys::obs_set_output_source(AUDIO_CHANNEL2, audio_source2) };
+        let audio_source2 = backend.source_create("wasapi_output_capture", AUDIO_SOURCE2, &data);
+        backend.set_output_source(AUDIO_CHANNEL2, audio_source2);
 
         // CREATE AUDIO SOURCE 3
-        let mut data = ObsData::new();
+        let mut data = Data::new();
         data.set_string("device_id", "default");
-        unsafe {
-            libobs_sys::obs_source_create(
-                get.c_str("wasapi_input_capture"),
-                AUDIO_SOURCE3,
-                data.as_ptr(),
-                null_mut(),
-            )
+        backend.source_create("wasapi_input_capture", AUDIO_SOURCE3, &data);
+
+        Ok(())
+    }
+
+    fn from_backend(backend: B) -> Result<Self> {
+        let output = backend
+            .output_by_name(OUTPUT)
+            .ok_or_else(|| Error::MissingObject("output".into()))?;
+        let hybrid_output = backend.output_by_name(HYBRID_OUTPUT);
+        let video_encoder = Cell::new(
+            backend
+                .encoder_by_name(VIDEO_ENCODER)
+                .ok_or_else(|| Error::MissingObject("video encoder".into()))?,
+        );
+        let audio_encoder = backend
+            .encoder_by_name(AUDIO_ENCODER)
+            .ok_or_else(|| Error::MissingObject("audio encoder".into()))?;
+        let video_source = backend
+            .source_by_name(VIDEO_SOURCE)
+            .ok_or_else(|| Error::MissingObject("video source".into()))?;
+        let audio_source1 = backend
+            .source_by_name(AUDIO_SOURCE1)
+            .ok_or_else(|| Error::MissingObject("audio source 1".into()))?;
+        let audio_source2 = backend
+            .source_by_name(AUDIO_SOURCE2)
+            .ok_or_else(|| Error::MissingObject("audio source 2".into()))?;
+        let audio_source3 = backend
+            .source_by_name(AUDIO_SOURCE3)
+            .ok_or_else(|| Error::MissingObject("audio source 3".into()))?;
+
+        Self::increment_refcount();
+
+        Ok(Self {
+            backend,
+            output,
+            hybrid_output,
+            outputs: BTreeMap::new(),
+            replay_buffer: None,
+            replay_buffer_settings: Cell::new(None),
+            container: Cell::new(Container::default()),
+            output_template: RefCell::new(None),
+            video_encoder,
+            audio_encoder,
+            video_source,
+            audio_source1,
+            audio_source2,
+            audio_source3,
+            _phantom: std::marker::PhantomData,
+        })
+    }
+
+    fn reset_video(backend: &B, input_size: Resolution, output_size: Resolution, framerate: Framerate) -> Result<()> {
+        let video = VideoInfo {
+            input_resolution: input_size,
+            output_resolution: output_size,
+            framerate,
         };
+        if !backend.reset_video(video) {
+            return Err(Error::VideoResetFailed);
+        }
 
         Ok(())
     }
 
-    pub fn get_handle() -> Result<Self, &'static str> {
-        Self::check_thread_initialized()?;
+    /// only call this function once on startup
+    /// resetting audio after initialisation crashes libobs
+    fn reset_audio(backend: &B) -> Result<()> {
+        if !backend.reset_audio() {
+            return Err(Error::AudioResetFailed);
+        }
+        Ok(())
+    }
 
-        unsafe {
-            let output =
-                NonNull::new(libobs_sys::obs_get_output_by_name(OUTPUT)).ok_or("got nullpointer instead of output")?;
-            let video_encoder = Cell::new(
-                NonNull::new(libobs_sys::obs_get_encoder_by_name(VIDEO_ENCODER))
-                    .ok_or("got nullpointer instead of video encoder")?,
-            );
-            let audio_encoder = NonNull::new(libobs_sys::obs_get_encoder_by_name(AUDIO_ENCODER))
-                .ok_or("got nullpointer instead of audio encoder")?;
-            let video_source = NonNull::new(libobs_sys::obs_get_source_by_name(VIDEO_SOURCE))
-                .ok_or("got nullpointer instead of video source")?;
-            let audio_source1 = NonNull::new(libobs_sys::obs_get_source_by_name(AUDIO_SOURCE1))
-                .ok_or("got nullpointer instead of audio source 1")?;
-            let audio_source2 = NonNull::new(libobs_sys::obs_get_source_by_name(AUDIO_SOURCE2))
-                .ok_or("got nullpointer instead of audio source2")?;
-            let audio_source3 = NonNull::new(libobs_sys::obs_get_source_by_name(AUDIO_SOURCE3))
-                .ok_or("got nullpointer instead of audio source3")?;
-
-            Self::increment_refcount();
-
-            Ok(Self {
-                output,
-                video_encoder,
-                audio_encoder,
-                video_source,
-                audio_source1,
-                audio_source2,
-                audio_source3,
-                _phantom: std::marker::PhantomData,
-            })
-        }
-    }
-
-    pub fn shutdown() -> Result<(), &'static str> {
-        Self::check_thread_initialized()?;
+    fn get_available_encoders_internal(backend: &B) -> Vec<Encoder> {
+        let adapter = Self::get_adapter_internal(backend);
 
-        if LIBOBS_SHUTDOWN.load(Ordering::Acquire) {
+        let mut encoders: Vec<Encoder> = backend
+            .encoder_types()
+            .iter()
+            .filter_map(|id| Encoder::try_from(id.as_str()).ok())
+            .filter(|enc| enc.matches_adapter(&adapter))
+            .collect();
+        encoders.sort();
+        encoders
+    }
+
+    fn get_adapter_internal(backend: &B) -> Adapter {
+        backend
+            .adapters()
+            .into_iter()
+            .find(|e| e.id() == AdapterId::default())
+            .expect("no adapters found?")
+    }
+
+    fn set_current_encoder(encoder: Encoder) {
+        CURRENT_ENCODER.set(encoder);
+    }
+
+    fn get_current_encoder() -> Encoder {
+        CURRENT_ENCODER.with(Cell::get)
+    }
+
+    fn main_recording<T>(f: impl FnOnce(&mut MainRecording) -> T) -> T {
+        MAIN_RECORDING.with_borrow_mut(f)
+    }
+
+    fn increment_refcount() {
+        REF_COUNT.with(|cell| cell.set(cell.get() + 1));
+    }
+
+    fn decrement_refcount() {
+        REF_COUNT.with(|cell| cell.set(cell.get() - 1));
+    }
+}
+
+impl<B: Backend> InpRecorder<B> {
+    /// Starts recording to the output path of the last `configure` and returns the file it records to.
+    ///
+    /// The placeholders of the output path are filled in every time, so the path changes from one recording to the
+    /// next if it contains any or if the file already exists.
+    pub fn start_recording(&mut self) -> Result<String> {
+        log::debug!("Recording Start: {}", self.backend.allocations());
+        if self.is_recording() {
+            Ok(Self::main_recording(|recording| recording.path.clone())) // already recording
+        } else {
+            let output = self.main_output();
+            let path = self.prepare_main_output(output)?;
+            // the stop handler of the last recording keeps its markers
+            let markers = Arc::default();
+            self.backend
+                .output_queue_stop_handler(output, self.stop_handler(&path, &markers));
+            if self.backend.output_start(output) {
+                log::info!("recording to {path}");
+                let recording = MainRecording {
+                    path: path.clone(),
+                    pause_clock: PauseClock::default(),
+                    markers,
+                };
+                Self::main_recording(|current| *current = recording);
+                return Ok(path);
+            }
+
+            self.backend.output_unqueue_stop_handler(output);
+            Err(Error::OutputStartFailed(self.backend.output_last_error(output)))
+        }
+    }
+
+    pub fn stop_recording(&mut self) {
+        self.stop(self.main_output());
+        self.update_pause_clock(PauseClock::resume);
+    }
+
+    /// Pauses the recording without ending the file, nothing gets written until it is resumed.
+    ///
+    /// libobs pauses the encoders, which all outputs share, so this fails with `Error::SharedEncoders` while any
+    /// other output is running.
+    pub fn pause_recording(&mut self) -> Result<()> {
+        if !self.is_recording() {
+            return Err(Error::NotRecording);
+        }
+        if self.is_paused() {
             return Ok(());
         }
+        let output = self.main_output();
+        if !self.backend.output_can_pause(output) {
+            return Err(Error::PauseUnsupported);
+        }
+        if self.outputs.values().any(|&output| self.backend.output_active(output)) {
+            return Err(Error::SharedEncoders);
+        }
 
-        if REF_COUNT.get() > 0 {
-            return Err("libobs can't be shut down due to existing Recorder instances");
+        if !self.backend.output_pause(output, true) {
+            return Err(Error::PauseFailed(self.backend.output_last_error(output)));
         }
+        self.update_pause_clock(PauseClock::pause);
+        log::info!("paused");
+        Ok(())
+    }
 
-        unsafe { libobs_sys::obs_shutdown() };
-        LIBOBS_SHUTDOWN.store(true, Ordering::Release);
+    pub fn resume_recording(&mut self) -> Result<()> {
+        if !self.is_recording() {
+            return Err(Error::NotRecording);
+        }
+        if !self.is_paused() {
+            return Ok(());
+        }
 
+        let output = self.main_output();
+        if !self.backend.output_pause(output, false) {
+            return Err(Error::PauseFailed(self.backend.output_last_error(output)));
+        }
+        self.update_pause_clock(PauseClock::resume);
+        log::info!("resumed");
         Ok(())
     }
 
-    fn get_video_info() -> Result<libobs_sys::obs_video_info, &'static str> {
-        let mut ovi = libobs_sys::obs_video_info::default();
-        if unsafe { libobs_sys::obs_get_video_info(&mut ovi) } {
-            Ok(ovi)
-        } else {
-            Err("Error video was not set! Maybe Recorder was not initialized?")
-        }
-    }
-
-    fn reset_video(input_size: Resolution, output_size: Resolution, framerate: Framerate) -> Result<(), &'static str> {
-        unsafe {
-            let mut get = Get::new();
-            let mut ovi = libobs_sys::obs_video_info {
-                adapter: AdapterId::default(),
-                graphics_module: get.c_str(GRAPHICS_MODULE),
-                fps_num: framerate.num(),
-                fps_den: framerate.den(),
-                base_width: input_size.width(),
-                base_height: input_size.height(),
-                output_width: output_size.width(),
-                output_height: output_size.height(),
-                output_format: libobs_sys::video_format_VIDEO_FORMAT_NV12,
-                gpu_conversion: true,
-                colorspace: libobs_sys::video_colorspace_VIDEO_CS_709,
-                range: libobs_sys::video_range_type_VIDEO_RANGE_DEFAULT,
-                scale_type: libobs_sys::obs_scale_type_OBS_SCALE_LANCZOS,
-            };
-
-            // OBS_VIDEO_SUCCESS is 0, so casting it to c_int should be fine
-            if libobs_sys::obs_reset_video(&mut ovi) != libobs_sys::OBS_VIDEO_SUCCESS as c_int {
-                return Err("error on libobs reset video");
-            }
-        }
+    pub fn is_paused(&self) -> bool {
+        self.backend.output_paused(self.main_output())
+    }
 
+    /// Ends the current file of the recording and continues in a new one, named like those of `set_split`.
+    ///
+    /// The recording has to be configured to split, though both limits may be off to only split on demand.
+    /// `RecorderEvent::SegmentFinished` reports the finished file once the new one has started.
+    pub fn split_recording(&mut self) -> Result<()> {
+        if !self.is_recording() {
+            return Err(Error::NotRecording);
+        }
+        if !self.backend.output_split_file(self.main_output()) {
+            return Err(Error::SplitNotConfigured);
+        }
+        log::info!("split");
         Ok(())
     }
 
-    /// only call this function once on startup
-    /// resetting audio after initialisation crashes libobs
-    fn reset_audio() -> Result<(), String> {
-        let ai = libobs_sys::obs_audio_info {
-            samples_per_sec: 44100,
-            speakers: libobs_sys::speaker_layout_SPEAKERS_STEREO,
+    /// Marks the current moment of the recording with `label`, e.g. a kill.
+    ///
+    /// The marker is timed by the frames the recording has written, so the time stands still while it is paused.
+    /// Once the recording has stopped, also on its own, its markers are written next to it in the background as
+    /// JSON (`game.markers.json` for `game.mkv`), a split recording gets them next to its first file.
+    ///
+    /// Only hybrid MP4 recordings get the markers as chapters as well, their muxer writes them right away. MKV and
+    /// (plain) MP4 recordings get no chapters, neither their muxer nor remuxing them can write any. The sidecar holds
+    /// the running time of the recording, so the chapters can be added later on, e.g. with ffmpeg.
+    pub fn add_marker(&mut self, label: &str) -> Result<Marker> {
+        if !self.is_recording() {
+            return Err(Error::NotRecording);
+        }
+        let output = self.main_output();
+        let marker = Marker {
+            label: label.to_string(),
+            time: self.backend.output_time(output),
         };
-        let ok = unsafe { libobs_sys::obs_reset_audio(&ai) };
-        if !ok {
-            return Err(String::from("error on libobs reset audio"));
+        if !self.backend.output_add_chapter(output, label) {
+            log::debug!("no chapter for marker {label}, the muxer does not support them");
         }
-        Ok(())
+        let markers = Self::main_recording(|recording| Arc::clone(&recording.markers));
+        markers.lock().unwrap_or_else(|e| e.into_inner()).push(marker.clone());
+        Ok(marker)
     }
 
-    fn get_available_encoders_internal() -> Vec<Encoder> {
-        let adapter = Self::get_adapters_internal()
-            .into_iter()
-            .find(|e| e.id() == AdapterId::default())
-            .expect("no adapters found?");
-
-        // GET AVAILABLE ENCODERS
-        let mut n = 0;
-        let mut encoders = Vec::new();
-        let mut ptr: *const c_char = unsafe { std::mem::zeroed() };
-        while unsafe { libobs_sys::obs_enum_encoder_types(n, &mut ptr) } {
-            n += 1;
-            let cstring = unsafe { CStr::from_ptr(ptr) };
-            if let Ok(enc) = cstring.to_str() {
-                let Ok(enc) = Encoder::try_from(enc) else { continue };
-
-                if enc.matches_adapter(&adapter) {
-                    encoders.push(enc);
-                }
-            }
+    /// Copies the streams of `input` into `output` without encoding them again, e.g. to turn an MKV into an MP4.
+    ///
+    /// Blocks until `output` is written, `progress` gets called with the percentage done along the way.
+    /// The directory of `output` is created if it is missing and an existing `output` is overwritten.
+    pub fn remux(&self, input: &str, output: &str, mut progress: impl FnMut(f32)) -> Result<()> {
+        self.check_remux(input, output)?;
+        if !self.backend.remux(input, output, &mut progress) {
+            return Err(Error::RemuxFailed(input.to_string()));
         }
-        encoders.sort();
-        encoders
+        log::info!("remuxed {input} to {output}");
+        Ok(())
     }
 
-    fn get_adapters_internal() -> Vec<Adapter> {
-        let mut adapters: Vec<Adapter> = Vec::new();
+    /// Like `remux`, but in the background.
+    ///
+    /// Returns once remuxing has started, `RecorderEvent::RemuxProgress` and `RecorderEvent::RemuxFinished` report
+    /// how it goes.
+    pub fn start_remux(&self, input: &str, output: &str) -> Result<()> {
+        self.check_remux(input, output)?;
+        self.backend.start_remux(input, output);
+        log::info!("remuxing {input} to {output}");
+        Ok(())
+    }
 
-        unsafe extern "C" fn callback(
-            vec: *mut ::std::os::raw::c_void,
-            name: *const ::std::os::raw::c_char,
-            id: u32,
-        ) -> bool {
-            let adapters = &mut *(vec as *mut Vec<Adapter>);
-            adapters.push(Adapter::new(id, CStr::from_ptr(name).to_string_lossy().to_string()));
+    fn check_remux(&self, input: &str, output: &str) -> Result<()> {
+        if !self.backend.file_exists(input) {
+            return Err(Error::FileNotFound(input.to_string()));
+        }
+        // libobs would truncate the input before reading it
+        if same_file(input, output) {
+            return Err(Error::RemuxSameFile(input.to_string()));
+        }
+        self.create_directory(output)
+    }
+
+    /// What has to happen once the recording to `path` has stopped, whether it was stopped or stopped on its own.
+    fn stop_handler(&self, path: &str, markers: &Arc<Mutex<Vec<Marker>>>) -> StopHandler {
+        let backend = self.backend.clone();
+        let path = path.to_string();
+        let markers = Arc::clone(markers);
+        let remux_on_stop = self.output_template().is_some_and(|template| template.remux_on_stop);
+        Box::new(move |end| {
+            let markers = std::mem::take(&mut *markers.lock().unwrap_or_else(|e| e.into_inner()));
+            save_markers(&backend, &path, &markers, end);
+            if remux_on_stop {
+                remux_to_mp4(&backend, &path);
+            }
+        })
+    }
 
-            true
+    /// Creates another output that shares the encoders and sources of the main output.
+    ///
+    /// The output belongs to this handle and is stopped and released when the handle is dropped.
+    pub fn add_output(&mut self, kind: &OutputKind) -> Result<OutputId> {
+        let mut data = Data::new();
+        match kind {
+            OutputKind::Recording { path } => data.set_string("path", path),
         }
+        let (id, _) = self.create_output(kind.type_id(), &data)?;
+        Ok(id)
+    }
 
-        unsafe {
-            libobs_sys::obs_enter_graphics();
-            libobs_sys::gs_enum_adapters(
-                Some(callback),
-                &mut adapters as *mut Vec<Adapter> as *mut ::std::os::raw::c_void,
-            );
-            libobs_sys::obs_leave_graphics();
+    /// Stops the output if it is running and releases it.
+    pub fn remove_output(&mut self, id: OutputId) -> Result<()> {
+        let output = self.outputs.remove(&id).ok_or(Error::UnknownOutput(id))?;
+        self.release_output(id, output);
+        if self.replay_buffer == Some(id) {
+            self.replay_buffer = None;
         }
+        Ok(())
+    }
 
-        adapters
+    /// All outputs of this handle, starting with `OutputId::MAIN`.
+    pub fn outputs(&self) -> Vec<OutputId> {
+        std::iter::once(OutputId::MAIN)
+            .chain(self.outputs.keys().copied())
+            .collect()
     }
 
-    fn check_thread_initialized() -> Result<(), &'static str> {
-        match LIBOBS_THREAD.get() {
-            Some(thread_id) if thread_id == &thread::current().id() => Ok(()),
-            Some(_) => Err("wrong thread - libobs was initialized in another thread"),
-            None => Err("libos has not been initialized yet"),
+    pub fn start_output(&mut self, id: OutputId) -> Result<()> {
+        let output = self.output(id)?;
+        if self.backend.output_active(output) {
+            return Ok(());
+        }
+        // it would start out with the paused encoders
+        if self.is_paused() {
+            return Err(Error::SharedEncoders);
+        }
+        if self.backend.output_start(output) {
+            return Ok(());
         }
+        Err(Error::OutputStartFailed(self.backend.output_last_error(output)))
     }
 
-    fn set_current_encoder(encoder: Encoder) {
-        CURRENT_ENCODER.set(encoder);
+    pub fn stop_output(&mut self, id: OutputId) -> Result<()> {
+        let output = self.output(id)?;
+        self.stop(output);
+        Ok(())
     }
 
-    fn get_current_encoder() -> Encoder {
-        CURRENT_ENCODER.with(Cell::get)
+    pub fn is_output_active(&self, id: OutputId) -> Result<bool> {
+        Ok(self.backend.output_active(self.output(id)?))
     }
 
-    fn increment_refcount() {
-        REF_COUNT.with(|cell| cell.set(cell.get() + 1));
+    /// Starts keeping the most recent part of the recording in memory, as configured with `set_replay_buffer`.
+    ///
+    /// The replay buffer is an output of its own, so it runs independently of the main output.
+    /// It is created the first time this is called and can be removed like any other output.
+    pub fn start_replay_buffer(&mut self) -> Result<OutputId> {
+        let replay_buffer = self
+            .replay_buffer_settings
+            .get()
+            .ok_or(Error::ReplayBufferNotConfigured)?;
+        // only the directory and the name are used, so there is no need to avoid existing files
+        let path = match self.output_template() {
+            Some(template) => template.path.expand(&Self::template_values(&template.window), 1),
+            None => DEFAULT_OUTPUT_PATH.to_string(),
+        };
+        self.create_directory(&path)?;
+        let data = replay_buffer.settings(&path);
+
+        let (id, output) = match self.replay_buffer.map(|id| (id, self.outputs[&id])) {
+            Some((id, output)) if self.backend.output_active(output) => return Ok(id),
+            Some((id, output)) => {
+                self.backend.output_update(output, &data);
+                (id, output)
+            }
+            None => {
+                let (id, output) = self.create_output(REPLAY_BUFFER, &data)?;
+                self.backend.connect_replay_signals(output);
+                self.replay_buffer = Some(id);
+                (id, output)
+            }
+        };
+
+        if self.is_paused() {
+            return Err(Error::SharedEncoders);
+        }
+        if self.backend.output_start(output) {
+            return Ok(id);
+        }
+        Err(Error::OutputStartFailed(self.backend.output_last_error(output)))
     }
 
-    fn decrement_refcount() {
-        REF_COUNT.with(|cell| cell.set(cell.get() - 1));
+    /// Writes what the replay buffer holds to a file.
+    ///
+    /// Saving happens in the background, `RecorderEvent::ReplaySaved` reports the path once the file is complete.
+    pub fn save_replay(&self) -> Result<()> {
+        let output = match self.replay_buffer.map(|id| self.outputs[&id]) {
+            Some(output) if self.backend.output_active(output) => output,
+            _ => return Err(Error::ReplayBufferNotActive),
+        };
+        if !self.backend.replay_buffer_save(output) {
+            return Err(Error::ReplaySaveFailed);
+        }
+        Ok(())
     }
-}
 
-impl InpRecorder {
-    pub fn start_recording(&mut self) -> Result<(), String> {
-        println!("Recording Start: {}", unsafe { libobs_sys::bnum_allocs() });
-        if self.is_recording() {
-            Ok(()) // already recording
-        } else {
-            if unsafe { libobs_sys::obs_output_start(self.output.as_ptr()) } {
-                return Ok(());
-            }
+    /// Stops the replay buffer and discards what it holds.
+    pub fn stop_replay_buffer(&mut self) {
+        if let Some(id) = self.replay_buffer {
+            self.stop(self.outputs[&id]);
+        }
+    }
+
+    fn create_output(&mut self, type_id: &str, data: &Data) -> Result<(OutputId, B::Output)> {
+        let id = OutputId::next();
+        let output = self
+            .backend
+            .output_create(type_id, &id.object_name(), data)
+            .ok_or(Error::OutputCreationFailed)?;
+        self.backend.output_set_video_encoder(output, self.video_encoder.get());
+        self.backend.output_set_audio_encoder(output, self.audio_encoder);
+        self.backend.connect_output_signals(output, id);
+
+        self.outputs.insert(id, output);
+        log::info!("added {id}");
+        Ok((id, output))
+    }
 
-            let error = unsafe {
-                let err = libobs_sys::obs_output_get_last_error(self.output.as_ptr());
-                if err.is_null() {
-                    c"no error message"
-                } else {
-                    CStr::from_ptr(err)
-                }
-            };
-            Err(error.to_str().unwrap_or("error message is invalid UTF-8").to_string())
+    fn output(&self, id: OutputId) -> Result<B::Output> {
+        match id {
+            OutputId::MAIN => Ok(self.main_output()),
+            id => self.outputs.get(&id).copied().ok_or(Error::UnknownOutput(id)),
         }
     }
 
-    pub fn stop_recording(&mut self) {
-        if self.is_recording() {
-            unsafe { libobs_sys::obs_output_stop(self.output.as_ptr()) }
-            println!("Recording Stop: {}", unsafe { libobs_sys::bnum_allocs() });
+    /// Stops `output` and waits for libobs to finish it, force stopping it after 3 seconds.
+    fn stop(&self, output: B::Output) {
+        if self.backend.output_active(output) {
+            self.backend.output_stop(output);
+            log::debug!("Recording Stop: {}", self.backend.allocations());
         }
 
         let now = std::time::Instant::now();
         loop {
             thread::sleep(Duration::from_millis(100));
-            if !self.is_recording() {
+            if !self.backend.output_active(output) {
                 return;
             } else if now.elapsed().as_millis() > 3000 {
-                unsafe { libobs_sys::obs_output_force_stop(self.output.as_ptr()) };
+                self.backend.output_force_stop(output);
                 return;
             }
         }
     }
 
-    pub fn configure(&self, settings: &RecorderSettings) -> Result<(), &'static str> {
-        if self.is_recording() {
-            return Err("can't change settings while recording");
+    fn release_output(&self, id: OutputId, output: B::Output) {
+        if self.backend.output_active(output) {
+            self.stop(output);
+        }
+        if self.replay_buffer == Some(id) {
+            self.backend.disconnect_replay_signals(output);
+        }
+        self.backend.disconnect_output_signals(output, id);
+        self.backend.output_release(output);
+        log::info!("removed {id}");
+    }
+
+    pub fn configure(&self, settings: &RecorderSettings) -> Result<()> {
+        // all outputs share the encoders
+        if self.all_outputs().any(|output| self.backend.output_active(output)) {
+            return Err(Error::Recording);
         }
 
         // set adapter, input_resolution, output_resolution, framerate
-        let ovi = Self::get_video_info()?;
+        let video = self.backend.video_info().ok_or(Error::VideoNotSet)?;
 
         let framerate = settings.framerate.unwrap_or(Framerate::new(30, 1));
 
-        let video_reset_necessary = settings.input_resolution.width() != ovi.base_width
-            || settings.input_resolution.height() != ovi.base_height
-            || settings.output_resolution.width() != ovi.output_width
-            || settings.output_resolution.height() != ovi.output_height
-            || framerate.num() != ovi.fps_num
-            || framerate.den() != ovi.fps_den;
+        let video_reset_necessary = settings.input_resolution != video.input_resolution
+            || settings.output_resolution != video.output_resolution
+            || framerate.num() != video.framerate.num()
+            || framerate.den() != video.framerate.den();
         if video_reset_necessary {
-            Self::reset_video(settings.input_resolution, settings.output_resolution, framerate)?;
-
-            unsafe {
-                // reconfigure video output pipeline after resetting the video backend
-                libobs_sys::obs_encoder_set_video(self.video_encoder.get().as_ptr(), libobs_sys::obs_get_video());
-                libobs_sys::obs_output_set_video_encoder(self.output.as_ptr(), self.video_encoder.get().as_ptr());
-                libobs_sys::obs_set_output_source(VIDEO_CHANNEL, self.video_source.as_ptr());
+            Self::reset_video(
+                &self.backend,
+                settings.input_resolution,
+                settings.output_resolution,
+                framerate,
+            )?;
+
+            // reconfigure video output pipeline after resetting the video backend
+            self.backend.encoder_set_video(self.video_encoder.get());
+            for output in self.all_outputs() {
+                self.backend.output_set_video_encoder(output, self.video_encoder.get());
             }
+            self.backend.set_output_source(VIDEO_CHANNEL, Some(self.video_source));
         }
 
-        let available_encoders = Self::get_available_encoders_internal();
+        let available_encoders = Self::get_available_encoders_internal(&self.backend);
         if let Some(encoder) = settings.encoder {
             // check if the given encoder is available on the current adapter
             if !available_encoders.contains(&encoder) {
-                return Err("encoder not available");
+                return Err(Error::EncoderUnavailable(encoder));
             }
         }
 
-        // if no encoder was explicitly set, choose an available encoder
+        // if no encoder was explicitly set, choose an available encoder the container can store
+        let container = settings.container.unwrap_or_default();
         let encoder = match settings.encoder {
             Some(encoder) => encoder,
-            None => *available_encoders.first().ok_or("no encoders available")?,
+            None => *available_encoders
+                .iter()
+                .find(|encoder| container.supports(encoder.codec()))
+                .ok_or(Error::NoEncoderAvailable)?,
         };
+        container.check_encoder(encoder)?;
+        if container == Container::HYBRID_MP4 && self.hybrid_output.is_none() {
+            return Err(Error::ContainerUnavailable(container));
+        }
+        let output_path = container.fix_extension(&settings.output_path)?;
+        let output_path = PathTemplate::parse(&output_path)?;
+        if settings.remux_on_stop && settings.split.is_some() {
+            return Err(Error::RemuxSplitRecording);
+        }
 
-        let mut get = Get::new();
+        // set container, the output path and splitting are set up when the recording starts
+        self.container.set(container);
+        self.output_template.replace(Some(OutputTemplate {
+            path: output_path,
+            window: settings.window.clone(),
+            split: settings.split.clone(),
+            remux_on_stop: settings.remux_on_stop,
+        }));
 
-        // set output_path
-        let mut data = ObsData::new();
-        data.set_string("path", &settings.output_path);
-        unsafe { libobs_sys::obs_output_update(self.output.as_ptr(), data.as_ptr()) };
+        // the replay buffer picks up its settings when it gets started
+        self.replay_buffer_settings.set(settings.replay_buffer);
 
         // set video encoder
         Self::set_current_encoder(encoder);
 
         let data = encoder.settings(settings.rate_control.unwrap_or_default());
-        let new_video_encoder = NonNull::new(unsafe {
-            libobs_sys::obs_video_encoder_create(
-                get.c_str(encoder.id()),
-                get.c_str("video_encoder"),
-                data.as_ptr(),
-                null_mut(),
-            )
-        })
-        .ok_or("unable to create video encoder")?;
-
-        unsafe {
-            libobs_sys::obs_encoder_set_video(new_video_encoder.as_ptr(), libobs_sys::obs_get_video());
-            libobs_sys::obs_output_set_video_encoder(self.output.as_ptr(), new_video_encoder.as_ptr());
+        let new_video_encoder = self
+            .backend
+            .video_encoder_create(encoder.id(), VIDEO_ENCODER, &data)
+            .ok_or(Error::EncoderCreationFailed(encoder))?;
+
+        self.backend.encoder_set_video(new_video_encoder);
+        for output in self.all_outputs() {
+            self.backend.output_set_video_encoder(output, new_video_encoder);
         }
 
         // replace and release old encoder
         let old_encoder = self.video_encoder.replace(new_video_encoder);
-        unsafe { libobs_sys::obs_encoder_release(old_encoder.as_ptr()) };
+        self.backend.encoder_release(old_encoder);
 
         // set video source (window)
-        let mut data = ObsData::new();
+        let mut data = Data::new();
         data.set_string("window", settings.window.get_libobs_window_id());
-        unsafe { libobs_sys::obs_source_update(self.video_source.as_ptr(), data.as_ptr()) };
+        self.backend.source_update(self.video_source, &data);
 
         // set audio sources
         let audio_setting = settings.audio_source.unwrap_or(AudioSource::APPLICATION);
@@ -533,76 +850,225 @@ impl InpRecorder {
         // audio source 1
         let audio_source1 = match audio_setting {
             AudioSource::APPLICATION => {
-                let mut data = ObsData::new();
+                let mut data = Data::new();
                 data.set_string("window", settings.window.get_libobs_window_id());
-                unsafe { libobs_sys::obs_source_update(self.audio_source1.as_ptr(), data.as_ptr()) };
+                self.backend.source_update(self.audio_source1, &data);
 
-                self.audio_source1.as_ptr()
+                Some(self.audio_source1)
             }
-            _ => null_mut(),
+            _ => None,
         };
-        unsafe { libobs_sys::obs_set_output_source(AUDIO_CHANNEL1, audio_source1) };
+        self.backend.set_output_source(AUDIO_CHANNEL1, audio_source1);
 
         // audio source 2
         let audio_source2 = match audio_setting {
-            AudioSource::SYSTEM | AudioSource::ALL => self.audio_source2.as_ptr(),
-            _ => null_mut(),
+            AudioSource::SYSTEM | AudioSource::ALL => Some(self.audio_source2),
+            _ => None,
         };
-        unsafe { libobs_sys::obs_set_output_source(AUDIO_CHANNEL2, audio_source2) };
+        self.backend.set_output_source(AUDIO_CHANNEL2, audio_source2);
 
         // audio source 3
         let audio_source3 = match audio_setting {
-            AudioSource::ALL => self.audio_source3.as_ptr(),
-            _ => null_mut(),
+            AudioSource::ALL => Some(self.audio_source3),
+            _ => None,
         };
-        unsafe { libobs_sys::obs_set_output_source(AUDIO_CHANNEL3, audio_source3) };
+        self.backend.set_output_source(AUDIO_CHANNEL3, audio_source3);
 
-        println!("configured");
+        log::info!("configured");
 
         Ok(())
     }
 
+    /// Registers a handler that gets called for every `RecorderEvent` (replaces the previous one).
+    ///
+    /// The handler gets called from libobs threads, so it should return quickly.
+    pub fn set_event_handler(&mut self, handler: impl Fn(RecorderEvent) + Send + 'static) {
+        self.backend.set_event_handler(Some(Box::new(handler)));
+    }
+
     pub fn is_recording(&self) -> bool {
-        unsafe { libobs_sys::obs_output_active(self.output.as_ptr()) }
+        self.backend.output_active(self.main_output())
+    }
+
+    #[must_use]
+    pub fn status(&self) -> Status {
+        let output = self.main_output();
+        let recording = self.is_recording();
+        if !recording {
+            // the output may have stopped on its own while paused
+            self.update_pause_clock(PauseClock::resume);
+        }
+
+        Status {
+            recording,
+            paused: recording && self.is_paused(),
+            paused_duration: Self::main_recording(|recording| recording.pause_clock.paused()),
+            frames: FrameCounters {
+                rendered: self.backend.total_frames(),
+                lagged: self.backend.lagged_frames(),
+                output: self.backend.output_total_frames(output),
+                dropped: self.backend.output_frames_dropped(output),
+            },
+        }
     }
 
     pub fn get_adapter_info(&self) -> Adapter {
         // public version of internal function that is only available after libobs is initialized
         // due to requiring &self
-        Self::get_adapters_internal()
-            .into_iter()
-            .find(|e| e.id() == AdapterId::default())
-            .expect("no adapters found?")
+        Self::get_adapter_internal(&self.backend)
     }
 
     pub fn get_available_encoders(&self) -> Vec<Encoder> {
         // public version of internal function that is only available after libobs is initialized
         // due to requiring &self
-        Self::get_available_encoders_internal()
+        Self::get_available_encoders_internal(&self.backend)
     }
 
     // re-export function as only available through a reference to a Recorder
     pub fn selected_encoder(&self) -> Encoder {
         Self::get_current_encoder()
     }
+
+    /// Fills in the output path of the last `configure` and points the main output at the resulting file.
+    fn prepare_main_output(&self, output: B::Output) -> Result<String> {
+        let Some(template) = self.output_template() else {
+            // not configured yet, so the output still has the path it was created with
+            return Ok(DEFAULT_OUTPUT_PATH.to_string());
+        };
+        let path = template
+            .path
+            .resolve(&Self::template_values(&template.window), |path| {
+                self.backend.file_exists(path)
+            })?;
+        self.create_directory(&path)?;
+
+        let mut data = self.container.get().settings(&path);
+        match &template.split {
+            Some(split) => data.apply(&split.settings(&path)),
+            None => data.set_bool("split_file", false),
+        }
+        self.backend.output_update(output, &data);
+        Ok(path)
+    }
+
+    fn output_template(&self) -> Option<OutputTemplate> {
+        self.output_template.borrow().clone()
+    }
+
+    fn template_values(window: &Window) -> TemplateValues<'_> {
+        TemplateValues {
+            time: SystemTime::now(),
+            window,
+            encoder: Self::get_current_encoder(),
+        }
+    }
+
+    /// Creates the directory `path` is in.
+    fn create_directory(&self, path: &str) -> Result<()> {
+        match Path::new(path).parent().and_then(Path::to_str) {
+            Some(directory) if !directory.is_empty() && !self.backend.create_directory(directory) => {
+                Err(Error::DirectoryCreationFailed(directory.to_string()))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn update_pause_clock(&self, update: impl FnOnce(&mut PauseClock)) {
+        Self::main_recording(|recording| update(&mut recording.pause_clock));
+    }
+
+    /// The main output that is running, otherwise the one that writes the container of the last `configure`.
+    ///
+    /// All handles share the main outputs, so another handle may have started the one of a different container.
+    fn main_output(&self) -> B::Output {
+        let configured = match self.container.get() {
+            Container::HYBRID_MP4 => self.hybrid_output.unwrap_or(self.output),
+            _ => self.output,
+        };
+        std::iter::once(self.output)
+            .chain(self.hybrid_output)
+            .find(|&output| self.backend.output_active(output))
+            .unwrap_or(configured)
+    }
+
+    fn all_outputs(&self) -> impl Iterator<Item = B::Output> + '_ {
+        std::iter::once(self.output)
+            .chain(self.hybrid_output)
+            .chain(self.outputs.values().copied())
+    }
+}
+
+/// Whether both paths lead to the same file, even if they are written differently (e.g. `./game.mkv` and `game.mkv`).
+fn same_file(a: &str, b: &str) -> bool {
+    fn normalize(path: &str) -> PathBuf {
+        let path = Path::new(path);
+        // the file may not exist yet, but its directory usually does
+        let directory = match path.parent() {
+            Some(directory) if !directory.as_os_str().is_empty() => directory,
+            _ => Path::new("."),
+        };
+        match (std::fs::canonicalize(directory), path.file_name()) {
+            (Ok(directory), Some(name)) => directory.join(name),
+            _ => path.components().filter(|c| *c != Component::CurDir).collect(),
+        }
+    }
+    normalize(a) == normalize(b)
 }
 
-impl Drop for InpRecorder {
+/// Writes the markers of the recording to `path` next to it, `end` is the time the recording ended at.
+fn save_markers<B: Backend>(backend: &B, path: &str, markers: &[Marker], end: Duration) {
+    if markers.is_empty() {
+        return;
+    }
+    let sidecar_path = marker::sidecar_path(path);
+    match marker::sidecar(path, end, markers) {
+        Ok(sidecar) if backend.write_file(&sidecar_path, &sidecar) => {
+            log::info!("saved {} markers to {sidecar_path}", markers.len());
+        }
+        Ok(_) => {}
+        Err(e) => log::error!("unable to save the markers of {path}: {e}"),
+    }
+}
+
+/// Starts remuxing the file of a recording into an MP4 next to it, unless it already is one.
+fn remux_to_mp4<B: Backend>(backend: &B, input: &str) {
+    let path = Path::new(input);
+    if path
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case(Container::MP4.extension()))
+    {
+        return;
+    }
+    let output = path.with_extension(Container::MP4.extension());
+    match unused_path(&output.to_string_lossy(), |path| backend.file_exists(path)) {
+        Ok(output) => {
+            backend.start_remux(input, &output);
+            log::info!("remuxing {input} to {output}");
+        }
+        Err(e) => log::error!("unable to remux {input}: {e}"),
+    }
+}
+
+impl<B: Backend> Drop for InpRecorder<B> {
     fn drop(&mut self) {
-        unsafe {
-            // output
-            libobs_sys::obs_output_release(self.output.as_ptr());
-            // video
-            libobs_sys::obs_encoder_release(self.video_encoder.get().as_ptr());
-            libobs_sys::obs_source_release(self.video_source.as_ptr());
-            // audio
-            libobs_sys::obs_encoder_release(self.audio_encoder.as_ptr());
-            libobs_sys::obs_source_release(self.audio_source1.as_ptr());
-            libobs_sys::obs_source_release(self.audio_source2.as_ptr());
-            libobs_sys::obs_source_release(self.audio_source3.as_ptr());
-
-            println!("drop bnum_allocs: {}", libobs_sys::bnum_allocs());
+        // outputs
+        for (id, output) in std::mem::take(&mut self.outputs) {
+            self.release_output(id, output);
+        }
+        self.backend.output_release(self.output);
+        if let Some(hybrid_output) = self.hybrid_output {
+            self.backend.output_release(hybrid_output);
         }
+        // video
+        self.backend.encoder_release(self.video_encoder.get());
+        self.backend.source_release(self.video_source);
+        // audio
+        self.backend.encoder_release(self.audio_encoder);
+        self.backend.source_release(self.audio_source1);
+        self.backend.source_release(self.audio_source2);
+        self.backend.source_release(self.audio_source3);
+
+        log::debug!("drop bnum_allocs: {}", self.backend.allocations());
 
         Self::decrement_refcount();
     }
@@ -1,5 +1,6 @@
 use std::ffi::CString;
 
+use super::backend::{Data, Value};
 use libobs_sys::{
     obs_data, obs_data_create, obs_data_release, obs_data_set_bool, obs_data_set_int, obs_data_set_string,
 };
@@ -55,3 +56,17 @@ impl Drop for ObsData {
         unsafe { obs_data_release(self.obs_data) };
     }
 }
+
+impl From<&Data> for ObsData {
+    fn from(settings: &Data) -> Self {
+        let mut data = Self::new();
+        for (name, value) in settings.iter() {
+            match value {
+                Value::String(value) => data.set_string(name, value),
+                Value::Int(value) => data.set_int(name, *value),
+                Value::Bool(value) => data.set_bool(name, *value),
+            }
+        }
+        data
+    }
+}
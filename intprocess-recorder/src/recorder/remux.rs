@@ -0,0 +1,52 @@
+use std::ffi::c_void;
+use std::ptr::null_mut;
+
+use super::backend::report_remux;
+use super::get::Get;
+use super::{jobs, signals};
+
+type Progress<'a> = &'a mut dyn FnMut(f32);
+
+/// Remuxes `input` into `output` with libobs, `progress` gets called with the percentage done.
+///
+/// Only uses ffmpeg, so unlike everything else this does not have to happen on the libobs thread.
+pub(crate) fn remux(input: &str, output: &str, mut progress: Progress) -> bool {
+    let mut get = Get::new();
+    let mut job = null_mut();
+    unsafe {
+        // the job is destroyed by libobs if it can't be created
+        if !libobs_sys::media_remux_job_create(&mut job, get.c_str(input), get.c_str(output)) {
+            return false;
+        }
+        // the callback only gets called while processing, so the progress outlives it
+        let data = (&mut progress as *mut Progress).cast();
+        let success = libobs_sys::media_remux_job_process(job, Some(remux_progress), data);
+        libobs_sys::media_remux_job_destroy(job);
+        // an interrupted remux leaves an incomplete file
+        success && !jobs::cancelled()
+    }
+}
+
+/// Remuxes on a thread of its own and reports how it goes with `RecorderEvent::RemuxProgress` and
+/// `RecorderEvent::RemuxFinished`.
+pub(crate) fn spawn(input: String, output: String) {
+    let (job_input, job_output) = (input.clone(), output.clone());
+    let spawned = jobs::spawn("remux", move || {
+        report_remux(
+            &job_input,
+            &job_output,
+            |progress| remux(&job_input, &job_output, progress),
+            signals::emit,
+        );
+    });
+    if !spawned {
+        report_remux(&input, &output, |_| false, signals::emit);
+    }
+}
+
+unsafe extern "C" fn remux_progress(data: *mut c_void, percent: f32) -> bool {
+    let progress = &mut *data.cast::<Progress>();
+    progress(percent);
+    // returning false stops the remux
+    !jobs::cancelled()
+}
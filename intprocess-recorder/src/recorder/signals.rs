@@ -0,0 +1,359 @@
+use std::ffi::{c_char, c_void, CStr};
+use std::mem::size_of;
+use std::ptr::{null, null_mut, NonNull};
+use std::sync::atomic::{AtomicPtr, Ordering};
+use std::sync::{Mutex, MutexGuard};
+
+use libobs_sys::{calldata_t, obs_output, obs_source};
+
+use super::backend::{Backend, EventHandler, Libobs, StopHandler};
+use super::get::Get;
+use super::jobs;
+use crate::events::{RecorderEvent, StopReason};
+use crate::output::{self, OutputId};
+
+// signal callbacks get called from libobs threads, so the handler has to be reachable from everywhere
+static EVENT_HANDLER: Mutex<Option<EventHandler>> = Mutex::new(None);
+// called when the output they are queued for stops, oldest first, the outputs are identified by their address
+static STOP_HANDLERS: Mutex<Vec<(usize, StopHandler)>> = Mutex::new(Vec::new());
+static FRAME_MONITOR: AtomicPtr<FrameMonitor> = AtomicPtr::new(null_mut());
+// file the main output is writing to, only set while it splits files
+static CURRENT_SEGMENT: Mutex<Option<String>> = Mutex::new(None);
+
+// how often the dropped frame counters get checked
+const FRAME_MONITOR_INTERVAL: f32 = 1.0;
+
+pub(crate) fn set_event_handler(handler: Option<EventHandler>) {
+    *EVENT_HANDLER.lock().unwrap_or_else(|e| e.into_inner()) = handler;
+}
+
+pub(crate) fn queue_stop_handler(output: *mut obs_output, handler: StopHandler) {
+    stop_handlers().push((output as usize, handler));
+}
+
+pub(crate) fn unqueue_stop_handler(output: *mut obs_output) {
+    let mut handlers = stop_handlers();
+    if let Some(index) = handlers.iter().rposition(|&(queued, _)| queued == output as usize) {
+        drop(handlers.remove(index));
+    }
+}
+
+fn stop_handlers() -> MutexGuard<'static, Vec<(usize, StopHandler)>> {
+    STOP_HANDLERS.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+pub(crate) fn emit(event: RecorderEvent) {
+    if let Some(handler) = EVENT_HANDLER.lock().unwrap_or_else(|e| e.into_inner()).as_ref() {
+        handler(event);
+    }
+}
+
+/// # Safety
+/// `outputs` and `video_source` have to be valid for as long as libobs is running
+pub(crate) unsafe fn connect(outputs: Vec<*mut obs_output>, video_source: *mut obs_source) {
+    // only one of them runs at a time
+    for &output in &outputs {
+        connect_output(output, OutputId::MAIN);
+        let handler = libobs_sys::obs_output_get_signal_handler(output);
+        libobs_sys::signal_handler_connect(handler, c"file_changed".as_ptr(), Some(file_changed), null_mut());
+    }
+
+    // game_capture specific signals
+    let source_handler = libobs_sys::obs_source_get_signal_handler(video_source);
+    libobs_sys::signal_handler_connect(source_handler, c"hooked".as_ptr(), Some(source_hooked), null_mut());
+    libobs_sys::signal_handler_connect(source_handler, c"unhooked".as_ptr(), Some(source_unhooked), null_mut());
+
+    let monitor = Box::into_raw(Box::new(FrameMonitor {
+        outputs,
+        elapsed: 0.0,
+        dropped: 0,
+        lagged: 0,
+    }));
+    FRAME_MONITOR.store(monitor, Ordering::Release);
+    libobs_sys::obs_add_tick_callback(Some(frame_monitor_tick), monitor.cast());
+}
+
+/// # Safety
+/// `output` has to be valid
+pub(crate) unsafe fn connect_output(output: *mut obs_output, id: OutputId) {
+    // the id is passed to the callbacks instead of a pointer
+    let param = id.to_raw() as *mut c_void;
+    let handler = libobs_sys::obs_output_get_signal_handler(output);
+    libobs_sys::signal_handler_connect(handler, c"start".as_ptr(), Some(output_start), param);
+    libobs_sys::signal_handler_connect(handler, c"stop".as_ptr(), Some(output_stop), param);
+}
+
+/// # Safety
+/// `output` has to be valid
+pub(crate) unsafe fn disconnect_output(output: *mut obs_output, id: OutputId) {
+    let param = id.to_raw() as *mut c_void;
+    let handler = libobs_sys::obs_output_get_signal_handler(output);
+    libobs_sys::signal_handler_disconnect(handler, c"start".as_ptr(), Some(output_start), param);
+    libobs_sys::signal_handler_disconnect(handler, c"stop".as_ptr(), Some(output_stop), param);
+}
+
+/// # Safety
+/// `output` has to be a valid `replay_buffer` output
+pub(crate) unsafe fn connect_replay(output: *mut obs_output) {
+    // the signal carries no data, so the callback needs the output to ask it for the file
+    let handler = libobs_sys::obs_output_get_signal_handler(output);
+    libobs_sys::signal_handler_connect(handler, c"saved".as_ptr(), Some(replay_saved), output.cast());
+}
+
+/// # Safety
+/// `output` has to be valid
+pub(crate) unsafe fn disconnect_replay(output: *mut obs_output) {
+    let handler = libobs_sys::obs_output_get_signal_handler(output);
+    libobs_sys::signal_handler_disconnect(handler, c"saved".as_ptr(), Some(replay_saved), output.cast());
+}
+
+/// # Safety
+/// `output` has to be valid
+pub(crate) unsafe fn replay_buffer_save(output: *mut obs_output) -> bool {
+    let mut data = calldata_t::default();
+    let found = libobs_sys::proc_handler_call(
+        libobs_sys::obs_output_get_proc_handler(output),
+        c"save".as_ptr(),
+        &mut data,
+    );
+    calldata_free(&mut data);
+    found
+}
+
+/// # Safety
+/// `output` has to be valid
+pub(crate) unsafe fn split_file(output: *mut obs_output) -> bool {
+    let mut data = calldata_t::default();
+    let found = libobs_sys::proc_handler_call(
+        libobs_sys::obs_output_get_proc_handler(output),
+        c"split_file".as_ptr(),
+        &mut data,
+    );
+    // the muxer only splits if it has been told to by its settings
+    let enabled = calldata_bool(&mut data, c"split_file_enabled").unwrap_or_default();
+    calldata_free(&mut data);
+    found && enabled
+}
+
+/// # Safety
+/// `output` has to be valid
+pub(crate) unsafe fn add_chapter(output: *mut obs_output, name: &str) -> bool {
+    let mut get = Get::new();
+    let mut data = calldata_t::default();
+    // calldata_set_string is an inline function
+    let name = name.replace('\0', "");
+    libobs_sys::calldata_set_data(
+        &mut data,
+        c"chapter_name".as_ptr(),
+        get.c_str(name.as_str()).cast(),
+        name.len() + 1,
+    );
+    // only the hybrid MP4 output has this procedure
+    let found = libobs_sys::proc_handler_call(
+        libobs_sys::obs_output_get_proc_handler(output),
+        c"add_chapter".as_ptr(),
+        &mut data,
+    );
+    calldata_free(&mut data);
+    found
+}
+
+/// Has to be called before libobs shuts down.
+pub(crate) fn disconnect() {
+    let monitor = FRAME_MONITOR.swap(null_mut(), Ordering::AcqRel);
+    if !monitor.is_null() {
+        unsafe {
+            // after removing the callback the graphics thread doesn't use the monitor anymore
+            libobs_sys::obs_remove_tick_callback(Some(frame_monitor_tick), monitor.cast());
+            drop(Box::from_raw(monitor));
+        }
+    }
+    stop_handlers().clear();
+    set_event_handler(None);
+}
+
+pub(crate) fn output_last_error(output: *mut obs_output) -> Option<String> {
+    let err = unsafe { libobs_sys::obs_output_get_last_error(output) };
+    if err.is_null() {
+        None
+    } else {
+        Some(unsafe { CStr::from_ptr(err) }.to_string_lossy().to_string())
+    }
+}
+
+unsafe extern "C" fn output_start(param: *mut c_void, data: *mut calldata_t) {
+    let id = OutputId::from_raw(param as usize);
+    if id == OutputId::MAIN {
+        let segment = calldata_ptr::<obs_output>(data, c"output").and_then(|output| split_file_path(output));
+        *CURRENT_SEGMENT.lock().unwrap_or_else(|e| e.into_inner()) = segment;
+    }
+    emit(output::started_event(id));
+}
+
+unsafe extern "C" fn output_stop(param: *mut c_void, data: *mut calldata_t) {
+    let reason = StopReason::from(calldata_int(data, c"code").unwrap_or_default() as i32);
+    let output = calldata_ptr::<obs_output>(data, c"output");
+    let error = match output {
+        Some(output) if reason != StopReason::Success => output_last_error(output),
+        _ => None,
+    };
+
+    if reason == StopReason::EncodeError {
+        emit(RecorderEvent::EncoderError { error: error.clone() });
+    }
+    let id = OutputId::from_raw(param as usize);
+    if id == OutputId::MAIN {
+        let segment = CURRENT_SEGMENT.lock().unwrap_or_else(|e| e.into_inner()).take();
+        if let Some(path) = segment {
+            emit(RecorderEvent::SegmentFinished { path, next: None });
+        }
+    }
+    if let Some(output) = output.and_then(NonNull::new) {
+        // the oldest one, those queued after it wait for the next stops of the output
+        let handler = {
+            let mut handlers = stop_handlers();
+            let index = handlers
+                .iter()
+                .position(|&(queued, _)| queued == output.as_ptr() as usize);
+            index.map(|index| handlers.remove(index).1)
+        };
+        if let Some(handler) = handler {
+            // it writes files, which must not hold up the signals of libobs
+            let time = Libobs.output_time(output);
+            jobs::spawn("stop", move || handler(time));
+        }
+    }
+    emit(output::stopped_event(id, reason, error));
+}
+
+unsafe extern "C" fn file_changed(_: *mut c_void, data: *mut calldata_t) {
+    let Some(next) = calldata_string(&*data, c"next_file") else {
+        log::warn!("file changed without a path");
+        return;
+    };
+    let path = CURRENT_SEGMENT
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .replace(next.clone());
+    match path {
+        Some(path) => emit(RecorderEvent::SegmentFinished { path, next: Some(next) }),
+        None => log::warn!("file changed to {next} without a current file"),
+    }
+}
+
+/// The path of the first file if the output splits files.
+unsafe fn split_file_path(output: *mut obs_output) -> Option<String> {
+    let settings = libobs_sys::obs_output_get_settings(output);
+    if settings.is_null() {
+        return None;
+    }
+    let path = if libobs_sys::obs_data_get_bool(settings, c"split_file".as_ptr()) {
+        let path = libobs_sys::obs_data_get_string(settings, c"path".as_ptr());
+        (!path.is_null()).then(|| CStr::from_ptr(path).to_string_lossy().to_string())
+    } else {
+        None
+    };
+    libobs_sys::obs_data_release(settings);
+    path
+}
+
+unsafe extern "C" fn replay_saved(param: *mut c_void, _: *mut calldata_t) {
+    let output = param.cast::<obs_output>();
+    let mut data = calldata_t::default();
+    libobs_sys::proc_handler_call(
+        libobs_sys::obs_output_get_proc_handler(output),
+        c"get_last_replay".as_ptr(),
+        &mut data,
+    );
+    let path = calldata_string(&data, c"path");
+    calldata_free(&mut data);
+
+    match path {
+        Some(path) => emit(RecorderEvent::ReplaySaved { path }),
+        None => log::warn!("replay saved without a path"),
+    }
+}
+
+unsafe extern "C" fn source_hooked(_: *mut c_void, _: *mut calldata_t) {
+    emit(RecorderEvent::SourceHooked);
+}
+
+unsafe extern "C" fn source_unhooked(_: *mut c_void, _: *mut calldata_t) {
+    emit(RecorderEvent::SourceLost);
+}
+
+// only ever accessed from the libobs graphics thread through the tick callback
+struct FrameMonitor {
+    outputs: Vec<*mut obs_output>,
+    elapsed: f32,
+    dropped: i32,
+    lagged: u32,
+}
+
+unsafe extern "C" fn frame_monitor_tick(param: *mut c_void, seconds: f32) {
+    let monitor = &mut *param.cast::<FrameMonitor>();
+    monitor.elapsed += seconds;
+    if monitor.elapsed < FRAME_MONITOR_INTERVAL {
+        return;
+    }
+    monitor.elapsed = 0.0;
+
+    let lagged = libobs_sys::obs_get_lagged_frames();
+    let Some(&output) = monitor
+        .outputs
+        .iter()
+        .find(|&&output| libobs_sys::obs_output_active(output))
+    else {
+        // only report frames that got lost while recording
+        monitor.dropped = 0;
+        monitor.lagged = lagged;
+        return;
+    };
+
+    let dropped = libobs_sys::obs_output_get_frames_dropped(output);
+    if dropped > monitor.dropped || lagged > monitor.lagged {
+        emit(RecorderEvent::DroppedFrames {
+            dropped: dropped.saturating_sub(monitor.dropped).unsigned_abs(),
+            lagged: lagged.saturating_sub(monitor.lagged),
+        });
+    }
+    monitor.dropped = dropped;
+    monitor.lagged = lagged;
+}
+
+// calldata_get_int and calldata_get_ptr are inline functions in libobs and therefore not part of the bindings
+unsafe fn calldata_int(data: *mut calldata_t, name: &CStr) -> Option<i64> {
+    let mut value: i64 = 0;
+    let found = libobs_sys::calldata_get_data(data, name.as_ptr(), (&mut value as *mut i64).cast(), size_of::<i64>());
+    found.then_some(value)
+}
+
+unsafe fn calldata_bool(data: *mut calldata_t, name: &CStr) -> Option<bool> {
+    let mut value = false;
+    let found = libobs_sys::calldata_get_data(data, name.as_ptr(), (&mut value as *mut bool).cast(), size_of::<bool>());
+    found.then_some(value)
+}
+
+unsafe fn calldata_ptr<T>(data: *mut calldata_t, name: &CStr) -> Option<*mut T> {
+    let mut value: *mut T = null_mut();
+    let found = libobs_sys::calldata_get_data(
+        data,
+        name.as_ptr(),
+        (&mut value as *mut *mut T).cast(),
+        size_of::<*mut T>(),
+    );
+    (found && !value.is_null()).then_some(value)
+}
+
+unsafe fn calldata_string(data: &calldata_t, name: &CStr) -> Option<String> {
+    let mut value: *const c_char = null();
+    let found = libobs_sys::calldata_get_string(data, name.as_ptr(), &mut value);
+    (found && !value.is_null()).then(|| CStr::from_ptr(value).to_string_lossy().to_string())
+}
+
+// calldata_free is an inline function as well
+unsafe fn calldata_free(data: &mut calldata_t) {
+    if !data.fixed {
+        libobs_sys::bfree(data.stack.cast());
+    }
+}
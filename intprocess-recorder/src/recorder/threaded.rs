@@ -0,0 +1,288 @@
+use std::sync::{mpsc, Arc};
+use std::thread::{self, JoinHandle};
+
+use super::backend::{Backend, Libobs};
+use super::InpRecorder;
+use crate::error::{Error, Result};
+use crate::events::RecorderEvent;
+use crate::marker::Marker;
+use crate::output::{OutputId, OutputKind};
+use crate::settings::{Adapter, Encoder, RecorderSettings};
+use crate::status::Status;
+
+type Job<B> = Box<dyn FnOnce(&mut InpRecorder<B>) + Send>;
+
+/// A handle to an `InpRecorder` that lives on a dedicated libobs thread.
+///
+/// Unlike `InpRecorder` it is `Send + Sync` and can be cloned, so it can be used from any thread.
+/// Every call is forwarded to the libobs thread and blocks until it has been handled there, one call at a time.
+///
+/// libobs is shut down on its thread once the last handle is dropped, `shutdown` does the same and waits for it.
+///
+/// # Errors
+/// If the libobs thread panics, every call through a handle fails with `Error::ThreadGone` from then on.
+pub struct ThreadedRecorder<B: Backend + 'static = Libobs> {
+    inner: Arc<Inner<B>>,
+}
+
+struct Inner<B: Backend + 'static> {
+    // `None` once the libobs thread has been told to finish
+    jobs: Option<mpsc::Sender<Job<B>>>,
+    thread: Option<JoinHandle<Result<()>>>,
+}
+
+impl ThreadedRecorder {
+    /// Spawns the libobs thread and calls `InpRecorder::initialize` on it.
+    ///
+    /// libobs is still a singleton, so this fails with `Error::AlreadyInitialized` if it has been initialized before.
+    pub fn initialize(
+        libobs_data_path: Option<&str>,
+        plugin_bin_path: Option<&str>,
+        plugin_data_path: Option<&str>,
+    ) -> Result<Self> {
+        let libobs_data_path = libobs_data_path.map(String::from);
+        let plugin_bin_path = plugin_bin_path.map(String::from);
+        let plugin_data_path = plugin_data_path.map(String::from);
+
+        Self::spawn(
+            move || {
+                InpRecorder::initialize(
+                    libobs_data_path.as_deref(),
+                    plugin_bin_path.as_deref(),
+                    plugin_data_path.as_deref(),
+                )?;
+                InpRecorder::get_handle()
+            },
+            InpRecorder::shutdown,
+        )
+    }
+}
+
+#[cfg(feature = "fake")]
+impl ThreadedRecorder<super::backend::FakeBackend> {
+    /// Like `InpRecorder::fake` but on a dedicated thread.
+    pub fn fake(backend: &super::backend::FakeBackend) -> Result<Self> {
+        let init_backend = backend.clone();
+        let backend = backend.clone();
+        Self::spawn(
+            move || InpRecorder::fake(&init_backend),
+            move || {
+                backend.shutdown();
+                Ok(())
+            },
+        )
+    }
+}
+
+impl<B: Backend + 'static> ThreadedRecorder<B> {
+    fn spawn(
+        init: impl FnOnce() -> Result<InpRecorder<B>> + Send + 'static,
+        shutdown: impl FnOnce() -> Result<()> + Send + 'static,
+    ) -> Result<Self> {
+        let (jobs, queue) = mpsc::channel::<Job<B>>();
+        let (ready, initialized) = mpsc::sync_channel(1);
+
+        let thread = thread::Builder::new()
+            .name("libobs".into())
+            .spawn(move || {
+                match init() {
+                    Ok(mut recorder) => {
+                        _ = ready.send(Ok(()));
+                        // runs until every handle is gone
+                        for job in queue {
+                            job(&mut recorder);
+                        }
+                    }
+                    Err(e) => _ = ready.send(Err(e)),
+                }
+                // the recorder has to be dropped before libobs can be shut down
+                shutdown()
+            })
+            .map_err(|e| Error::ThreadSpawnFailed(e.to_string()))?;
+
+        match initialized.recv() {
+            Ok(Ok(())) => Ok(Self {
+                inner: Arc::new(Inner {
+                    jobs: Some(jobs),
+                    thread: Some(thread),
+                }),
+            }),
+            Ok(Err(e)) => {
+                _ = thread.join();
+                Err(e)
+            }
+            // initialization panicked
+            Err(_) => {
+                _ = thread.join();
+                Err(Error::ThreadGone)
+            }
+        }
+    }
+
+    /// Drops the recorder and shuts libobs down, then waits for the libobs thread to finish.
+    ///
+    /// Fails with `Error::RecorderInUse` as long as clones of this handle exist, which keep libobs running.
+    pub fn shutdown(self) -> Result<()> {
+        match Arc::try_unwrap(self.inner) {
+            Ok(mut inner) => inner.finish(),
+            Err(_) => Err(Error::RecorderInUse),
+        }
+    }
+
+    pub fn start_recording(&self) -> Result<String> {
+        self.try_call(|recorder| recorder.start_recording())
+    }
+
+    pub fn stop_recording(&self) -> Result<()> {
+        self.call(|recorder| recorder.stop_recording())
+    }
+
+    pub fn pause_recording(&self) -> Result<()> {
+        self.try_call(|recorder| recorder.pause_recording())
+    }
+
+    pub fn resume_recording(&self) -> Result<()> {
+        self.try_call(|recorder| recorder.resume_recording())
+    }
+
+    pub fn is_paused(&self) -> Result<bool> {
+        self.call(|recorder| recorder.is_paused())
+    }
+
+    pub fn split_recording(&self) -> Result<()> {
+        self.try_call(|recorder| recorder.split_recording())
+    }
+
+    pub fn add_marker(&self, label: &str) -> Result<Marker> {
+        let label = label.to_string();
+        self.try_call(move |recorder| recorder.add_marker(&label))
+    }
+
+    /// Remuxes in the background like `InpRecorder::start_remux`, a blocking remux would hold up the libobs thread.
+    pub fn start_remux(&self, input: &str, output: &str) -> Result<()> {
+        let (input, output) = (input.to_string(), output.to_string());
+        self.try_call(move |recorder| recorder.start_remux(&input, &output))
+    }
+
+    pub fn add_output(&self, kind: &OutputKind) -> Result<OutputId> {
+        let kind = kind.clone();
+        self.try_call(move |recorder| recorder.add_output(&kind))
+    }
+
+    pub fn remove_output(&self, id: OutputId) -> Result<()> {
+        self.try_call(move |recorder| recorder.remove_output(id))
+    }
+
+    pub fn outputs(&self) -> Result<Vec<OutputId>> {
+        self.call(|recorder| recorder.outputs())
+    }
+
+    pub fn start_output(&self, id: OutputId) -> Result<()> {
+        self.try_call(move |recorder| recorder.start_output(id))
+    }
+
+    pub fn stop_output(&self, id: OutputId) -> Result<()> {
+        self.try_call(move |recorder| recorder.stop_output(id))
+    }
+
+    pub fn is_output_active(&self, id: OutputId) -> Result<bool> {
+        self.try_call(move |recorder| recorder.is_output_active(id))
+    }
+
+    pub fn start_replay_buffer(&self) -> Result<OutputId> {
+        self.try_call(|recorder| recorder.start_replay_buffer())
+    }
+
+    pub fn save_replay(&self) -> Result<()> {
+        self.try_call(|recorder| recorder.save_replay())
+    }
+
+    pub fn stop_replay_buffer(&self) -> Result<()> {
+        self.call(|recorder| recorder.stop_replay_buffer())
+    }
+
+    pub fn configure(&self, settings: &RecorderSettings) -> Result<()> {
+        let settings = settings.clone();
+        self.try_call(move |recorder| recorder.configure(&settings))
+    }
+
+    /// Registers a handler that gets called for every `RecorderEvent` (replaces the previous one).
+    ///
+    /// The handler gets called from libobs threads, so it should return quickly.
+    /// It must not call back into the recorder, since that might be waiting for the handler to return.
+    pub fn set_event_handler(&self, handler: impl Fn(RecorderEvent) + Send + 'static) -> Result<()> {
+        self.call(move |recorder| recorder.set_event_handler(handler))
+    }
+
+    pub fn is_recording(&self) -> Result<bool> {
+        self.call(|recorder| recorder.is_recording())
+    }
+
+    pub fn status(&self) -> Result<Status> {
+        self.call(|recorder| recorder.status())
+    }
+
+    pub fn get_adapter_info(&self) -> Result<Adapter> {
+        self.call(|recorder| recorder.get_adapter_info())
+    }
+
+    pub fn get_available_encoders(&self) -> Result<Vec<Encoder>> {
+        self.call(|recorder| recorder.get_available_encoders())
+    }
+
+    pub fn selected_encoder(&self) -> Result<Encoder> {
+        self.call(|recorder| recorder.selected_encoder())
+    }
+
+    /// Runs `f` on the libobs thread and waits for its result.
+    ///
+    /// Fails with `Error::ThreadGone` if the libobs thread has panicked (before or while running `f`).
+    fn call<T: Send + 'static>(&self, f: impl FnOnce(&mut InpRecorder<B>) -> T + Send + 'static) -> Result<T> {
+        let (tx, rx) = mpsc::sync_channel(1);
+        let job: Job<B> = Box::new(move |recorder| _ = tx.send(f(recorder)));
+
+        if let Some(jobs) = &self.inner.jobs {
+            // a failed send drops `tx` along with the job, so `recv` fails as well
+            _ = jobs.send(job);
+        }
+        rx.recv().map_err(|_| Error::ThreadGone)
+    }
+
+    /// Like `call` for the methods of `InpRecorder` that can fail.
+    fn try_call<T: Send + 'static>(
+        &self,
+        f: impl FnOnce(&mut InpRecorder<B>) -> Result<T> + Send + 'static,
+    ) -> Result<T> {
+        self.call(f)?
+    }
+}
+
+impl<B: Backend + 'static> Clone for ThreadedRecorder<B> {
+    fn clone(&self) -> Self {
+        Self { inner: Arc::clone(&self.inner) }
+    }
+}
+
+impl<B: Backend + 'static> Inner<B> {
+    fn finish(&mut self) -> Result<()> {
+        // ends the job loop on the libobs thread
+        self.jobs = None;
+        match self.thread.take().map(JoinHandle::join) {
+            Some(Ok(result)) => result,
+            Some(Err(_)) => Err(Error::ThreadGone),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<B: Backend + 'static> Drop for Inner<B> {
+    fn drop(&mut self) {
+        // don't panic again while unwinding from a panic on the libobs thread
+        self.jobs = None;
+        match self.thread.take().map(JoinHandle::join) {
+            Some(Ok(Err(e))) => log::error!("failed to shut down libobs: {e}"),
+            Some(Err(_)) => log::error!("the libobs thread panicked"),
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,92 @@
+use std::path::Path;
+
+use crate::error::{Error, Result};
+use crate::recorder::backend::Data;
+
+use super::{Codec, Encoder};
+
+// makes ffmpeg write a fragment at every keyframe instead of the index at the very end
+const FRAGMENTED_MOVFLAGS: &str = "movflags=frag_keyframe+empty_moov+delay_moov";
+
+/// File format of the recording.
+///
+/// A plain MP4 or MOV is unreadable if the recorder (or the game along with it) crashes before the file is finalised.
+/// The fragmented formats, MKV and FLV survive a crash up to the last few seconds. A hybrid MP4 is written like a
+/// fragmented one and turned into a regular MP4 when the recording stops, but it needs libobs 30.2 or newer.
+#[allow(non_camel_case_types)]
+#[cfg_attr(feature = "specta", derive(specta::Type))]
+#[derive(serde::Serialize, serde::Deserialize, Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum Container {
+    #[default]
+    MP4,
+    FRAGMENTED_MP4,
+    HYBRID_MP4,
+    MOV,
+    FRAGMENTED_MOV,
+    MKV,
+    /// only supports h264
+    FLV,
+}
+
+impl Container {
+    #[must_use]
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::MP4 | Self::FRAGMENTED_MP4 | Self::HYBRID_MP4 => "mp4",
+            Self::MOV | Self::FRAGMENTED_MOV => "mov",
+            Self::MKV => "mkv",
+            Self::FLV => "flv",
+        }
+    }
+
+    #[must_use]
+    pub fn supports(&self, codec: Codec) -> bool {
+        match self {
+            Self::FLV => codec == Codec::H264,
+            _ => true,
+        }
+    }
+
+    /// Returns `path` with the extension of the container, which is appended if `path` has none.
+    ///
+    /// Fails with `Error::ExtensionMismatch` if `path` has a different extension, e.g. `game.mp4` for MKV.
+    pub fn fix_extension(&self, path: &str) -> Result<String> {
+        match Path::new(path).extension().and_then(|e| e.to_str()) {
+            Some(extension) if extension.eq_ignore_ascii_case(self.extension()) => Ok(path.to_string()),
+            Some(_) => Err(Error::ExtensionMismatch {
+                path: path.to_string(),
+                container: *self,
+            }),
+            None => Ok(format!("{path}.{}", self.extension())),
+        }
+    }
+
+    pub(crate) fn check_encoder(&self, encoder: Encoder) -> Result<()> {
+        if self.supports(encoder.codec()) {
+            Ok(())
+        } else {
+            Err(Error::IncompatibleContainer { encoder, container: *self })
+        }
+    }
+
+    /// Type id of the libobs output that writes this container.
+    pub(crate) fn output_type(&self) -> &'static str {
+        match self {
+            Self::HYBRID_MP4 => "mp4_output",
+            _ => "ffmpeg_muxer",
+        }
+    }
+
+    /// Settings of the output for a recording to `path`, which must already have the right extension.
+    pub(crate) fn settings(&self, path: &str) -> Data {
+        let mut data = Data::new();
+        data.set_string("path", path);
+        // ffmpeg picks the format by the extension, but the flags have to be reset when switching formats
+        let muxer_settings = match self {
+            Self::FRAGMENTED_MP4 | Self::FRAGMENTED_MOV => FRAGMENTED_MOVFLAGS,
+            _ => "",
+        };
+        data.set_string("muxer_settings", muxer_settings);
+        data
+    }
+}
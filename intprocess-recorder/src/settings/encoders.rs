@@ -1,4 +1,4 @@
-use crate::{recorder::obs_data::ObsData, settings::RateControl};
+use crate::{recorder::backend::Data, settings::RateControl};
 
 use super::{adapter::AdapterType, Adapter};
 
@@ -25,6 +25,14 @@ pub enum Encoder {
     OBS_X264,
 }
 
+/// Video codec an `Encoder` produces.
+#[cfg_attr(feature = "specta", derive(specta::Type))]
+#[derive(serde::Serialize, serde::Deserialize, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Codec {
+    H264,
+    AV1,
+}
+
 impl Encoder {
     #[must_use]
     pub fn id(&self) -> &str {
@@ -41,7 +49,17 @@ impl Encoder {
     }
 
     #[must_use]
-    pub(crate) fn settings(self, rate_control: RateControl) -> ObsData {
+    pub fn codec(&self) -> Codec {
+        match self {
+            Self::JIM_AV1 | Self::AMD_AMF_AV1 | Self::OBS_QSV11_AV1 => Codec::AV1,
+            Self::JIM_NVENC | Self::FFMPEG_NVENC | Self::AMD_AMF_H264 | Self::OBS_QSV11_H264 | Self::OBS_X264 => {
+                Codec::H264
+            }
+        }
+    }
+
+    #[must_use]
+    pub(crate) fn settings(self, rate_control: RateControl) -> Data {
         match self {
             Self::JIM_NVENC | Self::FFMPEG_NVENC => nvidia_h264_settings(rate_control),
             Self::JIM_AV1 => nvidia_av1_settings(rate_control),
@@ -69,16 +87,19 @@ impl TryFrom<&str> for Encoder {
         match value {
             "jim_nvenc" => Ok(Self::JIM_NVENC),
             "ffmpeg_nvenc" => Ok(Self::FFMPEG_NVENC),
+            "jim_av1_nvenc" => Ok(Self::JIM_AV1),
             "h264_texture_amf" => Ok(Self::AMD_AMF_H264),
+            "av1_texture_amf" => Ok(Self::AMD_AMF_AV1),
             "obs_qsv11" => Ok(Self::OBS_QSV11_H264),
+            "obs_qsv11_av1" => Ok(Self::OBS_QSV11_AV1),
             "obs_x264" => Ok(Self::OBS_X264),
             _ => Err(()),
         }
     }
 }
 
-fn nvidia_h264_settings(settings: RateControl) -> ObsData {
-    let mut data = ObsData::new();
+fn nvidia_h264_settings(settings: RateControl) -> Data {
+    let mut data = Data::new();
 
     data.set_int("bf", 2);
     data.set_bool("psycho_aq", true);
@@ -108,8 +129,8 @@ fn nvidia_h264_settings(settings: RateControl) -> ObsData {
     data
 }
 
-fn nvidia_av1_settings(settings: RateControl) -> ObsData {
-    let mut data = ObsData::new();
+fn nvidia_av1_settings(settings: RateControl) -> Data {
+    let mut data = Data::new();
 
     data.set_string("rate_control", "CQP");
     data.set_string("profile", "main");
@@ -135,8 +156,8 @@ fn nvidia_av1_settings(settings: RateControl) -> ObsData {
     data
 }
 
-fn amd_amf_settings(rate_control: RateControl) -> ObsData {
-    let mut data = ObsData::new();
+fn amd_amf_settings(rate_control: RateControl) -> Data {
+    let mut data = Data::new();
 
     // Picture Control Properties
     data.set_int("bf", 1);
@@ -165,8 +186,8 @@ fn amd_amf_settings(rate_control: RateControl) -> ObsData {
     data
 }
 
-fn intel_quicksync_h264_settings(settings: RateControl) -> ObsData {
-    let mut data = ObsData::new();
+fn intel_quicksync_h264_settings(settings: RateControl) -> Data {
+    let mut data = Data::new();
 
     data.set_string("profile", "high");
 
@@ -197,8 +218,8 @@ fn intel_quicksync_h264_settings(settings: RateControl) -> ObsData {
     data
 }
 
-fn intel_quicksync_av1_settings(settings: RateControl) -> ObsData {
-    let mut data = ObsData::new();
+fn intel_quicksync_av1_settings(settings: RateControl) -> Data {
+    let mut data = Data::new();
 
     data.set_string("profile", "high");
 
@@ -215,8 +236,8 @@ fn intel_quicksync_av1_settings(settings: RateControl) -> ObsData {
     data
 }
 
-fn obs_x264_settings(rate_control: RateControl) -> ObsData {
-    let mut data = ObsData::new();
+fn obs_x264_settings(rate_control: RateControl) -> Data {
+    let mut data = Data::new();
 
     data.set_bool("use_bufsize", true);
 
@@ -1,17 +1,25 @@
 pub use adapter::{Adapter, AdapterId, AdapterType};
 pub use audio::AudioSource;
-pub use encoders::Encoder;
+pub use container::Container;
+pub use encoders::{Codec, Encoder};
 pub use framerate::Framerate;
+pub(crate) use path_template::{unused_path, PathTemplate, TemplateValues};
 pub use rate_control::RateControl;
+pub use replay_buffer::ReplayBuffer;
 pub use resolution::{Resolution, StdResolution};
+pub use split::Split;
 pub use window::Window;
 
 mod adapter;
 mod audio;
+mod container;
 mod encoders;
 mod framerate;
+mod path_template;
 mod rate_control;
+mod replay_buffer;
 mod resolution;
+mod split;
 mod window;
 
 #[cfg_attr(feature = "specta", derive(specta::Type))]
@@ -26,6 +34,10 @@ pub struct RecorderSettings {
     pub(crate) rate_control: Option<RateControl>,
     pub(crate) audio_source: Option<AudioSource>,
     pub(crate) encoder: Option<Encoder>,
+    pub(crate) container: Option<Container>,
+    pub(crate) replay_buffer: Option<ReplayBuffer>,
+    pub(crate) split: Option<Split>,
+    pub(crate) remux_on_stop: bool,
 }
 
 impl RecorderSettings {
@@ -52,6 +64,10 @@ impl RecorderSettings {
             rate_control: None,
             audio_source: None,
             encoder: None,
+            container: None,
+            replay_buffer: None,
+            split: None,
+            remux_on_stop: false,
         }
     }
 
@@ -79,6 +95,20 @@ impl RecorderSettings {
         &self.output_resolution
     }
 
+    /// The path may contain placeholders, which are filled in every time the recording starts:
+    ///
+    /// | placeholder | replaced with                                        |
+    /// |-------------|------------------------------------------------------|
+    /// | `{date}`    | the date in UTC, e.g. `2024-05-01`                   |
+    /// | `{time}`    | the time in UTC, e.g. `20-15-42`                     |
+    /// | `{window}`  | the title of the window                              |
+    /// | `{process}` | the executable of the window without `.exe`          |
+    /// | `{encoder}` | the libobs id of the encoder, e.g. `jim_nvenc`       |
+    /// | `{counter}` | the lowest number from `001` up whose file is unused |
+    ///
+    /// `{{` and `}}` stand for literal braces. Characters that are not allowed in file names are replaced in the values.
+    /// An existing file is never overwritten, without `{counter}` a number is appended instead (`game_1.mp4`).
+    /// Missing directories are created.
     pub fn set_output_path(&mut self, output_path: impl Into<String>) {
         self.output_path = output_path.into();
     }
@@ -118,4 +148,40 @@ impl RecorderSettings {
     pub fn get_encoder(&self) -> Option<&Encoder> {
         self.encoder.as_ref()
     }
+
+    pub fn set_container(&mut self, container: Container) {
+        self.container = Some(container);
+    }
+
+    pub fn get_container(&self) -> Option<&Container> {
+        self.container.as_ref()
+    }
+
+    pub fn set_replay_buffer(&mut self, replay_buffer: ReplayBuffer) {
+        self.replay_buffer = Some(replay_buffer);
+    }
+
+    pub fn get_replay_buffer(&self) -> Option<&ReplayBuffer> {
+        self.replay_buffer.as_ref()
+    }
+
+    pub fn set_split(&mut self, split: Split) {
+        self.split = Some(split);
+    }
+
+    pub fn get_split(&self) -> Option<&Split> {
+        self.split.as_ref()
+    }
+
+    /// Remuxes the recording into an MP4 next to it once `stop_recording` has finished the file, e.g. for a
+    /// recording to MKV that survives crashes but should end up as MP4. The original file is kept.
+    ///
+    /// Has no effect if the recording already is an MP4 and can't be combined with `set_split`.
+    pub fn set_remux_on_stop(&mut self, remux_on_stop: bool) {
+        self.remux_on_stop = remux_on_stop;
+    }
+
+    pub fn get_remux_on_stop(&self) -> bool {
+        self.remux_on_stop
+    }
 }
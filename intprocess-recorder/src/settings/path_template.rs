@@ -0,0 +1,362 @@
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::error::{Error, Result};
+
+use super::{Encoder, Window};
+
+// the name of a single file or directory is limited to 255 characters on most file systems
+const MAX_VALUE_LENGTH: usize = 100;
+// how many counters are tried before giving up, e.g. on a broken file system that reports every file as existing
+const MAX_COUNTER: u32 = 10_000;
+// device names Windows reserves in every directory, with or without an extension
+const RESERVED_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8", "COM9", "LPT1", "LPT2",
+    "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// An output path with placeholders that get filled in when the recording starts, see `set_output_path`.
+///
+/// The values are made safe to use as part of a file name, the rest of the path is used as is.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct PathTemplate {
+    parts: Vec<Part>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Part {
+    Literal(String),
+    Date,
+    Time,
+    Window,
+    Process,
+    Encoder,
+    Counter,
+}
+
+/// What the placeholders of a `PathTemplate` are replaced with.
+pub(crate) struct TemplateValues<'a> {
+    pub(crate) time: SystemTime,
+    pub(crate) window: &'a Window,
+    pub(crate) encoder: Encoder,
+}
+
+impl PathTemplate {
+    /// Fails with `Error::InvalidPathTemplate` on unknown placeholders and unmatched braces.
+    pub(crate) fn parse(template: &str) -> Result<Self> {
+        let invalid = || Error::InvalidPathTemplate(template.to_string());
+
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut rest = template;
+        while let Some(c) = rest.chars().next() {
+            rest = &rest[c.len_utf8()..];
+            match c {
+                '{' if rest.starts_with('{') => {
+                    literal.push('{');
+                    rest = &rest[1..];
+                }
+                '}' if rest.starts_with('}') => {
+                    literal.push('}');
+                    rest = &rest[1..];
+                }
+                '{' => {
+                    let (name, after) = rest.split_once('}').ok_or_else(invalid)?;
+                    let part = match name {
+                        "date" => Part::Date,
+                        "time" => Part::Time,
+                        "window" => Part::Window,
+                        "process" => Part::Process,
+                        "encoder" => Part::Encoder,
+                        "counter" => Part::Counter,
+                        _ => return Err(invalid()),
+                    };
+                    if !literal.is_empty() {
+                        parts.push(Part::Literal(std::mem::take(&mut literal)));
+                    }
+                    parts.push(part);
+                    rest = after;
+                }
+                '}' => return Err(invalid()),
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            parts.push(Part::Literal(literal));
+        }
+
+        Ok(Self { parts })
+    }
+
+    /// Fills in the placeholders, with `counter` for `{counter}`.
+    pub(crate) fn expand(&self, values: &TemplateValues, counter: u32) -> String {
+        let (date, time) = utc_date_time(values.time);
+        let mut path = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Literal(literal) => path.push_str(literal),
+                Part::Date => path.push_str(&date),
+                Part::Time => path.push_str(&time),
+                Part::Window => path.push_str(&sanitize(values.window.name())),
+                Part::Process => {
+                    let process = values.window.process().unwrap_or_default();
+                    let process = match process.rsplit_once('.') {
+                        Some((name, extension)) if extension.eq_ignore_ascii_case("exe") => name,
+                        _ => process,
+                    };
+                    path.push_str(&sanitize(process));
+                }
+                Part::Encoder => path.push_str(&sanitize(values.encoder.id())),
+                Part::Counter => path.push_str(&format!("{counter:03}")),
+            }
+        }
+        path
+    }
+
+    /// Fills in the placeholders so that the path does not exist yet.
+    ///
+    /// `{counter}` counts up until `exists` returns `false`, without it a counter is appended to the file name
+    /// instead, e.g. `game.mp4` becomes `game_1.mp4`. Fails with `Error::NoUnusedPath` if no counter is free.
+    pub(crate) fn resolve(&self, values: &TemplateValues, exists: impl Fn(&str) -> bool) -> Result<String> {
+        if !self.parts.contains(&Part::Counter) {
+            return unused_path(&self.expand(values, 1), exists);
+        }
+        (1..=MAX_COUNTER)
+            .map(|counter| self.expand(values, counter))
+            .find(|path| !exists(path))
+            .ok_or_else(|| Error::NoUnusedPath(self.expand(values, 1)))
+    }
+}
+
+/// Returns `path` or, if `exists` says it is taken, the first free one with a counter appended to the file name.
+pub(crate) fn unused_path(path: &str, exists: impl Fn(&str) -> bool) -> Result<String> {
+    if !exists(path) {
+        return Ok(path.to_string());
+    }
+    (1..=MAX_COUNTER)
+        .map(|counter| append_counter(path, counter))
+        .find(|path| !exists(path))
+        .ok_or_else(|| Error::NoUnusedPath(path.to_string()))
+}
+
+/// Makes a value safe to use as (part of) the name of a file or directory on any platform.
+fn sanitize(value: &str) -> String {
+    let value: String = value
+        .chars()
+        .map(|c| match c {
+            '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .take(MAX_VALUE_LENGTH)
+        .collect();
+    // Windows drops trailing dots and spaces, which also rules out `.` and `..`
+    let value = value.trim_start_matches(' ').trim_end_matches(['.', ' ']);
+
+    let stem = value.split('.').next().unwrap_or_default();
+    if value.is_empty() {
+        "unknown".to_string()
+    } else if RESERVED_NAMES.iter().any(|name| name.eq_ignore_ascii_case(stem)) {
+        format!("_{value}")
+    } else {
+        value.to_string()
+    }
+}
+
+/// `./videos/game.mp4` with `counter` 1 becomes `./videos/game_1.mp4`.
+fn append_counter(path: &str, counter: u32) -> String {
+    let file_name = Path::new(path).file_name().and_then(|n| n.to_str()).unwrap_or_default();
+    let directory = &path[..path.len() - file_name.len()];
+    match file_name.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() => format!("{directory}{stem}_{counter}.{extension}"),
+        _ => format!("{directory}{file_name}_{counter}"),
+    }
+}
+
+/// `YYYY-MM-DD` and `hh-mm-ss` in UTC, the time has no colons since Windows does not allow them in file names.
+fn utc_date_time(time: SystemTime) -> (String, String) {
+    let seconds = time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    let (year, month, day) = civil_from_days((seconds / 86_400) as i64);
+    let seconds = seconds % 86_400;
+    (
+        format!("{year:04}-{month:02}-{day:02}"),
+        format!("{:02}-{:02}-{:02}", seconds / 3600, seconds / 60 % 60, seconds % 60),
+    )
+}
+
+/// Converts days since 1970-01-01 to a date in the proleptic Gregorian calendar.
+///
+/// See <https://howardhinnant.github.io/date_algorithms.html#civil_from_days>.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::time::Duration;
+
+    use super::*;
+
+    // 2024-05-01 20:15:42 UTC
+    const TIME: u64 = 1_714_594_542;
+
+    fn window() -> Window {
+        Window::new(
+            "League of Legends (TM) Client",
+            None,
+            Some("League of Legends.exe".into()),
+        )
+    }
+
+    fn values(window: &Window) -> TemplateValues<'_> {
+        TemplateValues {
+            time: UNIX_EPOCH + Duration::from_secs(TIME),
+            window,
+            encoder: Encoder::JIM_NVENC,
+        }
+    }
+
+    fn expand(template: &str) -> String {
+        PathTemplate::parse(template).unwrap().expand(&values(&window()), 1)
+    }
+
+    #[test]
+    fn literal_paths_stay_the_same() {
+        assert_eq!(expand("./videos/game.mp4"), "./videos/game.mp4");
+        assert_eq!(expand(r"C:\Videos\game.mp4"), r"C:\Videos\game.mp4");
+        assert_eq!(expand(""), "");
+    }
+
+    #[test]
+    fn placeholders_are_replaced() {
+        assert_eq!(expand("{date}_{time}.mp4"), "2024-05-01_20-15-42.mp4");
+        assert_eq!(
+            expand("./{process}/{window}.mp4"),
+            "./League of Legends/League of Legends (TM) Client.mp4"
+        );
+        assert_eq!(expand("{encoder}_{counter}.mp4"), "jim_nvenc_001.mp4");
+        assert_eq!(expand("{date}{date}"), "2024-05-012024-05-01");
+    }
+
+    #[test]
+    fn braces_can_be_escaped() {
+        assert_eq!(expand("{{date}}.mp4"), "{date}.mp4");
+        assert_eq!(expand("{{{date}}}.mp4"), "{2024-05-01}.mp4");
+    }
+
+    #[test]
+    fn invalid_templates_are_rejected() {
+        for template in [
+            "{unknown}.mp4",
+            "{date.mp4",
+            "date}.mp4",
+            "{}.mp4",
+            "{Date}.mp4",
+            "{ date }.mp4",
+        ] {
+            assert_eq!(
+                PathTemplate::parse(template),
+                Err(Error::InvalidPathTemplate(template.into())),
+                "{template}"
+            );
+        }
+    }
+
+    #[test]
+    fn missing_process_is_unknown() {
+        let window = Window::new("Game", None, None);
+        let template = PathTemplate::parse("{process}.mp4").unwrap();
+        assert_eq!(template.expand(&values(&window), 1), "unknown.mp4");
+    }
+
+    #[test]
+    fn values_are_sanitized() {
+        assert_eq!(sanitize(r#"a<b>c:d"e/f\g|h?i*j"#), "a_b_c_d_e_f_g_h_i_j");
+        assert_eq!(sanitize("tab\there\n"), "tab_here_");
+        assert_eq!(sanitize("  name. . "), "name");
+        assert_eq!(sanitize(".."), "unknown");
+        assert_eq!(sanitize(""), "unknown");
+        assert_eq!(sanitize("Ünïcödé ゲーム"), "Ünïcödé ゲーム");
+        assert_eq!(sanitize("con"), "_con");
+        assert_eq!(sanitize("LPT1.txt"), "_LPT1.txt");
+        assert_eq!(sanitize("CONSOLE"), "CONSOLE");
+        assert_eq!(sanitize(&"x".repeat(300)).len(), MAX_VALUE_LENGTH);
+
+        // a window title can't escape the directory
+        let window = Window::new("../../evil", None, Some(r"..\..\evil.exe".into()));
+        let template = PathTemplate::parse("./videos/{window}/{process}.mp4").unwrap();
+        assert_eq!(
+            template.expand(&values(&window), 1),
+            "./videos/.._.._evil/.._.._evil.mp4"
+        );
+    }
+
+    #[test]
+    fn collisions_get_a_counter() {
+        let window = window();
+        let values = values(&window);
+        let existing: HashSet<&str> = ["./game.mp4", "./game_1.mp4", "./rec_001.mp4", "./noext"].into();
+        let exists = |path: &str| existing.contains(path);
+
+        let resolve = |template| PathTemplate::parse(template).unwrap().resolve(&values, exists);
+        assert_eq!(resolve("./other.mp4"), Ok("./other.mp4".into()));
+        assert_eq!(resolve("./game.mp4"), Ok("./game_2.mp4".into()));
+        assert_eq!(resolve("./rec_{counter}.mp4"), Ok("./rec_002.mp4".into()));
+        assert_eq!(resolve("./noext"), Ok("./noext_1".into()));
+
+        // gives up instead of counting forever
+        let template = PathTemplate::parse("./rec_{counter}.mp4").unwrap();
+        assert_eq!(
+            template.resolve(&values, |_| true),
+            Err(Error::NoUnusedPath("./rec_001.mp4".into()))
+        );
+    }
+
+    #[test]
+    fn unused_paths() {
+        let existing: HashSet<&str> = ["./game.mp4", "./game_1.mp4"].into();
+        let exists = |path: &str| existing.contains(path);
+        assert_eq!(unused_path("./other.mp4", exists), Ok("./other.mp4".into()));
+        assert_eq!(unused_path("./game.mp4", exists), Ok("./game_2.mp4".into()));
+        // braces are not placeholders here
+        assert_eq!(unused_path("./{game}.mp4", exists), Ok("./{game}.mp4".into()));
+        assert_eq!(
+            unused_path("./game.mp4", |_| true),
+            Err(Error::NoUnusedPath("./game.mp4".into()))
+        );
+    }
+
+    #[test]
+    fn counters_go_before_the_extension() {
+        assert_eq!(append_counter("./videos/game.mp4", 3), "./videos/game_3.mp4");
+        assert_eq!(
+            append_counter(r"C:\videos\game.tar.mkv", 1),
+            r"C:\videos\game.tar_1.mkv"
+        );
+        assert_eq!(append_counter("game", 1), "game_1");
+        assert_eq!(append_counter("./v1.0/.hidden", 1), "./v1.0/.hidden_1");
+    }
+
+    #[test]
+    fn dates_are_in_the_gregorian_calendar() {
+        let at = |seconds| utc_date_time(UNIX_EPOCH + Duration::from_secs(seconds));
+        assert_eq!(at(0), ("1970-01-01".into(), "00-00-00".into()));
+        assert_eq!(at(TIME), ("2024-05-01".into(), "20-15-42".into()));
+        // leap days
+        assert_eq!(at(951_782_400).0, "2000-02-29");
+        assert_eq!(at(1_709_164_800).0, "2024-02-29");
+        assert_eq!(at(4_107_542_399), ("2100-02-28".into(), "23-59-59".into()));
+        assert_eq!(at(4_107_542_400).0, "2100-03-01");
+        assert_eq!(civil_from_days(-1), (1969, 12, 31));
+    }
+}
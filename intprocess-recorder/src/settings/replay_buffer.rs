@@ -0,0 +1,55 @@
+use std::path::Path;
+
+use crate::recorder::backend::Data;
+
+/// How much of the most recent recording the replay buffer keeps in memory, whichever limit is reached first.
+///
+/// Saved replays go into the directory of the output path and are named after it,
+/// e.g. `./videos/game.mp4` becomes `./videos/game_replay_2024-05-01_20-15-42.mp4`.
+#[cfg_attr(feature = "specta", derive(specta::Type))]
+#[derive(serde::Serialize, serde::Deserialize, Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ReplayBuffer {
+    max_seconds: u32,
+    max_size_mb: u32,
+}
+
+impl ReplayBuffer {
+    #[must_use]
+    pub fn new(max_seconds: u32, max_size_mb: u32) -> Self {
+        Self {
+            max_seconds: max_seconds.max(1),
+            max_size_mb: max_size_mb.max(1),
+        }
+    }
+
+    #[must_use]
+    pub fn max_seconds(&self) -> u32 {
+        self.max_seconds
+    }
+
+    #[must_use]
+    pub fn max_size_mb(&self) -> u32 {
+        self.max_size_mb
+    }
+
+    /// Settings of the libobs `replay_buffer` output for replays next to `output_path`.
+    pub(crate) fn settings(&self, output_path: &str) -> Data {
+        let path = Path::new(output_path);
+        let directory = match path.parent().and_then(Path::to_str) {
+            Some("") | None => ".",
+            Some(directory) => directory,
+        };
+        let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("recording");
+        let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("mp4");
+
+        let mut data = Data::new();
+        data.set_int("max_time_sec", self.max_seconds);
+        data.set_int("max_size_mb", self.max_size_mb);
+        data.set_string("directory", directory);
+        // libobs expands the strftime-like specifiers when saving
+        data.set_string("format", format!("{stem}_replay_%CCYY-%MM-%DD_%hh-%mm-%ss"));
+        data.set_string("extension", extension);
+        data.set_bool("allow_spaces", true);
+        data
+    }
+}
@@ -0,0 +1,74 @@
+use std::path::Path;
+
+use crate::recorder::backend::Data;
+
+/// Splits the recording into several files, each of which is complete on its own.
+///
+/// The first file is written to the output path, libobs names the following ones after `name_format`, which may
+/// contain the specifiers of libobs (e.g. `%CCYY-%MM-%DD_%hh-%mm-%ss`) and gets the extension of the container.
+/// They go into the directory of the output path.
+#[cfg_attr(feature = "specta", derive(specta::Type))]
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Split {
+    max_seconds: u32,
+    max_size_mb: u32,
+    name_format: Option<String>,
+}
+
+impl Split {
+    /// Starts a new file once the current one reaches `max_seconds` or `max_size_mb`, `0` turns a limit off.
+    ///
+    /// Without any limit the file is only split by `split_recording`.
+    #[must_use]
+    pub fn new(max_seconds: u32, max_size_mb: u32) -> Self {
+        Self {
+            max_seconds,
+            max_size_mb,
+            name_format: None,
+        }
+    }
+
+    #[must_use]
+    pub fn max_seconds(&self) -> u32 {
+        self.max_seconds
+    }
+
+    #[must_use]
+    pub fn max_size_mb(&self) -> u32 {
+        self.max_size_mb
+    }
+
+    /// Defaults to the name of the output path followed by the date and time, e.g. `game_2024-05-01_20-15-42.mp4`.
+    pub fn set_name_format(&mut self, name_format: impl Into<String>) {
+        self.name_format = Some(name_format.into());
+    }
+
+    pub fn get_name_format(&self) -> Option<&str> {
+        self.name_format.as_deref()
+    }
+
+    /// Settings of the output for a recording to `output_path`, which must already have the right extension.
+    pub(crate) fn settings(&self, output_path: &str) -> Data {
+        let path = Path::new(output_path);
+        let directory = match path.parent().and_then(Path::to_str) {
+            Some("") | None => ".",
+            Some(directory) => directory,
+        };
+        let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("recording");
+        let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("mp4");
+        let name_format = match &self.name_format {
+            Some(name_format) => name_format.clone(),
+            None => format!("{stem}_%CCYY-%MM-%DD_%hh-%mm-%ss"),
+        };
+
+        let mut data = Data::new();
+        data.set_bool("split_file", true);
+        data.set_int("max_time_sec", self.max_seconds);
+        data.set_int("max_size_mb", self.max_size_mb);
+        data.set_string("directory", directory);
+        data.set_string("format", name_format);
+        data.set_string("extension", extension);
+        data.set_bool("allow_spaces", true);
+        data
+    }
+}
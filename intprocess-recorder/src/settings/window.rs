@@ -15,6 +15,14 @@ impl Window {
         }
     }
 
+    pub(crate) fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub(crate) fn process(&self) -> Option<&str> {
+        self.process.as_deref()
+    }
+
     pub(crate) fn get_libobs_window_id(&self) -> String {
         let mut window_id = String::new();
         window_id.push_str(&self.name);
@@ -0,0 +1,26 @@
+use std::time::Duration;
+
+/// Snapshot of what the recorder is doing right now.
+#[cfg_attr(feature = "specta", derive(specta::Type))]
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Status {
+    pub recording: bool,
+    pub paused: bool,
+    /// time the current (or last) recording has spent paused so far
+    pub paused_duration: Duration,
+    pub frames: FrameCounters,
+}
+
+/// Frame counters of libobs since it was initialized (`rendered` and `lagged`) or the output was started.
+#[cfg_attr(feature = "specta", derive(specta::Type))]
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FrameCounters {
+    /// frames rendered by the graphics thread - stops counting up if it is stuck
+    pub rendered: u32,
+    /// frames the graphics thread could not render in time
+    pub lagged: u32,
+    /// frames written by the output
+    pub output: u32,
+    /// frames dropped by the output
+    pub dropped: u32,
+}
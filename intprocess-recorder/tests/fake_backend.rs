@@ -0,0 +1,1096 @@
+use std::sync::mpsc;
+use std::time::Duration;
+
+use intprocess_recorder::backend::{Failure, FakeBackend, VideoInfo};
+use intprocess_recorder::events::{RecorderEvent, StopReason};
+use intprocess_recorder::output::{OutputId, OutputKind};
+use intprocess_recorder::settings::{
+    AudioSource, Container, Encoder, Framerate, RateControl, RecorderSettings, ReplayBuffer, Resolution, Split,
+    StdResolution, Window,
+};
+use intprocess_recorder::{Error, InpRecorder};
+
+fn settings() -> RecorderSettings {
+    RecorderSettings::new(
+        Window::new("Game", Some("GameClass".into()), Some("game.exe".into())),
+        StdResolution::_1920x1080p,
+        StdResolution::_1920x1080p,
+        "./recording.mp4",
+    )
+}
+
+fn events(recorder: &mut InpRecorder<FakeBackend>) -> mpsc::Receiver<RecorderEvent> {
+    let (tx, rx) = mpsc::channel();
+    recorder.set_event_handler(move |event| {
+        let _ = tx.send(event);
+    });
+    rx
+}
+
+#[test]
+fn initialization_creates_the_pipeline() {
+    let fake = FakeBackend::new();
+    let recorder = InpRecorder::fake(&fake).unwrap();
+
+    let calls = fake.calls();
+    assert_eq!(calls[..3], ["startup", "add_data_path", "reset_video"]);
+    assert!(calls.contains(&"load_modules".to_string()));
+    assert!(calls.contains(&"output_set_video_encoder(output, video_encoder)".to_string()));
+    assert!(calls.contains(&"output_set_audio_encoder(output, audio_encoder)".to_string()));
+    assert!(calls.contains(&"connect_signals".to_string()));
+
+    assert_eq!(
+        fake.video(),
+        Some(VideoInfo {
+            input_resolution: Resolution::new(1920, 1080),
+            output_resolution: Resolution::new(1920, 1080),
+            framerate: Framerate::new(30, 1),
+        })
+    );
+    assert_eq!(recorder.selected_encoder(), Encoder::OBS_X264);
+    assert_eq!(fake.type_id("video_encoder").as_deref(), Some("obs_x264"));
+    assert_eq!(fake.output_source(0).as_deref(), Some("video_source"));
+    assert_eq!(fake.output_source(2).as_deref(), Some("audio_source2"));
+    assert!(!recorder.is_recording());
+}
+
+#[test]
+fn initialization_failures() {
+    let fake = FakeBackend::new();
+    fake.fail(Failure::Startup);
+    assert!(matches!(InpRecorder::fake(&fake), Err(Error::StartupFailed)));
+
+    let fake = FakeBackend::new();
+    fake.set_encoder_types(&["ffmpeg_aac"]);
+    assert!(matches!(InpRecorder::fake(&fake), Err(Error::NoEncoderAvailable)));
+
+    let fake = FakeBackend::new();
+    fake.fail(Failure::EncoderCreation("obs_x264".into()));
+    assert!(matches!(
+        InpRecorder::fake(&fake),
+        Err(Error::EncoderCreationFailed(Encoder::OBS_X264))
+    ));
+}
+
+#[test]
+fn encoders_are_filtered_by_adapter_and_sorted_by_priority() {
+    let fake = FakeBackend::new();
+    fake.set_adapter("NVIDIA GeForce RTX 4080");
+    fake.set_encoder_types(&[
+        "obs_x264",
+        "h264_texture_amf",
+        "ffmpeg_nvenc",
+        "jim_nvenc",
+        "unknown_encoder",
+    ]);
+    let recorder = InpRecorder::fake(&fake).unwrap();
+
+    assert_eq!(
+        recorder.get_available_encoders(),
+        [Encoder::JIM_NVENC, Encoder::FFMPEG_NVENC, Encoder::OBS_X264]
+    );
+    assert_eq!(recorder.get_adapter_info().name(), "NVIDIA GeForce RTX 4080");
+    assert_eq!(recorder.selected_encoder(), Encoder::JIM_NVENC);
+    assert_eq!(fake.type_id("video_encoder").as_deref(), Some("jim_nvenc"));
+}
+
+#[test]
+fn configure_selects_encoder() {
+    let fake = FakeBackend::new();
+    fake.set_adapter("AMD Radeon RX 7900");
+    fake.set_encoder_types(&["obs_x264", "h264_texture_amf"]);
+    let recorder = InpRecorder::fake(&fake).unwrap();
+
+    // unavailable encoders are rejected without touching the pipeline
+    let mut settings = settings();
+    settings.set_encoder(Encoder::JIM_NVENC);
+    fake.clear_calls();
+    assert!(matches!(
+        recorder.configure(&settings),
+        Err(Error::EncoderUnavailable(Encoder::JIM_NVENC))
+    ));
+    assert!(!fake.calls().iter().any(|c| c.starts_with("video_encoder_create")));
+    assert_eq!(recorder.selected_encoder(), Encoder::AMD_AMF_H264);
+
+    // an explicit encoder replaces the old one
+    settings.set_encoder(Encoder::OBS_X264);
+    settings.set_rate_control(RateControl::CBR(8000));
+    recorder.configure(&settings).unwrap();
+    assert_eq!(recorder.selected_encoder(), Encoder::OBS_X264);
+    assert_eq!(fake.type_id("video_encoder").as_deref(), Some("obs_x264"));
+    let encoder_settings = fake.settings("video_encoder").unwrap();
+    assert_eq!(encoder_settings.get_string("rate_control"), Some("CBR"));
+    assert_eq!(encoder_settings.get_int("bitrate"), Some(8000));
+    assert!(fake.calls().contains(&"encoder_release(video_encoder)".to_string()));
+
+    // without an explicit encoder the best available one is used
+    let mut settings = self::settings();
+    settings.set_rate_control(RateControl::CQP(100));
+    recorder.configure(&settings).unwrap();
+    assert_eq!(recorder.selected_encoder(), Encoder::AMD_AMF_H264);
+    assert_eq!(fake.settings("video_encoder").unwrap().get_int("cqp"), Some(51));
+
+    fake.fail(Failure::EncoderCreation("h264_texture_amf".into()));
+    assert!(matches!(
+        recorder.configure(&settings),
+        Err(Error::EncoderCreationFailed(Encoder::AMD_AMF_H264))
+    ));
+}
+
+#[test]
+fn configure_updates_output_and_sources() {
+    let fake = FakeBackend::new();
+    let mut recorder = InpRecorder::fake(&fake).unwrap();
+
+    // same video settings as on startup -> no reset
+    let mut settings = settings();
+    settings.set_output_path("./game.mp4");
+    fake.clear_calls();
+    recorder.configure(&settings).unwrap();
+    assert!(!fake.calls().contains(&"reset_video".to_string()));
+    assert_eq!(recorder.start_recording(), Ok("./game.mp4".into()));
+    assert_eq!(fake.settings("output").unwrap().get_string("path"), Some("./game.mp4"));
+    recorder.stop_recording();
+    assert_eq!(
+        fake.settings("video_source").unwrap().get_string("window"),
+        Some("Game:GameClass:game.exe")
+    );
+    assert_eq!(fake.output_source(1).as_deref(), Some("audio_source1"));
+    assert_eq!(fake.output_source(2), None);
+    assert_eq!(fake.output_source(3), None);
+
+    settings.set_output_resolution(StdResolution::_1280x720p);
+    settings.set_framerate(Framerate::new(60, 1));
+    settings.set_audio_source(AudioSource::ALL);
+    fake.clear_calls();
+    recorder.configure(&settings).unwrap();
+    assert!(fake.calls().contains(&"reset_video".to_string()));
+    assert_eq!(fake.video().unwrap().output_resolution, Resolution::new(1280, 720));
+    assert_eq!(fake.video().unwrap().framerate, Framerate::new(60, 1));
+    assert_eq!(fake.output_source(0).as_deref(), Some("video_source"));
+    assert_eq!(fake.output_source(1), None);
+    assert_eq!(fake.output_source(2).as_deref(), Some("audio_source2"));
+    assert_eq!(fake.output_source(3).as_deref(), Some("audio_source3"));
+
+    fake.fail(Failure::ResetVideo);
+    settings.set_framerate(Framerate::new(30, 1));
+    assert!(matches!(recorder.configure(&settings), Err(Error::VideoResetFailed)));
+}
+
+#[test]
+fn start_stop_state_machine() {
+    let fake = FakeBackend::new();
+    let mut recorder = InpRecorder::fake(&fake).unwrap();
+    let events = events(&mut recorder);
+    recorder.configure(&settings()).unwrap();
+
+    recorder.start_recording().unwrap();
+    assert!(recorder.is_recording());
+    assert_eq!(events.try_recv(), Ok(RecorderEvent::RecordingStarted));
+
+    // starting twice is a no-op
+    recorder.start_recording().unwrap();
+    assert_eq!(fake.calls().iter().filter(|c| *c == "output_start(output)").count(), 1);
+    assert!(events.try_recv().is_err());
+
+    // settings can't change while recording
+    assert!(matches!(recorder.configure(&settings()), Err(Error::Recording)));
+
+    recorder.stop_recording();
+    assert!(!recorder.is_recording());
+    assert_eq!(
+        events.try_recv(),
+        Ok(RecorderEvent::RecordingStopped {
+            reason: StopReason::Success,
+            error: None
+        })
+    );
+
+    // stopping twice is a no-op
+    recorder.stop_recording();
+    assert_eq!(fake.calls().iter().filter(|c| *c == "output_stop(output)").count(), 1);
+
+    recorder.configure(&settings()).unwrap();
+}
+
+#[test]
+fn start_failure_reports_the_output_error() {
+    let fake = FakeBackend::new();
+    let mut recorder = InpRecorder::fake(&fake).unwrap();
+
+    fake.fail(Failure::OutputStart(Some("disk full".into())));
+    match recorder.start_recording() {
+        Err(Error::OutputStartFailed(Some(error))) => assert_eq!(error, "disk full"),
+        other => panic!("unexpected result: {other:?}"),
+    }
+    assert!(!recorder.is_recording());
+
+    fake.recover(&Failure::OutputStart(Some("disk full".into())));
+    recorder.start_recording().unwrap();
+    assert!(recorder.is_recording());
+}
+
+#[test]
+fn output_stopping_on_its_own() {
+    let fake = FakeBackend::new();
+    let mut recorder = InpRecorder::fake(&fake).unwrap();
+    let events = events(&mut recorder);
+
+    recorder.start_recording().unwrap();
+    fake.stop_output(StopReason::EncodeError, Some("encoder crashed".into()));
+    assert!(!recorder.is_recording());
+
+    let received: Vec<_> = events.try_iter().collect();
+    assert_eq!(
+        received,
+        [
+            RecorderEvent::RecordingStarted,
+            RecorderEvent::EncoderError {
+                error: Some("encoder crashed".into())
+            },
+            RecorderEvent::RecordingStopped {
+                reason: StopReason::EncodeError,
+                error: Some("encoder crashed".into())
+            },
+        ]
+    );
+}
+
+#[test]
+fn dropping_a_handle_releases_its_references() {
+    let fake = FakeBackend::new();
+    let recorder = InpRecorder::fake(&fake).unwrap();
+    recorder.configure(&settings()).unwrap();
+
+    let names = [
+        "output",
+        "video_encoder",
+        "audio_encoder",
+        "video_source",
+        "audio_source1",
+        "audio_source2",
+        "audio_source3",
+    ];
+    for name in names {
+        assert_eq!(fake.references(name), 2, "{name}");
+    }
+
+    drop(recorder);
+    for name in names {
+        assert_eq!(fake.references(name), 1, "{name}");
+    }
+}
+
+fn second_recording() -> OutputKind {
+    OutputKind::Recording { path: "./second.mp4".into() }
+}
+
+#[test]
+fn additional_outputs_run_independently() {
+    let fake = FakeBackend::new();
+    let mut recorder = InpRecorder::fake(&fake).unwrap();
+    let events = events(&mut recorder);
+    recorder.configure(&settings()).unwrap();
+
+    let id = recorder.add_output(&second_recording()).unwrap();
+    assert_ne!(id, OutputId::MAIN);
+    assert_eq!(recorder.outputs(), [OutputId::MAIN, id]);
+
+    recorder.start_output(id).unwrap();
+    assert_eq!(events.try_recv(), Ok(RecorderEvent::OutputStarted { output: id }));
+    assert!(recorder.is_output_active(id).unwrap());
+    assert!(!recorder.is_recording());
+
+    // the encoders are shared, so they can't be replaced while any output is running
+    assert_eq!(recorder.configure(&settings()), Err(Error::Recording));
+
+    recorder.start_recording().unwrap();
+    assert_eq!(events.try_recv(), Ok(RecorderEvent::RecordingStarted));
+    recorder.stop_output(id).unwrap();
+    assert_eq!(
+        events.try_recv(),
+        Ok(RecorderEvent::OutputStopped {
+            output: id,
+            reason: StopReason::Success,
+            error: None
+        })
+    );
+    assert!(recorder.is_recording());
+    recorder.stop_recording();
+    assert!(!recorder.is_output_active(OutputId::MAIN).unwrap());
+}
+
+#[test]
+fn additional_outputs_share_the_encoders() {
+    let fake = FakeBackend::new();
+    let mut recorder = InpRecorder::fake(&fake).unwrap();
+    let id = recorder.add_output(&second_recording()).unwrap();
+    let name = fake.output_name(id).unwrap();
+
+    assert_eq!(fake.type_id(&name).as_deref(), Some("ffmpeg_muxer"));
+    assert_eq!(fake.settings(&name).unwrap().get_string("path"), Some("./second.mp4"));
+    let calls = fake.calls();
+    assert!(calls.contains(&format!("output_set_video_encoder({name}, video_encoder)")));
+    assert!(calls.contains(&format!("output_set_audio_encoder({name}, audio_encoder)")));
+
+    // a new video encoder gets assigned to every output
+    fake.clear_calls();
+    recorder.configure(&settings()).unwrap();
+    let calls = fake.calls();
+    assert!(calls.contains(&"output_set_video_encoder(output, video_encoder)".to_string()));
+    assert!(calls.contains(&format!("output_set_video_encoder({name}, video_encoder)")));
+
+    recorder.remove_output(id).unwrap();
+    assert_eq!(fake.references(&name), 0);
+    assert_eq!(recorder.remove_output(id), Err(Error::UnknownOutput(id)));
+    assert_eq!(recorder.start_output(id), Err(Error::UnknownOutput(id)));
+    assert_eq!(
+        recorder.remove_output(OutputId::MAIN),
+        Err(Error::UnknownOutput(OutputId::MAIN))
+    );
+}
+
+#[test]
+fn dropping_a_handle_stops_and_releases_its_outputs() {
+    let fake = FakeBackend::new();
+    let mut recorder = InpRecorder::fake(&fake).unwrap();
+    let id = recorder.add_output(&second_recording()).unwrap();
+    let name = fake.output_name(id).unwrap();
+    recorder.start_output(id).unwrap();
+
+    drop(recorder);
+    let calls = fake.calls();
+    assert!(calls.contains(&format!("output_stop({name})")));
+    assert!(calls.contains(&format!("disconnect_output_signals({name})")));
+    assert_eq!(fake.references(&name), 0);
+}
+
+#[test]
+fn replay_buffer() {
+    let fake = FakeBackend::new();
+    let mut recorder = InpRecorder::fake(&fake).unwrap();
+    let events = events(&mut recorder);
+
+    recorder.configure(&settings()).unwrap();
+    assert_eq!(recorder.start_replay_buffer(), Err(Error::ReplayBufferNotConfigured));
+    assert_eq!(recorder.save_replay(), Err(Error::ReplayBufferNotActive));
+
+    let mut settings = settings();
+    settings.set_output_path("./videos/game.mkv");
+    settings.set_container(Container::MKV);
+    settings.set_replay_buffer(ReplayBuffer::new(30, 500));
+    recorder.configure(&settings).unwrap();
+
+    let id = recorder.start_replay_buffer().unwrap();
+    let name = fake.output_name(id).unwrap();
+    assert_eq!(events.try_recv(), Ok(RecorderEvent::OutputStarted { output: id }));
+    assert_eq!(fake.type_id(&name).as_deref(), Some("replay_buffer"));
+    let data = fake.settings(&name).unwrap();
+    assert_eq!(data.get_int("max_time_sec"), Some(30));
+    assert_eq!(data.get_int("max_size_mb"), Some(500));
+    assert_eq!(data.get_string("directory"), Some("./videos"));
+    assert_eq!(data.get_string("extension"), Some("mkv"));
+    // independent of the main output
+    assert!(!recorder.is_recording());
+    assert_eq!(recorder.start_replay_buffer(), Ok(id));
+
+    recorder.save_replay().unwrap();
+    assert_eq!(
+        events.try_recv(),
+        Ok(RecorderEvent::ReplaySaved {
+            path: "./videos/game_replay_%CCYY-%MM-%DD_%hh-%mm-%ss.mkv".into()
+        })
+    );
+
+    recorder.stop_replay_buffer();
+    assert_eq!(
+        events.try_recv(),
+        Ok(RecorderEvent::OutputStopped {
+            output: id,
+            reason: StopReason::Success,
+            error: None
+        })
+    );
+    assert_eq!(recorder.save_replay(), Err(Error::ReplayBufferNotActive));
+
+    // restarting reuses the output with the latest settings
+    settings.set_replay_buffer(ReplayBuffer::new(60, 500));
+    recorder.configure(&settings).unwrap();
+    assert_eq!(recorder.start_replay_buffer(), Ok(id));
+    assert_eq!(fake.settings(&name).unwrap().get_int("max_time_sec"), Some(60));
+
+    drop(recorder);
+    assert!(fake.calls().contains(&format!("disconnect_replay_signals({name})")));
+    assert_eq!(fake.references(&name), 0);
+}
+
+#[test]
+fn pause_and_resume() {
+    let fake = FakeBackend::new();
+    let mut recorder = InpRecorder::fake(&fake).unwrap();
+    recorder.configure(&settings()).unwrap();
+    assert_eq!(recorder.pause_recording(), Err(Error::NotRecording));
+
+    recorder.start_recording().unwrap();
+    fake.render_frames(10);
+    recorder.pause_recording().unwrap();
+    assert!(recorder.is_paused());
+    // pausing twice is fine
+    recorder.pause_recording().unwrap();
+    fake.render_frames(10);
+    std::thread::sleep(Duration::from_millis(20));
+
+    let paused = recorder.status();
+    assert!(paused.recording && paused.paused);
+    assert_eq!(paused.frames.output, 10);
+    assert!(paused.paused_duration >= Duration::from_millis(20));
+
+    recorder.resume_recording().unwrap();
+    assert!(!recorder.is_paused());
+    let resumed = recorder.status();
+    assert!(!resumed.paused);
+    assert!(resumed.paused_duration >= paused.paused_duration);
+    // no longer counts up
+    std::thread::sleep(Duration::from_millis(20));
+    assert_eq!(
+        recorder.status().paused_duration.as_millis(),
+        resumed.paused_duration.as_millis()
+    );
+
+    // stopping while paused ends the pause, a new recording starts over
+    recorder.pause_recording().unwrap();
+    recorder.stop_recording();
+    assert!(!recorder.is_paused());
+    assert_eq!(recorder.resume_recording(), Err(Error::NotRecording));
+    recorder.start_recording().unwrap();
+    assert_eq!(recorder.status().paused_duration, Duration::ZERO);
+}
+
+#[test]
+fn pausing_requires_the_encoders_to_itself() {
+    let fake = FakeBackend::new();
+    let mut recorder = InpRecorder::fake(&fake).unwrap();
+    recorder.configure(&settings()).unwrap();
+    let id = recorder.add_output(&second_recording()).unwrap();
+
+    recorder.start_recording().unwrap();
+    recorder.start_output(id).unwrap();
+    assert_eq!(recorder.pause_recording(), Err(Error::SharedEncoders));
+    recorder.stop_output(id).unwrap();
+
+    recorder.pause_recording().unwrap();
+    assert_eq!(recorder.start_output(id), Err(Error::SharedEncoders));
+    recorder.resume_recording().unwrap();
+    recorder.start_output(id).unwrap();
+}
+
+#[test]
+fn pause_failures() {
+    let fake = FakeBackend::new();
+    let mut recorder = InpRecorder::fake(&fake).unwrap();
+    recorder.configure(&settings()).unwrap();
+    recorder.start_recording().unwrap();
+
+    fake.fail(Failure::PauseUnsupported("ffmpeg_muxer".into()));
+    assert_eq!(recorder.pause_recording(), Err(Error::PauseUnsupported));
+    fake.recover(&Failure::PauseUnsupported("ffmpeg_muxer".into()));
+
+    fake.fail(Failure::Pause);
+    assert_eq!(recorder.pause_recording(), Err(Error::PauseFailed(None)));
+    assert!(!recorder.is_paused());
+    assert!(!recorder.status().paused);
+}
+
+#[test]
+fn container_sets_up_the_muxer() {
+    let fake = FakeBackend::new();
+    let mut recorder = InpRecorder::fake(&fake).unwrap();
+
+    recorder.configure(&settings()).unwrap();
+    recorder.start_recording().unwrap();
+    recorder.stop_recording();
+    let output = fake.settings("output").unwrap();
+    assert_eq!(output.get_string("path"), Some("./recording.mp4"));
+    assert_eq!(output.get_string("muxer_settings"), Some(""));
+
+    // a missing extension gets added
+    let mut settings = settings();
+    settings.set_output_path("./videos/game");
+    settings.set_container(Container::FRAGMENTED_MP4);
+    recorder.configure(&settings).unwrap();
+    recorder.start_recording().unwrap();
+    recorder.stop_recording();
+    let output = fake.settings("output").unwrap();
+    assert_eq!(output.get_string("path"), Some("./videos/game.mp4"));
+    assert_eq!(
+        output.get_string("muxer_settings"),
+        Some("movflags=frag_keyframe+empty_moov+delay_moov")
+    );
+
+    settings.set_output_path("./videos/game.MKV");
+    settings.set_container(Container::MKV);
+    recorder.configure(&settings).unwrap();
+    recorder.start_recording().unwrap();
+    recorder.stop_recording();
+    let output = fake.settings("output").unwrap();
+    assert_eq!(output.get_string("path"), Some("./videos/game.MKV"));
+    assert_eq!(output.get_string("muxer_settings"), Some(""));
+
+    // a different extension is rejected before anything changes
+    settings.set_output_path("./videos/game.mp4");
+    assert_eq!(
+        recorder.configure(&settings),
+        Err(Error::ExtensionMismatch {
+            path: "./videos/game.mp4".into(),
+            container: Container::MKV
+        })
+    );
+    assert_eq!(recorder.start_recording(), Ok("./videos/game_1.MKV".into()));
+}
+
+#[test]
+fn container_has_to_fit_the_encoder() {
+    let fake = FakeBackend::new();
+    fake.set_adapter("NVIDIA GeForce RTX 4080");
+    fake.set_encoder_types(&["obs_x264", "jim_av1_nvenc"]);
+    let recorder = InpRecorder::fake(&fake).unwrap();
+    assert_eq!(recorder.selected_encoder(), Encoder::JIM_AV1);
+
+    let mut settings = settings();
+    settings.set_output_path("./game.flv");
+    settings.set_container(Container::FLV);
+    settings.set_encoder(Encoder::JIM_AV1);
+    assert_eq!(
+        recorder.configure(&settings),
+        Err(Error::IncompatibleContainer {
+            encoder: Encoder::JIM_AV1,
+            container: Container::FLV
+        })
+    );
+
+    // without an explicit encoder the best one the container can store is used
+    let mut settings = self::settings();
+    settings.set_output_path("./game.flv");
+    settings.set_container(Container::FLV);
+    recorder.configure(&settings).unwrap();
+    assert_eq!(recorder.selected_encoder(), Encoder::OBS_X264);
+}
+
+#[test]
+fn hybrid_mp4_records_with_its_own_output() {
+    let fake = FakeBackend::new();
+    let mut recorder = InpRecorder::fake(&fake).unwrap();
+    let events = events(&mut recorder);
+    assert_eq!(fake.type_id("hybrid_output").as_deref(), Some("mp4_output"));
+    assert!(fake
+        .calls()
+        .contains(&"output_set_video_encoder(hybrid_output, video_encoder)".to_string()));
+
+    let mut settings = settings();
+    settings.set_container(Container::HYBRID_MP4);
+    recorder.configure(&settings).unwrap();
+
+    fake.clear_calls();
+    recorder.start_recording().unwrap();
+    assert_eq!(
+        fake.settings("hybrid_output").unwrap().get_string("path"),
+        Some("./recording.mp4")
+    );
+    assert!(fake.calls().contains(&"output_start(hybrid_output)".to_string()));
+    assert_eq!(events.try_recv(), Ok(RecorderEvent::RecordingStarted));
+    assert!(recorder.is_recording());
+    assert!(recorder.is_output_active(OutputId::MAIN).unwrap());
+    recorder.stop_recording();
+    assert!(fake.calls().contains(&"output_stop(hybrid_output)".to_string()));
+
+    // back to the ffmpeg output
+    recorder.configure(&self::settings()).unwrap();
+    fake.clear_calls();
+    recorder.start_recording().unwrap();
+    assert!(fake.calls().contains(&"output_start(output)".to_string()));
+
+    drop(recorder);
+    assert_eq!(fake.references("hybrid_output"), 1);
+}
+
+#[test]
+fn container_belongs_to_the_handle() {
+    let fake = FakeBackend::new();
+    let mut recorder = InpRecorder::fake(&fake).unwrap();
+    let other = InpRecorder::fake_handle(&fake).unwrap();
+
+    let mut settings = settings();
+    settings.set_container(Container::HYBRID_MP4);
+    recorder.configure(&settings).unwrap();
+    other.configure(&self::settings()).unwrap();
+
+    fake.clear_calls();
+    recorder.start_recording().unwrap();
+    assert!(fake.calls().contains(&"output_start(hybrid_output)".to_string()));
+}
+
+#[test]
+fn recording_of_another_handle_uses_its_container() {
+    let fake = FakeBackend::new();
+    let mut recorder = InpRecorder::fake(&fake).unwrap();
+    let mut other = InpRecorder::fake_handle(&fake).unwrap();
+
+    let mut settings = settings();
+    settings.set_container(Container::HYBRID_MP4);
+    recorder.configure(&settings).unwrap();
+    recorder.start_recording().unwrap();
+
+    // the other handle is configured for the ffmpeg output, but sees the recording of the hybrid one
+    assert!(other.is_recording());
+    assert!(other.status().recording);
+    assert!(other.is_output_active(OutputId::MAIN).unwrap());
+    other.pause_recording().unwrap();
+    assert!(fake.calls().contains(&"output_pause(hybrid_output)".to_string()));
+    assert!(recorder.is_paused());
+    other.resume_recording().unwrap();
+
+    fake.clear_calls();
+    other.stop_recording();
+    assert!(fake.calls().contains(&"output_stop(hybrid_output)".to_string()));
+    assert!(!recorder.is_recording());
+
+    // once stopped, each handle starts the output of its own container again
+    fake.clear_calls();
+    other.start_recording().unwrap();
+    assert!(fake.calls().contains(&"output_start(output)".to_string()));
+}
+
+#[test]
+fn hybrid_mp4_needs_a_recent_libobs() {
+    let fake = FakeBackend::new();
+    fake.set_output_types(&["ffmpeg_muxer", "replay_buffer"]);
+    let recorder = InpRecorder::fake(&fake).unwrap();
+    assert_eq!(fake.type_id("hybrid_output"), None);
+
+    let mut settings = settings();
+    settings.set_container(Container::HYBRID_MP4);
+    assert_eq!(
+        recorder.configure(&settings),
+        Err(Error::ContainerUnavailable(Container::HYBRID_MP4))
+    );
+}
+
+#[test]
+fn split_sets_up_the_muxer() {
+    let fake = FakeBackend::new();
+    let mut recorder = InpRecorder::fake(&fake).unwrap();
+    let mut record = |settings: &RecorderSettings| {
+        recorder.configure(settings).unwrap();
+        recorder.start_recording().unwrap();
+        recorder.stop_recording();
+    };
+
+    let mut settings = settings();
+    settings.set_output_path("./videos/game_{counter}.mkv");
+    settings.set_container(Container::MKV);
+    settings.set_split(Split::new(900, 0));
+    record(&settings);
+    let data = fake.settings("output").unwrap();
+    assert_eq!(data.get_string("path"), Some("./videos/game_001.mkv"));
+    assert_eq!(data.get_bool("split_file"), Some(true));
+    assert_eq!(data.get_int("max_time_sec"), Some(900));
+    assert_eq!(data.get_int("max_size_mb"), Some(0));
+    assert_eq!(data.get_string("directory"), Some("./videos"));
+    // named after the file the recording starts with
+    assert_eq!(data.get_string("format"), Some("game_001_%CCYY-%MM-%DD_%hh-%mm-%ss"));
+    assert_eq!(data.get_string("extension"), Some("mkv"));
+
+    let mut split = Split::new(0, 2048);
+    split.set_name_format("part_%hh-%mm");
+    settings.set_split(split);
+    record(&settings);
+    let data = fake.settings("output").unwrap();
+    assert_eq!(data.get_int("max_time_sec"), Some(0));
+    assert_eq!(data.get_int("max_size_mb"), Some(2048));
+    assert_eq!(data.get_string("format"), Some("part_%hh-%mm"));
+
+    // the muxer keeps the last settings, so splitting has to be turned off explicitly
+    record(&self::settings());
+    assert_eq!(fake.settings("output").unwrap().get_bool("split_file"), Some(false));
+}
+
+#[test]
+fn split_recording() {
+    let fake = FakeBackend::new();
+    let mut recorder = InpRecorder::fake(&fake).unwrap();
+    let events = events(&mut recorder);
+
+    let mut settings = settings();
+    settings.set_output_path("./videos/game.mp4");
+    settings.set_split(Split::new(0, 0));
+    recorder.configure(&settings).unwrap();
+    assert_eq!(recorder.split_recording(), Err(Error::NotRecording));
+
+    recorder.start_recording().unwrap();
+    assert_eq!(events.try_recv(), Ok(RecorderEvent::RecordingStarted));
+    recorder.split_recording().unwrap();
+    let next = "./videos/game_%CCYY-%MM-%DD_%hh-%mm-%ss_2.mp4".to_string();
+    assert_eq!(
+        events.try_recv(),
+        Ok(RecorderEvent::SegmentFinished {
+            path: "./videos/game.mp4".into(),
+            next: Some(next.clone())
+        })
+    );
+
+    // the last file is reported before the recording stops
+    recorder.stop_recording();
+    assert_eq!(
+        events.try_recv(),
+        Ok(RecorderEvent::SegmentFinished { path: next, next: None })
+    );
+    assert_eq!(
+        events.try_recv(),
+        Ok(RecorderEvent::RecordingStopped {
+            reason: StopReason::Success,
+            error: None
+        })
+    );
+}
+
+#[test]
+fn split_recording_needs_the_settings() {
+    let fake = FakeBackend::new();
+    let mut recorder = InpRecorder::fake(&fake).unwrap();
+    let events = events(&mut recorder);
+
+    recorder.configure(&settings()).unwrap();
+    recorder.start_recording().unwrap();
+    assert_eq!(recorder.split_recording(), Err(Error::SplitNotConfigured));
+    assert!(recorder.is_recording());
+
+    // no segments without splitting
+    recorder.stop_recording();
+    assert_eq!(events.try_recv(), Ok(RecorderEvent::RecordingStarted));
+    assert!(matches!(events.try_recv(), Ok(RecorderEvent::RecordingStopped { .. })));
+}
+
+#[test]
+fn output_path_is_filled_in_when_recording_starts() {
+    let fake = FakeBackend::new();
+    let mut recorder = InpRecorder::fake(&fake).unwrap();
+
+    let mut settings = settings();
+    settings.set_output_path("./videos/{process}/{window}_{encoder}");
+    recorder.configure(&settings).unwrap();
+    assert!(fake.directories().is_empty());
+
+    let path = recorder.start_recording().unwrap();
+    assert_eq!(path, "./videos/game/Game_obs_x264.mp4");
+    assert_eq!(fake.settings("output").unwrap().get_string("path"), Some(path.as_str()));
+    assert_eq!(fake.directories(), ["./videos/game"]);
+    // already recording
+    assert_eq!(recorder.start_recording(), Ok(path));
+    recorder.stop_recording();
+
+    // the previous recording is not overwritten
+    assert_eq!(
+        recorder.start_recording(),
+        Ok("./videos/game/Game_obs_x264_1.mp4".into())
+    );
+    recorder.stop_recording();
+
+    settings.set_output_path("./{date}/{time}_{counter}.mp4");
+    recorder.configure(&settings).unwrap();
+    let path = recorder.start_recording().unwrap();
+    let (date, file) = path.trim_start_matches("./").split_once('/').unwrap();
+    assert_eq!(date.len(), "2024-05-01".len());
+    assert!(file.ends_with("_001.mp4"), "{path}");
+    assert!(!path.contains(['{', '}', ':']), "{path}");
+}
+
+#[test]
+fn output_path_belongs_to_the_handle() {
+    let fake = FakeBackend::new();
+    let mut recorder = InpRecorder::fake(&fake).unwrap();
+    let other = InpRecorder::fake_handle(&fake).unwrap();
+
+    let mut settings = settings();
+    settings.set_output_path("./first.mp4");
+    recorder.configure(&settings).unwrap();
+    settings.set_output_path("./second.mp4");
+    other.configure(&settings).unwrap();
+
+    assert_eq!(recorder.start_recording(), Ok("./first.mp4".into()));
+}
+
+#[test]
+fn output_path_errors() {
+    let fake = FakeBackend::new();
+    let mut recorder = InpRecorder::fake(&fake).unwrap();
+
+    let mut settings = settings();
+    settings.set_output_path("./{game}.mp4");
+    assert_eq!(
+        recorder.configure(&settings),
+        Err(Error::InvalidPathTemplate("./{game}.mp4".into()))
+    );
+
+    settings.set_output_path("./videos/{window}.mp4");
+    recorder.configure(&settings).unwrap();
+    fake.fail(Failure::CreateDirectory);
+    assert_eq!(
+        recorder.start_recording(),
+        Err(Error::DirectoryCreationFailed("./videos".into()))
+    );
+    assert!(!recorder.is_recording());
+
+    fake.recover(&Failure::CreateDirectory);
+    fake.add_file("./videos/Game.mp4");
+    assert_eq!(recorder.start_recording(), Ok("./videos/Game_1.mp4".into()));
+}
+
+#[test]
+fn remux() {
+    let fake = FakeBackend::new();
+    let recorder = InpRecorder::fake(&fake).unwrap();
+    fake.add_file("./game.mkv");
+
+    let mut progress = Vec::new();
+    recorder
+        .remux("./game.mkv", "./videos/game.mp4", |percent| progress.push(percent))
+        .unwrap();
+    assert_eq!(progress, [0.0, 50.0, 100.0]);
+    assert!(fake
+        .calls()
+        .contains(&"remux(./game.mkv, ./videos/game.mp4)".to_string()));
+    assert_eq!(fake.directories(), ["./videos"]);
+
+    assert_eq!(
+        recorder.remux("./missing.mkv", "./missing.mp4", |_| {}),
+        Err(Error::FileNotFound("./missing.mkv".into()))
+    );
+    assert_eq!(
+        recorder.remux("./game.mkv", "./game.mkv", |_| {}),
+        Err(Error::RemuxSameFile("./game.mkv".into()))
+    );
+    // written differently, but still the same file
+    assert_eq!(
+        recorder.remux("./game.mkv", "game.mkv", |_| {}),
+        Err(Error::RemuxSameFile("./game.mkv".into()))
+    );
+    fake.fail(Failure::Remux);
+    assert_eq!(
+        recorder.remux("./game.mkv", "./game2.mp4", |_| {}),
+        Err(Error::RemuxFailed("./game.mkv".into()))
+    );
+}
+
+#[test]
+fn start_remux_reports_progress() {
+    let fake = FakeBackend::new();
+    let mut recorder = InpRecorder::fake(&fake).unwrap();
+    let events = events(&mut recorder);
+    fake.add_file("./game.mkv");
+
+    recorder.start_remux("./game.mkv", "./game.mp4").unwrap();
+    for percent in [0, 50, 100] {
+        assert_eq!(
+            events.try_recv(),
+            Ok(RecorderEvent::RemuxProgress {
+                output: "./game.mp4".into(),
+                percent
+            })
+        );
+    }
+    assert_eq!(
+        events.try_recv(),
+        Ok(RecorderEvent::RemuxFinished {
+            input: "./game.mkv".into(),
+            output: "./game.mp4".into(),
+            success: true
+        })
+    );
+
+    fake.fail(Failure::Remux);
+    recorder.start_remux("./game.mkv", "./game2.mp4").unwrap();
+    let finished = events.try_iter().last();
+    assert_eq!(
+        finished,
+        Some(RecorderEvent::RemuxFinished {
+            input: "./game.mkv".into(),
+            output: "./game2.mp4".into(),
+            success: false
+        })
+    );
+}
+
+#[test]
+fn remux_on_stop() {
+    let fake = FakeBackend::new();
+    let mut recorder = InpRecorder::fake(&fake).unwrap();
+    let events = events(&mut recorder);
+
+    let mut settings = settings();
+    settings.set_output_path("./game");
+    settings.set_container(Container::MKV);
+    settings.set_remux_on_stop(true);
+    recorder.configure(&settings).unwrap();
+    fake.add_file("./game.mp4");
+
+    assert_eq!(recorder.start_recording(), Ok("./game.mkv".into()));
+    recorder.stop_recording();
+    // the fake remuxes right away, before the stop is reported
+    let reported: Vec<_> = events.try_iter().collect();
+    assert!(matches!(reported.last(), Some(RecorderEvent::RecordingStopped { .. })));
+    // an existing file is not overwritten
+    assert_eq!(
+        reported[reported.len() - 2],
+        RecorderEvent::RemuxFinished {
+            input: "./game.mkv".into(),
+            output: "./game_1.mp4".into(),
+            success: true
+        }
+    );
+
+    // a recording that stops on its own is remuxed as well
+    assert_eq!(recorder.start_recording(), Ok("./game_1.mkv".into()));
+    fake.stop_output(StopReason::Error, Some("disk full".into()));
+    assert!(events.try_iter().any(|event| event
+        == RecorderEvent::RemuxFinished {
+            input: "./game_1.mkv".into(),
+            output: "./game_1_1.mp4".into(),
+            success: true
+        }));
+
+    // nothing to do for an MP4
+    settings.set_container(Container::MP4);
+    settings.set_output_path("./other.mp4");
+    recorder.configure(&settings).unwrap();
+    fake.clear_calls();
+    recorder.start_recording().unwrap();
+    recorder.stop_recording();
+    assert!(!fake.calls().iter().any(|call| call.starts_with("remux")));
+
+    settings.set_split(Split::new(60, 0));
+    assert_eq!(recorder.configure(&settings), Err(Error::RemuxSplitRecording));
+}
+
+#[test]
+fn markers() {
+    let fake = FakeBackend::new();
+    let mut recorder = InpRecorder::fake(&fake).unwrap();
+
+    let mut settings = settings();
+    settings.set_output_path("./videos/game.mkv");
+    settings.set_container(Container::MKV);
+    recorder.configure(&settings).unwrap();
+    assert_eq!(recorder.add_marker("kill"), Err(Error::NotRecording));
+
+    recorder.start_recording().unwrap();
+    // 30 fps
+    fake.render_frames(60);
+    let kill = recorder.add_marker("kill").unwrap();
+    assert_eq!(kill.label, "kill");
+    assert_eq!(kill.time, Duration::from_secs(2));
+
+    // the time stands still while paused
+    recorder.pause_recording().unwrap();
+    fake.render_frames(300);
+    let baron = recorder.add_marker("baron=dead").unwrap();
+    recorder.resume_recording().unwrap();
+    assert_eq!(baron.time, Duration::from_secs(2));
+    fake.render_frames(45);
+
+    // MKV gets no chapters, only the sidecar
+    assert!(fake.calls().contains(&"output_add_chapter(output)".to_string()));
+    assert!(!fake.calls().iter().any(|call| call.starts_with("chapter")));
+
+    assert_eq!(fake.file("./videos/game.markers.json"), None);
+    recorder.stop_recording();
+    let sidecar: serde_json::Value = serde_json::from_str(&fake.file("./videos/game.markers.json").unwrap()).unwrap();
+    assert_eq!(sidecar["recording"], "game.mkv");
+    assert_eq!(sidecar["markers"][0]["label"], "kill");
+    assert_eq!(sidecar["markers"][0]["time_ms"], 2000);
+    assert_eq!(sidecar["markers"][1]["label"], "baron=dead");
+    assert_eq!(sidecar["markers"].as_array().unwrap().len(), 2);
+    // the running time of the whole recording
+    assert_eq!(sidecar["duration_ms"], 3500);
+
+    // the next recording starts without markers and writes no sidecar if it has none
+    fake.clear_calls();
+    recorder.start_recording().unwrap();
+    recorder.stop_recording();
+    assert!(!fake.calls().iter().any(|call| call.starts_with("write_file")));
+}
+
+#[test]
+fn markers_of_hybrid_mp4_become_chapters() {
+    let fake = FakeBackend::new();
+    let mut recorder = InpRecorder::fake(&fake).unwrap();
+
+    let mut settings = settings();
+    settings.set_container(Container::HYBRID_MP4);
+    recorder.configure(&settings).unwrap();
+    recorder.start_recording().unwrap();
+    recorder.add_marker("dragon").unwrap();
+    assert!(fake.calls().contains(&"chapter(dragon)".to_string()));
+
+    // the sidecar is written anyway
+    recorder.stop_recording();
+    assert!(fake.file("./recording.markers.json").unwrap().contains("dragon"));
+}
+
+#[test]
+fn markers_of_a_recording_that_stopped_on_its_own() {
+    let fake = FakeBackend::new();
+    let mut recorder = InpRecorder::fake(&fake).unwrap();
+    recorder.configure(&settings()).unwrap();
+
+    recorder.start_recording().unwrap();
+    recorder.add_marker("kill").unwrap();
+    fake.stop_output(StopReason::Disconnected, None);
+    assert!(!recorder.is_recording());
+    assert!(fake.file("./recording.markers.json").unwrap().contains("kill"));
+
+    // the next recording has markers of its own
+    assert_eq!(recorder.start_recording(), Ok("./recording_1.mp4".into()));
+    recorder.add_marker("ace").unwrap();
+    recorder.stop_recording();
+    let sidecar = fake.file("./recording_1.markers.json").unwrap();
+    assert!(sidecar.contains("ace") && !sidecar.contains("kill"));
+}
+
+#[test]
+fn markers_belong_to_the_recording_of_all_handles() {
+    let fake = FakeBackend::new();
+    let mut recorder = InpRecorder::fake(&fake).unwrap();
+    let mut other = InpRecorder::fake_handle(&fake).unwrap();
+    recorder.configure(&settings()).unwrap();
+
+    assert_eq!(recorder.start_recording(), Ok("./recording.mp4".into()));
+    assert_eq!(other.start_recording(), Ok("./recording.mp4".into()));
+    recorder.add_marker("kill").unwrap();
+    other.add_marker("ace").unwrap();
+    other.stop_recording();
+    let sidecar = fake.file("./recording.markers.json").unwrap();
+    assert!(sidecar.contains("kill") && sidecar.contains("ace"));
+}
+
+#[test]
+fn late_stop_of_the_last_recording() {
+    let fake = FakeBackend::new();
+    let mut recorder = InpRecorder::fake(&fake).unwrap();
+    recorder.configure(&settings()).unwrap();
+
+    fake.delay_stops();
+    recorder.start_recording().unwrap();
+    recorder.add_marker("kill").unwrap();
+    recorder.stop_recording();
+    // the next recording starts before libobs reports that the last one has stopped
+    assert_eq!(recorder.start_recording(), Ok("./recording_1.mp4".into()));
+    recorder.add_marker("ace").unwrap();
+    fake.report_stops();
+    let sidecar = fake.file("./recording.markers.json").unwrap();
+    assert!(sidecar.contains("kill") && !sidecar.contains("ace"));
+    assert_eq!(fake.file("./recording_1.markers.json"), None);
+
+    recorder.stop_recording();
+    let sidecar = fake.file("./recording_1.markers.json").unwrap();
+    assert!(sidecar.contains("ace") && !sidecar.contains("kill"));
+}
@@ -0,0 +1,104 @@
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use intprocess_recorder::backend::{Failure, FakeBackend};
+use intprocess_recorder::events::RecorderEvent;
+use intprocess_recorder::settings::{Encoder, RecorderSettings, StdResolution, Window};
+use intprocess_recorder::{Error, ThreadedRecorder};
+
+fn settings() -> RecorderSettings {
+    RecorderSettings::new(
+        Window::new("Game", Some("GameClass".into()), Some("game.exe".into())),
+        StdResolution::_1920x1080p,
+        StdResolution::_1920x1080p,
+        "./recording.mp4",
+    )
+}
+
+fn assert_send_sync<T: Send + Sync>() {}
+
+#[test]
+fn handle_is_send_and_sync() {
+    assert_send_sync::<ThreadedRecorder>();
+    assert_send_sync::<ThreadedRecorder<FakeBackend>>();
+}
+
+#[test]
+fn calls_run_on_the_libobs_thread() {
+    let fake = FakeBackend::new();
+    let recorder = ThreadedRecorder::fake(&fake).unwrap();
+    let (tx, events) = mpsc::channel();
+    recorder
+        .set_event_handler(move |event| {
+            // events of the fake are reported on the thread that started the output
+            _ = tx.send((event, thread::current().name().map(String::from)));
+        })
+        .unwrap();
+
+    let other = recorder.clone();
+    thread::spawn(move || {
+        other.configure(&settings()).unwrap();
+        other.start_recording().unwrap();
+    })
+    .join()
+    .unwrap();
+
+    assert_eq!(recorder.is_recording(), Ok(true));
+    assert_eq!(recorder.selected_encoder(), Ok(Encoder::OBS_X264));
+    assert_eq!(
+        events.recv_timeout(Duration::from_secs(5)).unwrap(),
+        (RecorderEvent::RecordingStarted, Some("libobs".into()))
+    );
+
+    recorder.stop_recording().unwrap();
+    assert!(!recorder.status().unwrap().recording);
+}
+
+#[test]
+fn shutdown_waits_for_other_handles() {
+    let fake = FakeBackend::new();
+    let recorder = ThreadedRecorder::fake(&fake).unwrap();
+    let other = recorder.clone();
+
+    assert_eq!(other.shutdown(), Err(Error::RecorderInUse));
+    assert!(!fake.calls().contains(&"shutdown".to_string()));
+
+    recorder.shutdown().unwrap();
+    assert_eq!(fake.calls().last().map(String::as_str), Some("shutdown"));
+    // the recorder has been dropped before libobs was shut down
+    assert_eq!(fake.references("output"), 1);
+}
+
+#[test]
+fn dropping_the_last_handle_shuts_down() {
+    let fake = FakeBackend::new();
+    let recorder = ThreadedRecorder::fake(&fake).unwrap();
+    drop(recorder.clone());
+    assert!(!fake.calls().contains(&"shutdown".to_string()));
+
+    drop(recorder);
+    assert_eq!(fake.calls().last().map(String::as_str), Some("shutdown"));
+}
+
+#[test]
+fn initialization_failure_is_reported() {
+    let fake = FakeBackend::new();
+    fake.fail(Failure::Startup);
+    assert!(matches!(ThreadedRecorder::fake(&fake), Err(Error::StartupFailed)));
+}
+
+#[test]
+fn a_panic_on_the_libobs_thread_is_reported() {
+    let fake = FakeBackend::new();
+    let recorder = ThreadedRecorder::fake(&fake).unwrap();
+    // the fake reports events on the libobs thread
+    recorder
+        .set_event_handler(|event| panic!("unexpected event: {event:?}"))
+        .unwrap();
+    recorder.configure(&settings()).unwrap();
+
+    assert_eq!(recorder.start_recording(), Err(Error::ThreadGone));
+    assert_eq!(recorder.is_recording(), Err(Error::ThreadGone));
+    assert_eq!(recorder.shutdown(), Err(Error::ThreadGone));
+}
@@ -0,0 +1,403 @@
+//! Non-blocking counterpart of `IpcLinkMaster` for use with tokio.
+
+use std::collections::HashMap;
+use std::path::{Component, Path};
+use std::pin::Pin;
+use std::process::{ExitStatus, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use intprocess_recorder::events::RecorderEvent;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines};
+use tokio::process::{Child, ChildStdout, Command};
+use tokio::sync::{mpsc, oneshot};
+
+use crate::close::{self, CloseReport, StageOutcome};
+use crate::{
+    forward_log, log_stray_line, Error, ExitStatusDisplay, Hello, IpcCommand, IpcMessage, IpcResponse, Request,
+    RequestId, Timeouts,
+};
+
+type Pending = Arc<Mutex<HashMap<RequestId, oneshot::Sender<IpcResponse>>>>;
+type Subscribers = Arc<Mutex<Vec<mpsc::UnboundedSender<RecorderEvent>>>>;
+
+/// Talks to the recorder process through non-blocking stdio (always `Transport::Stdio` and `Framing::JsonLines`).
+///
+/// All methods take `&self`, so multiple commands can be awaited concurrently from different tasks.
+/// Every future is cancellation-safe: dropping it before the response arrives just discards the response.
+/// Has to be created inside a tokio runtime since it spawns tasks for reading and writing.
+#[derive(Debug)]
+pub struct AsyncIpcLinkMaster {
+    // serialized by a separate task, so a cancelled future can never leave a half-written request behind
+    requests: mpsc::UnboundedSender<Request>,
+    pending: Pending,
+    subscribers: Subscribers,
+    child_process: Arc<tokio::sync::Mutex<Child>>,
+    timeouts: Mutex<Timeouts>,
+    next_id: AtomicU64,
+    // `close` has already run, so there is nothing left to do on drop
+    closed: AtomicBool,
+    // the recorder has answered `Exit`, so only its process is left to reap
+    exiting: AtomicBool,
+}
+
+impl AsyncIpcLinkMaster {
+    pub async fn new(executable: impl AsRef<Path>) -> Result<Self, Error> {
+        Self::with_timeouts(executable, Timeouts::default()).await
+    }
+
+    pub async fn with_timeouts(executable: impl AsRef<Path>, timeouts: Timeouts) -> Result<Self, Error> {
+        let executable = executable.as_ref().canonicalize()?;
+
+        let mut child_process = Command::new(executable.as_os_str())
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .current_dir(executable.parent().unwrap_or_else(|| Path::new(&Component::RootDir)))
+            .kill_on_drop(true)
+            .spawn()?;
+
+        let mut stdin = child_process.stdin.take().unwrap();
+        let mut stdout = BufReader::new(child_process.stdout.take().unwrap()).lines();
+
+        // on error child_process gets dropped which kills the process
+        handshake(&mut stdin, &mut stdout, &mut child_process, timeouts.handshake).await?;
+
+        let pending = Pending::default();
+        let subscribers = Subscribers::default();
+        let (requests, mut rx) = mpsc::unbounded_channel::<Request>();
+
+        tokio::spawn(async move {
+            while let Some(request) = rx.recv().await {
+                if stdin.write_all(&encode(&request)).await.is_err() || stdin.flush().await.is_err() {
+                    break;
+                }
+            }
+        });
+        tokio::spawn(read_messages(stdout, pending.clone(), subscribers.clone()));
+
+        Ok(Self {
+            requests,
+            pending,
+            subscribers,
+            child_process: Arc::new(tokio::sync::Mutex::new(child_process)),
+            timeouts: Mutex::new(timeouts),
+            next_id: AtomicU64::new(0),
+            closed: AtomicBool::new(false),
+            exiting: AtomicBool::new(false),
+        })
+    }
+
+    #[must_use]
+    pub fn timeouts(&self) -> Timeouts {
+        *self.timeouts.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Only affects commands sent after this call.
+    pub fn set_timeouts(&self, timeouts: Timeouts) {
+        *self.timeouts.lock().unwrap_or_else(|e| e.into_inner()) = timeouts;
+    }
+
+    /// Sends `cmd` and waits until the matching response arrives or the timeout for the command runs out.
+    pub async fn send(&self, cmd: IpcCommand) -> Result<IpcResponse, Error> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let timeout = self.timeouts().for_command(&cmd);
+        let command = cmd.name();
+
+        let (tx, rx) = oneshot::channel();
+        self.pending.lock().unwrap_or_else(|e| e.into_inner()).insert(id, tx);
+        // removes the pending entry again if this future is dropped or times out
+        let _guard = PendingGuard { pending: &self.pending, id };
+
+        if self.requests.send(Request { id, command: cmd }).is_err() {
+            return Err(Error::ChildExited(self.wait_for_exit().await));
+        }
+
+        match tokio::time::timeout(timeout, rx).await {
+            Ok(Ok(IpcResponse::ProtocolError(e))) => Err(Error::Protocol(e)),
+            Ok(Ok(IpcResponse::Ok)) if command == IpcCommand::Exit.name() => {
+                self.exiting.store(true, Ordering::Relaxed);
+                Ok(IpcResponse::Ok)
+            }
+            Ok(Ok(response)) => Ok(response),
+            Ok(Err(_)) => Err(Error::ChildExited(self.wait_for_exit().await)),
+            Err(_) => Err(Error::Timeout { command, timeout }),
+        }
+    }
+
+    /// Returns a stream of all `RecorderEvent`s sent by the recorder from now on.
+    pub fn events(&self) -> EventStream {
+        let (tx, rx) = mpsc::unbounded_channel();
+        self.subscribers.lock().unwrap_or_else(|e| e.into_inner()).push(tx);
+        EventStream { rx }
+    }
+
+    /// Returns the exit status if the recorder process has already exited.
+    pub async fn exit_status(&self) -> std::io::Result<Option<ExitStatus>> {
+        self.child_process.lock().await.try_wait()
+    }
+
+    pub async fn kill(&self) -> std::io::Result<()> {
+        self.child_process.lock().await.kill().await
+    }
+
+    /// The stdout of the child has been closed, which means it exited or is about to.
+    async fn wait_for_exit(&self) -> Option<ExitStatus> {
+        let mut child_process = self.child_process.lock().await;
+        tokio::time::timeout(Duration::from_secs(1), child_process.wait())
+            .await
+            .ok()?
+            .ok()
+    }
+
+    /// Stops the recording, shuts libobs down and waits for the recorder process to exit, all within `timeout`.
+    ///
+    /// Goes through the same stages as `IpcLinkMaster::close`: the recording only counts as stopped once the recorder
+    /// reports `RecordingStopped`, and the process is killed if it has not exited by the deadline.
+    /// Dropping the link closes it on a task of the current runtime with a timeout of 10 seconds and logs the report
+    /// if it was not clean. Without a runtime the process can only be killed.
+    pub async fn close(self, timeout: Duration) -> CloseReport {
+        self.close_in_place(timeout).await
+    }
+
+    async fn close_in_place(&self, timeout: Duration) -> CloseReport {
+        let deadline = Instant::now() + timeout;
+        self.closed.store(true, Ordering::Relaxed);
+
+        if self.exiting.load(Ordering::Relaxed) {
+            // shut down and told to exit with `send` already, there is nothing left to ask the recorder
+            let (exit_status, killed) = self.reap(&StageOutcome::Done, deadline).await;
+            return CloseReport {
+                stop_recording: StageOutcome::NotNeeded,
+                shutdown: StageOutcome::NotNeeded,
+                exit: StageOutcome::Done,
+                exit_status,
+                killed,
+            };
+        }
+
+        let stop_recording = self.stop_recording_stage(deadline).await;
+        let shutdown = self
+            .command_stage(&stop_recording, IpcCommand::Shutdown, deadline)
+            .await;
+        let exit = self.command_stage(&shutdown, IpcCommand::Exit, deadline).await;
+        let (exit_status, killed) = self.reap(&exit, deadline).await;
+
+        CloseReport {
+            stop_recording,
+            shutdown,
+            exit,
+            exit_status,
+            killed,
+        }
+    }
+
+    async fn stop_recording_stage(&self, deadline: Instant) -> StageOutcome {
+        let mut events = self.events();
+
+        match self.send_until(IpcCommand::IsRecording, deadline).await {
+            Ok(IpcResponse::Recording(true)) => {}
+            Ok(IpcResponse::Recording(false)) => return StageOutcome::NotNeeded,
+            result => return StageOutcome::from_result(result),
+        }
+        match StageOutcome::from_result(self.send_until(IpcCommand::StopRecording, deadline).await) {
+            StageOutcome::Done => {}
+            outcome => return outcome,
+        }
+
+        // the event might have been sent before the response, but not before subscribing
+        let stopped = async {
+            while let Some(event) = events.next().await {
+                if let RecorderEvent::RecordingStopped { reason, error } = event {
+                    return StageOutcome::from_stop(reason, error);
+                }
+            }
+            // the stream ends when the recorder exits
+            StageOutcome::Exited
+        };
+        tokio::time::timeout(deadline.saturating_duration_since(Instant::now()), stopped)
+            .await
+            .unwrap_or(StageOutcome::TimedOut)
+    }
+
+    async fn command_stage(&self, previous: &StageOutcome, cmd: IpcCommand, deadline: Instant) -> StageOutcome {
+        if previous.blocks_later_stages() {
+            return StageOutcome::Skipped;
+        }
+        if Instant::now() >= deadline {
+            return StageOutcome::TimedOut;
+        }
+        StageOutcome::from_result(self.send_until(cmd, deadline).await)
+    }
+
+    /// Waits for the process to exit until `deadline` if it was told to, kills it otherwise.
+    async fn reap(&self, exit: &StageOutcome, deadline: Instant) -> (Option<ExitStatus>, bool) {
+        let mut child_process = self.child_process.lock().await;
+        let exited = match exit {
+            StageOutcome::Done | StageOutcome::Exited => {
+                tokio::time::timeout(deadline.saturating_duration_since(Instant::now()), child_process.wait())
+                    .await
+                    .ok()
+                    .and_then(Result::ok)
+            }
+            _ => child_process.try_wait().ok().flatten(),
+        };
+        if let Some(status) = exited {
+            return (Some(status), false);
+        }
+
+        let killed = child_process.kill().await.is_ok();
+        (child_process.wait().await.ok(), killed)
+    }
+
+    /// Like `send` but gives up at `deadline` if that is earlier than the timeout of the command.
+    async fn send_until(&self, cmd: IpcCommand, deadline: Instant) -> Result<IpcResponse, Error> {
+        let command = cmd.name();
+        let timeout = deadline.saturating_duration_since(Instant::now());
+        match tokio::time::timeout(timeout, self.send(cmd)).await {
+            Ok(result) => result,
+            Err(_) => Err(Error::Timeout { command, timeout }),
+        }
+    }
+}
+
+impl Drop for AsyncIpcLinkMaster {
+    fn drop(&mut self) {
+        if self.closed.load(Ordering::Relaxed) {
+            return;
+        }
+        // without a runtime nothing can be awaited, the child gets killed when it is dropped (kill_on_drop)
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            log::warn!("recorder dropped outside of a tokio runtime - killing it");
+            return;
+        };
+
+        // the tasks for reading and writing keep running, so a link sharing them can close the recorder
+        // it counts as closed from the start, if the runtime drops the task it must not try again
+        let link = Self {
+            requests: self.requests.clone(),
+            pending: self.pending.clone(),
+            subscribers: self.subscribers.clone(),
+            child_process: self.child_process.clone(),
+            timeouts: Mutex::new(self.timeouts()),
+            next_id: AtomicU64::new(self.next_id.load(Ordering::Relaxed)),
+            closed: AtomicBool::new(true),
+            exiting: AtomicBool::new(self.exiting.load(Ordering::Relaxed)),
+        };
+        runtime.spawn(async move {
+            let report = link.close_in_place(close::DROP_TIMEOUT).await;
+            if !report.is_clean() {
+                log::warn!("recorder did not close cleanly: {report}");
+            }
+        });
+    }
+}
+
+/// `Stream` of `RecorderEvent`s. Ends when the recorder process exits.
+#[derive(Debug)]
+pub struct EventStream {
+    rx: mpsc::UnboundedReceiver<RecorderEvent>,
+}
+
+impl EventStream {
+    pub async fn next(&mut self) -> Option<RecorderEvent> {
+        self.rx.recv().await
+    }
+}
+
+impl futures_core::Stream for EventStream {
+    type Item = RecorderEvent;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.rx.poll_recv(cx)
+    }
+}
+
+struct PendingGuard<'a> {
+    pending: &'a Pending,
+    id: RequestId,
+}
+
+impl Drop for PendingGuard<'_> {
+    fn drop(&mut self) {
+        self.pending.lock().unwrap_or_else(|e| e.into_inner()).remove(&self.id);
+    }
+}
+
+async fn handshake(
+    stdin: &mut tokio::process::ChildStdin,
+    stdout: &mut Lines<BufReader<ChildStdout>>,
+    child_process: &mut Child,
+    timeout: Duration,
+) -> Result<(), Error> {
+    let hello = Hello::current();
+    stdin.write_all(&encode(&hello)).await?;
+    stdin.flush().await?;
+
+    let read_answer = async {
+        loop {
+            match stdout.next_line().await {
+                Ok(Some(line)) => match serde_json::from_str::<Hello>(&line) {
+                    Ok(answer) => return Ok(answer),
+                    Err(_) => match serde_json::from_str::<IpcMessage>(&line) {
+                        Ok(IpcMessage::Log(record)) => forward_log(record),
+                        _ => log_stray_line(&line),
+                    },
+                },
+                Ok(None) | Err(_) => {
+                    let status = tokio::time::timeout(Duration::from_secs(1), child_process.wait()).await;
+                    let status = status.ok().and_then(Result::ok);
+                    return Err(Error::Handshake(format!(
+                        "recorder exited ({})",
+                        ExitStatusDisplay(status)
+                    )));
+                }
+            }
+        }
+    };
+
+    let answer = match tokio::time::timeout(timeout, read_answer).await {
+        Ok(answer) => answer?,
+        Err(_) => return Err(Error::Handshake(format!("no answer within {timeout:?}"))),
+    };
+
+    if hello.is_compatible(&answer) {
+        Ok(())
+    } else {
+        Err(Error::VersionMismatch { master: hello, slave: answer })
+    }
+}
+
+async fn read_messages(mut stdout: Lines<BufReader<ChildStdout>>, pending: Pending, subscribers: Subscribers) {
+    while let Ok(Some(line)) = stdout.next_line().await {
+        match serde_json::from_str::<IpcMessage>(&line) {
+            Ok(IpcMessage::Response(response)) => {
+                match pending.lock().unwrap_or_else(|e| e.into_inner()).remove(&response.id) {
+                    Some(tx) => _ = tx.send(response.response),
+                    None => log::warn!("discarding response to unknown or timed out request {}", response.id),
+                }
+            }
+            Ok(IpcMessage::Event(event)) => {
+                let mut subscribers = subscribers.lock().unwrap_or_else(|e| e.into_inner());
+                subscribers.retain(|subscriber| subscriber.send(event.clone()).is_ok());
+            }
+            Ok(IpcMessage::Log(record)) => forward_log(record),
+            Ok(IpcMessage::ProtocolError(e)) => log::error!("recorder could not read a request: {e}"),
+            // the async link has no watchdog, so it never asks for heartbeats
+            Ok(IpcMessage::Heartbeat(_)) => {}
+            Err(_) => log_stray_line(&line),
+        }
+    }
+
+    // EOF - dropping the senders wakes up everybody who is still waiting for a response
+    pending.lock().unwrap_or_else(|e| e.into_inner()).clear();
+    subscribers.lock().unwrap_or_else(|e| e.into_inner()).clear();
+}
+
+/// Serializes `message` as a single line of JSON.
+fn encode(message: &impl serde::Serialize) -> Vec<u8> {
+    let mut line = serde_json::to_vec(message).expect("IPC messages are always serializable");
+    line.push(b'\n');
+    line
+}
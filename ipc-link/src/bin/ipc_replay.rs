@@ -0,0 +1,42 @@
+//! Replays a journal written by `IpcLinkMaster::journal_to` against a recorder and prints every answer that differs.
+//!
+//! Usage: `ipc_replay <journal> <recorder executable> [--realtime]`
+//!
+//! Exits with 0 if all answers matched, 1 if some did not and 2 if the replay could not be run.
+
+use std::{env, process};
+
+use ipc_link::{journal, IpcLinkMaster};
+
+const USAGE: &str = "usage: ipc_replay <journal> <recorder executable> [--realtime]";
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let realtime = args.iter().any(|arg| arg == "--realtime");
+    let paths: Vec<&String> = args.iter().filter(|arg| !arg.starts_with("--")).collect();
+    let [journal_path, executable] = paths[..] else {
+        eprintln!("{USAGE}");
+        process::exit(2);
+    };
+
+    let records = journal::read(journal_path).unwrap_or_else(|e| {
+        eprintln!("failed to read {journal_path}: {e}");
+        process::exit(2);
+    });
+    let mut link = IpcLinkMaster::new(executable).unwrap_or_else(|e| {
+        eprintln!("failed to start {executable}: {e}");
+        process::exit(2);
+    });
+
+    let mismatches = journal::replay(&records, &mut link, realtime).unwrap_or_else(|e| {
+        eprintln!("replay failed: {e}");
+        process::exit(2);
+    });
+    for mismatch in &mismatches {
+        println!("{mismatch}");
+    }
+    if !mismatches.is_empty() {
+        process::exit(1);
+    }
+    println!("all answers matched");
+}
@@ -0,0 +1,248 @@
+//! Shuts the recorder down in stages, so a recording is finalised before the process goes away.
+
+use std::{
+    fmt,
+    process::ExitStatus,
+    time::{Duration, Instant},
+};
+
+use intprocess_recorder::events::{RecorderEvent, StopReason};
+
+use crate::{Error, ExitStatusDisplay, IpcCommand, IpcLinkMaster, IpcResponse};
+
+/// Deadline `Drop` gives the recorder to close, generous enough for libobs to write the index of a long recording.
+pub(crate) const DROP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// What happened to each stage of `IpcLinkMaster::close`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CloseReport {
+    /// stopping the output and waiting for libobs to confirm that the file is complete
+    pub stop_recording: StageOutcome,
+    pub shutdown: StageOutcome,
+    pub exit: StageOutcome,
+    /// `None` for a link attached to a daemon or if it could not be determined
+    pub exit_status: Option<ExitStatus>,
+    /// the deadline passed (or the recorder refused to exit) and the process was killed
+    pub killed: bool,
+}
+
+impl CloseReport {
+    /// Returns `true` if every stage went through and the process exited on its own.
+    #[must_use]
+    pub fn is_clean(&self) -> bool {
+        [&self.stop_recording, &self.shutdown, &self.exit]
+            .iter()
+            .all(|stage| matches!(stage, StageOutcome::Done | StageOutcome::NotNeeded))
+            && !self.killed
+    }
+
+    fn detached() -> Self {
+        Self {
+            stop_recording: StageOutcome::NotNeeded,
+            shutdown: StageOutcome::NotNeeded,
+            exit: StageOutcome::NotNeeded,
+            exit_status: None,
+            killed: false,
+        }
+    }
+}
+
+impl fmt::Display for CloseReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "stop recording: {}, shutdown: {}, exit: {}, {}",
+            self.stop_recording,
+            self.shutdown,
+            self.exit,
+            ExitStatusDisplay(self.exit_status)
+        )?;
+        if self.killed {
+            f.write_str(" (killed)")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StageOutcome {
+    Done,
+    /// there was nothing to do, e.g. no recording was running or libobs was never initialized
+    NotNeeded,
+    /// the recorder answered with an error or the recording stopped with an error
+    Failed(String),
+    /// the deadline passed before the stage completed
+    TimedOut,
+    /// the recorder process went away during the stage
+    Exited,
+    /// not attempted because an earlier stage timed out or the recorder exited
+    Skipped,
+}
+
+impl fmt::Display for StageOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StageOutcome::Done => f.write_str("done"),
+            StageOutcome::NotNeeded => f.write_str("not needed"),
+            StageOutcome::Failed(e) => write!(f, "failed ({e})"),
+            StageOutcome::TimedOut => f.write_str("timed out"),
+            StageOutcome::Exited => f.write_str("recorder exited"),
+            StageOutcome::Skipped => f.write_str("skipped"),
+        }
+    }
+}
+
+impl StageOutcome {
+    pub(crate) fn from_result(result: Result<IpcResponse, Error>) -> Self {
+        match result {
+            Ok(IpcResponse::Ok) => StageOutcome::Done,
+            Ok(IpcResponse::Err(intprocess_recorder::Error::NotInitialized)) => StageOutcome::NotNeeded,
+            Ok(IpcResponse::Err(e)) => StageOutcome::Failed(e.to_string()),
+            Ok(response) => StageOutcome::Failed(format!("unexpected response {response:?}")),
+            Err(Error::Timeout { .. }) => StageOutcome::TimedOut,
+            Err(Error::ChildExited(_)) => StageOutcome::Exited,
+            Err(e) => StageOutcome::Failed(e.to_string()),
+        }
+    }
+
+    /// Outcome of stopping the recording, from the `RecordingStopped` event.
+    pub(crate) fn from_stop(reason: StopReason, error: Option<String>) -> Self {
+        if reason == StopReason::Success {
+            return StageOutcome::Done;
+        }
+        StageOutcome::Failed(match error {
+            Some(error) => format!("{reason:?}: {error}"),
+            None => format!("{reason:?}"),
+        })
+    }
+
+    /// The recorder answers one command after the other, so nothing gets through after a timeout.
+    pub(crate) fn blocks_later_stages(&self) -> bool {
+        matches!(
+            self,
+            StageOutcome::TimedOut | StageOutcome::Exited | StageOutcome::Skipped
+        )
+    }
+}
+
+impl IpcLinkMaster {
+    /// Stops the recording, shuts libobs down and waits for the recorder process to exit, all within `timeout`.
+    ///
+    /// The recording only counts as stopped once libobs confirms that the output has stopped,
+    /// which is when the file has been finalised (e.g. the index of an MP4 has been written).
+    /// If a stage times out the remaining stages are skipped, and the process is killed if it has not exited
+    /// by the deadline. A recorder that refuses to exit is killed right away.
+    ///
+    /// A link attached to a daemon only detaches from it. If the recorder has already answered `IpcCommand::Exit`, only
+    /// its process is waited for.
+    /// Dropping the link closes it with a timeout of 10 seconds and logs the report if it was not clean.
+    pub fn close(mut self, timeout: Duration) -> CloseReport {
+        self.close_in_place(timeout)
+    }
+
+    pub(crate) fn close_in_place(&mut self, timeout: Duration) -> CloseReport {
+        let deadline = Instant::now() + timeout;
+        self.closed = true;
+        // the watchdog must not report the shutdown as the recorder being unresponsive
+        self.watchdog = None;
+
+        if self.exiting {
+            // shut down and told to exit with `send` already, there is nothing left to ask the recorder
+            let (exit_status, killed) = self.reap(&StageOutcome::Done, deadline);
+            return CloseReport {
+                stop_recording: StageOutcome::NotNeeded,
+                shutdown: StageOutcome::NotNeeded,
+                exit: StageOutcome::Done,
+                exit_status,
+                killed,
+            };
+        }
+        if self.child_process.is_none() {
+            // leave the daemon running for the other masters
+            // waits for the answer, so the daemon knows about it before anyone else talks to it
+            _ = self.send_until(IpcCommand::Detach, deadline);
+            return CloseReport::detached();
+        }
+
+        let stop_recording = self.stop_recording_stage(deadline);
+        let shutdown = self.command_stage(&stop_recording, IpcCommand::Shutdown, deadline);
+        let exit = self.command_stage(&shutdown, IpcCommand::Exit, deadline);
+        let (exit_status, killed) = self.reap(&exit, deadline);
+
+        CloseReport {
+            stop_recording,
+            shutdown,
+            exit,
+            exit_status,
+            killed,
+        }
+    }
+
+    fn stop_recording_stage(&mut self, deadline: Instant) -> StageOutcome {
+        let events = self.subscribe();
+
+        match self.send_until(IpcCommand::IsRecording, deadline) {
+            Ok(IpcResponse::Recording(true)) => {}
+            Ok(IpcResponse::Recording(false)) => return StageOutcome::NotNeeded,
+            result => return StageOutcome::from_result(result),
+        }
+        match StageOutcome::from_result(self.send_until(IpcCommand::StopRecording, deadline)) {
+            StageOutcome::Done => {}
+            outcome => return outcome,
+        }
+
+        // the event might have been sent before the response, but not before subscribing
+        loop {
+            match events.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                Ok(RecorderEvent::RecordingStopped { reason, error }) => return StageOutcome::from_stop(reason, error),
+                Ok(_) => continue,
+                Err(_) => return StageOutcome::TimedOut,
+            }
+        }
+    }
+
+    fn command_stage(&mut self, previous: &StageOutcome, cmd: IpcCommand, deadline: Instant) -> StageOutcome {
+        if previous.blocks_later_stages() {
+            return StageOutcome::Skipped;
+        }
+        if Instant::now() >= deadline {
+            return StageOutcome::TimedOut;
+        }
+        StageOutcome::from_result(self.send_until(cmd, deadline))
+    }
+
+    /// Waits for the process to exit until `deadline` if it was told to, kills it otherwise.
+    fn reap(&mut self, exit: &StageOutcome, deadline: Instant) -> (Option<ExitStatus>, bool) {
+        use wait_timeout::ChildExt;
+
+        let Some(mut child_process) = self.child() else {
+            return (None, false);
+        };
+        let exited = match exit {
+            StageOutcome::Done | StageOutcome::Exited => {
+                child_process.wait_timeout(deadline.saturating_duration_since(Instant::now()))
+            }
+            _ => child_process.try_wait(),
+        };
+        if let Ok(Some(status)) = exited {
+            return (Some(status), false);
+        }
+
+        let killed = child_process.kill().is_ok();
+        (child_process.wait().ok(), killed)
+    }
+
+    /// Like `send` but gives up at `deadline` if that is earlier than the timeout of the command.
+    fn send_until(&mut self, cmd: IpcCommand, deadline: Instant) -> Result<IpcResponse, Error> {
+        let id = self.submit(cmd)?;
+        if let Some(request) = self.in_flight.get_mut(&id) {
+            if deadline < request.deadline {
+                request.timeout = request
+                    .timeout
+                    .saturating_sub(request.deadline.saturating_duration_since(deadline));
+                request.deadline = deadline;
+            }
+        }
+        self.wait(id)
+    }
+}
@@ -0,0 +1,94 @@
+//! How messages are delimited and encoded on the wire after the handshake.
+
+use std::{
+    fmt,
+    io::{self, BufRead, Write},
+};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+/// Upper bound for the length prefix of a `Framing::MessagePack` frame.
+///
+/// Anything bigger is a corrupted stream rather than a real message, and allocating it could take down the process.
+const MAX_FRAME_LEN: usize = 64 * 1024 * 1024;
+
+/// Requested by the master in its `Hello` and confirmed by the slave in its answer.
+///
+/// The handshake itself is always sent as JSON lines, so both sides can tell which framing the other one wants
+/// (and report a version mismatch) no matter what was picked.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum Framing {
+    /// one JSON object per line - easy to read in a journal, a debugger or by typing into the recorder's stdin
+    #[default]
+    JsonLines,
+    /// MessagePack prefixed with its length as a little endian `u32` - smaller and faster to (de)serialize
+    MessagePack,
+}
+
+impl Framing {
+    /// Writes `message` as a single frame and flushes it.
+    pub(crate) fn write(self, tx: &mut impl Write, message: &impl Serialize) -> io::Result<()> {
+        match self {
+            Framing::JsonLines => {
+                serde_json::to_writer(&mut *tx, message)?;
+                tx.write_all(b"\n")?;
+            }
+            Framing::MessagePack => {
+                let payload = rmp_serde::to_vec(message).map_err(io::Error::other)?;
+                let len = u32::try_from(payload.len())
+                    .ok()
+                    .filter(|&len| len as usize <= MAX_FRAME_LEN)
+                    .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "message too large for one frame"))?;
+                tx.write_all(&len.to_le_bytes())?;
+                tx.write_all(&payload)?;
+            }
+        }
+        tx.flush()
+    }
+
+    /// Reads the next frame into `frame` (replacing its content) and returns `false` on EOF.
+    ///
+    /// Fails with `io::ErrorKind::InvalidData` if the length prefix is out of bounds -
+    /// the stream can't be resynchronized after that.
+    pub(crate) fn read(self, rx: &mut impl BufRead, frame: &mut Vec<u8>) -> io::Result<bool> {
+        frame.clear();
+        match self {
+            Framing::JsonLines => Ok(rx.read_until(b'\n', frame)? > 0),
+            Framing::MessagePack => {
+                let mut len = [0; 4];
+                match rx.read_exact(&mut len) {
+                    Ok(()) => {}
+                    Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(false),
+                    Err(e) => return Err(e),
+                }
+                let len = u32::from_le_bytes(len) as usize;
+                if len > MAX_FRAME_LEN {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("frame length {len} exceeds the maximum of {MAX_FRAME_LEN}"),
+                    ));
+                }
+                frame.resize(len, 0);
+                rx.read_exact(frame)?;
+                Ok(true)
+            }
+        }
+    }
+
+    /// Deserializes a frame returned by `read`.
+    pub(crate) fn decode<T: DeserializeOwned>(self, frame: &[u8]) -> Result<T, String> {
+        match self {
+            Framing::JsonLines => serde_json::from_slice(frame).map_err(|e| e.to_string()),
+            Framing::MessagePack => rmp_serde::from_slice(frame).map_err(|e| e.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for Framing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Framing::JsonLines => f.write_str("JSON lines"),
+            Framing::MessagePack => f.write_str("MessagePack"),
+        }
+    }
+}
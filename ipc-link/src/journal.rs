@@ -0,0 +1,237 @@
+//! Journal of everything that goes over an `IpcLinkMaster`, written as one JSON object per line.
+//!
+//! A journal can be fed to another recorder process with [`replay`] to find out whether it still answers the same way,
+//! e.g. to reproduce a bug report with the mock recorder or a different libobs build.
+
+use std::{
+    collections::HashMap,
+    fmt,
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::Path,
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+use intprocess_recorder::events::RecorderEvent;
+
+use crate::{Error, IpcCommand, IpcLinkMaster, IpcResponse, LogRecord, Request, RequestId, Response};
+
+/// One line of a journal.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct JournalRecord {
+    /// time since the journal was started
+    pub elapsed_ms: u64,
+    pub entry: JournalEntry,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum JournalEntry {
+    /// a command has been sent to the recorder
+    Command(Request),
+    /// the recorder has answered a command
+    Response(Response),
+    Event(RecorderEvent),
+    Log(LogRecord),
+    /// the recorder did not answer the command in time
+    Timeout {
+        id: RequestId,
+        command: String,
+    },
+    /// the recorder exited while a command was waiting for its answer
+    Exited {
+        code: Option<i32>,
+    },
+    /// the recorder process has been replaced by a new one
+    Restarted,
+}
+
+/// Writes `JournalRecord`s to a file.
+pub struct Journal {
+    writer: BufWriter<File>,
+    started: Instant,
+}
+
+impl Journal {
+    /// Creates the file at `path`, replacing it if it already exists.
+    pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(Self {
+            writer: BufWriter::new(File::create(path)?),
+            started: Instant::now(),
+        })
+    }
+
+    pub fn record(&mut self, entry: JournalEntry) -> io::Result<()> {
+        let record = JournalRecord {
+            elapsed_ms: u64::try_from(self.started.elapsed().as_millis()).unwrap_or(u64::MAX),
+            entry,
+        };
+        serde_json::to_writer(&mut self.writer, &record)?;
+        self.writer.write_all(b"\n")?;
+        // flushed after every record, so the journal is complete even if the host crashes
+        self.writer.flush()
+    }
+}
+
+impl fmt::Debug for Journal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Journal")
+            .field("started", &self.started)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Shared between the master and its reader thread, so a journal can be started at any time.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct JournalSlot(Arc<Mutex<Option<Journal>>>);
+
+impl JournalSlot {
+    pub(crate) fn set(&self, journal: Option<Journal>) {
+        *self.0.lock().unwrap_or_else(|e| e.into_inner()) = journal;
+    }
+
+    pub(crate) fn record(&self, entry: impl FnOnce() -> JournalEntry) {
+        let mut journal = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(writer) = journal.as_mut() {
+            if let Err(e) = writer.record(entry()) {
+                // a broken journal must not break the recording
+                log::warn!("failed to write journal - stopping it: {e}");
+                *journal = None;
+            }
+        }
+    }
+}
+
+/// Reads a journal written by `IpcLinkMaster::journal_to`.
+pub fn read(path: impl AsRef<Path>) -> io::Result<Vec<JournalRecord>> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut records = Vec::new();
+    for (n, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let record = serde_json::from_str(&line)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("line {}: {e}", n + 1)))?;
+        records.push(record);
+    }
+    Ok(records)
+}
+
+/// What a recorder did with a command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    Response(IpcResponse),
+    Timeout,
+    Exited,
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Outcome::Response(response) => write!(f, "{response:?}"),
+            Outcome::Timeout => f.write_str("timed out"),
+            Outcome::Exited => f.write_str("recorder exited"),
+        }
+    }
+}
+
+/// A command that got a different answer during `replay` than in the journal.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mismatch {
+    /// id of the command in the journal
+    pub id: RequestId,
+    pub command: &'static str,
+    pub expected: Outcome,
+    pub found: Outcome,
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "#{} {}: expected {} but found {}",
+            self.id, self.command, self.expected, self.found
+        )
+    }
+}
+
+/// Sends the commands of a journal to the recorder behind `link` one after another and compares the answers.
+///
+/// With `realtime` every command is delayed until it is as far into the replay as it was into the journal,
+/// otherwise the commands are sent as fast as the recorder answers them.
+/// The recorder gets restarted wherever the journal says it has been.
+/// Commands the journal has no answer for (e.g. because it ends early) are sent anyway, but can't mismatch.
+pub fn replay(records: &[JournalRecord], link: &mut IpcLinkMaster, realtime: bool) -> Result<Vec<Mismatch>, Error> {
+    let expected = expected_outcomes(records);
+
+    let started = Instant::now();
+    let mut mismatches = Vec::new();
+    for record in records {
+        let request = match &record.entry {
+            JournalEntry::Command(request) => request,
+            JournalEntry::Restarted => {
+                link.restart()?;
+                continue;
+            }
+            _ => continue,
+        };
+        if let IpcCommand::Detach = request.command {
+            // only meaningful for the connection the journal was recorded on
+            continue;
+        }
+
+        if realtime {
+            thread::sleep(Duration::from_millis(record.elapsed_ms).saturating_sub(started.elapsed()));
+        }
+
+        let found = match link.send(request.command.clone()) {
+            Ok(response) => Outcome::Response(response),
+            Err(Error::Timeout { .. }) => Outcome::Timeout,
+            Err(Error::Protocol(e)) => Outcome::Response(IpcResponse::ProtocolError(e)),
+            // writing fails once the recorder is gone
+            Err(Error::ChildExited(_) | Error::Io(_)) => Outcome::Exited,
+            Err(e) => return Err(e),
+        };
+        match expected.get(&request.id) {
+            Some(expected) if *expected != found => mismatches.push(Mismatch {
+                id: request.id,
+                command: request.command.name(),
+                expected: expected.clone(),
+                found,
+            }),
+            _ => {}
+        }
+    }
+
+    Ok(mismatches)
+}
+
+/// Collects the answer to every command in the journal.
+fn expected_outcomes(records: &[JournalRecord]) -> HashMap<RequestId, Outcome> {
+    let mut outcomes = HashMap::new();
+    let mut unanswered = Vec::new();
+    for record in records {
+        match &record.entry {
+            JournalEntry::Command(request) => unanswered.push(request.id),
+            JournalEntry::Response(response) => {
+                unanswered.retain(|id| *id != response.id);
+                // a late answer to a command that has timed out does not count
+                outcomes
+                    .entry(response.id)
+                    .or_insert_with(|| Outcome::Response(response.response.clone()));
+            }
+            JournalEntry::Timeout { id, .. } => {
+                unanswered.retain(|other| other != id);
+                outcomes.insert(*id, Outcome::Timeout);
+            }
+            JournalEntry::Exited { .. } => {
+                // nothing that was still waiting for an answer will get one
+                outcomes.extend(unanswered.drain(..).map(|id| (id, Outcome::Exited)));
+            }
+            _ => {}
+        }
+    }
+    outcomes
+}
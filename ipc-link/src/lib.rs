@@ -1,13 +1,157 @@
 use std::{
-    io::{self, BufRead, BufReader, BufWriter, StdinLock, StdoutLock, Write},
-    path::{Component, Path},
-    process::{Child, ChildStdin, ChildStdout, Command, Stdio},
-    time::Duration,
+    collections::HashMap,
+    error, fmt,
+    io::{self, BufReader, BufWriter},
+    path::{Component, Path, PathBuf},
+    process::{Child, Command, ExitStatus, Stdio},
+    sync::{
+        mpsc::{self, RecvTimeoutError},
+        Arc, Mutex, MutexGuard,
+    },
+    thread,
+    time::{Duration, Instant},
 };
 
+#[cfg(feature = "async")]
+pub use async_link::{AsyncIpcLinkMaster, EventStream};
+pub use close::{CloseReport, StageOutcome};
+pub use framing::Framing;
+use intprocess_recorder::events::RecorderEvent;
+use intprocess_recorder::marker::Marker;
+use intprocess_recorder::output::{OutputId, OutputKind};
 use intprocess_recorder::settings::{Adapter, Encoder, RecorderSettings};
+use intprocess_recorder::status::Status;
+use journal::JournalSlot;
+pub use journal::{Journal, JournalEntry, JournalRecord};
+pub use slave::{EventSender, IpcLinkSlave};
+use transport::{Reader, Writer};
+pub use transport::{Transport, DAEMON_ARG, TRANSPORT_ARG};
+use watchdog::{Liveness, WatchdogThread};
+pub use watchdog::{Watchdog, WatchdogAction};
+
+#[cfg(feature = "async")]
+mod async_link;
+mod close;
+mod framing;
+pub mod journal;
+mod slave;
+mod transport;
+mod watchdog;
+
+/// Version of the message format spoken over the link.
+///
+/// Has to be incremented once per release in which `IpcCommand`, `IpcResponse` or `IpcMessage` changed in a way that
+/// breaks (de)serialization.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// First message exchanged in both directions after the recorder process has been spawned.
+///
+/// The master sends its `Hello` and the slave answers with its own.
+/// Both sides refuse to continue if the versions in the two messages don't match.
+/// Both messages are always sent as JSON lines, everything after them uses the `framing` requested by the master.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Hello {
+    pub protocol_version: u32,
+    pub crate_version: String,
+    pub libobs_version: String,
+    /// requested by the master and echoed by the slave
+    #[serde(default)]
+    pub framing: Framing,
+}
+
+impl Hello {
+    #[must_use]
+    pub fn current() -> Self {
+        Self {
+            protocol_version: PROTOCOL_VERSION,
+            crate_version: env!("CARGO_PKG_VERSION").to_string(),
+            libobs_version: libobs_sys::VERSION.to_string(),
+            framing: Framing::default(),
+        }
+    }
+
+    #[must_use]
+    pub fn with_framing(framing: Framing) -> Self {
+        Self { framing, ..Self::current() }
+    }
+
+    #[must_use]
+    pub fn is_compatible(&self, other: &Hello) -> bool {
+        self.protocol_version == other.protocol_version
+            && self.crate_version == other.crate_version
+            && self.libobs_version == other.libobs_version
+    }
+}
+
+impl fmt::Display for Hello {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "protocol v{} / ipc-link {} / libobs {}",
+            self.protocol_version, self.crate_version, self.libobs_version
+        )
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    /// the recorder did not answer the `Hello` message correctly - it is most likely an outdated binary
+    Handshake(String),
+    VersionMismatch {
+        master: Hello,
+        slave: Hello,
+    },
+    /// the recorder did not answer in time - it might be stuck inside libobs
+    Timeout {
+        command: &'static str,
+        timeout: Duration,
+    },
+    /// the recorder process has exited (the exit status is `None` if it could not be determined)
+    ///
+    /// For a link attached to a daemon this means the connection was closed.
+    ChildExited(Option<ExitStatus>),
+    /// `wait` was called with an id that was never submitted, already collected or has timed out
+    UnknownRequest(RequestId),
+    /// the recorder could not make sense of the request (e.g. a corrupted frame or a command it doesn't know)
+    Protocol(String),
+}
 
-#[derive(Debug, serde::Serialize, serde::Deserialize)]
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "{e}"),
+            Error::Handshake(e) => write!(f, "handshake with recorder failed: {e}"),
+            Error::VersionMismatch { master, slave } => {
+                write!(
+                    f,
+                    "recorder version mismatch - expected ({master}) but recorder is ({slave})"
+                )
+            }
+            Error::Timeout { command, timeout } => write!(f, "recorder did not answer {command} within {timeout:?}"),
+            Error::ChildExited(status) => write!(f, "recorder exited ({})", ExitStatusDisplay(*status)),
+            Error::UnknownRequest(id) => write!(f, "no request with id {id} is in flight"),
+            Error::Protocol(e) => write!(f, "recorder rejected the request: {e}"),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
 pub enum IpcCommand {
     Init {
         libobs_data_path: Option<String>,
@@ -17,152 +161,884 @@ pub enum IpcCommand {
     Configure(RecorderSettings),
     Encoders,
     Adapter,
+    /// answered with `IpcResponse::Path` (the file the recording writes to)
     StartRecording,
     StopRecording,
     IsRecording,
+    PauseRecording,
+    ResumeRecording,
+    /// answered with `IpcResponse::Recording`
+    IsPaused,
+    /// the finished file arrives as `RecorderEvent::SegmentFinished` once the next one has started
+    SplitRecording,
+    /// answered with `IpcResponse::Marker`
+    AddMarker(String),
+    /// answered with `IpcResponse::Output`
+    AddOutput(OutputKind),
+    RemoveOutput(OutputId),
+    /// answered with `IpcResponse::Outputs`
+    Outputs,
+    StartOutput(OutputId),
+    StopOutput(OutputId),
+    /// answered with `IpcResponse::Recording`
+    IsOutputActive(OutputId),
+    /// answered with `IpcResponse::Output` (the id of the replay buffer)
+    StartReplayBuffer,
+    /// the path of the replay arrives as `RecorderEvent::ReplaySaved` once it has been written
+    SaveReplay,
+    StopReplayBuffer,
+    /// answered once remuxing has started, `RecorderEvent::RemuxProgress` and `RecorderEvent::RemuxFinished` follow
+    Remux {
+        input: String,
+        output: String,
+    },
     Shutdown,
     Exit,
+    /// ends the connection to a recorder daemon without affecting the recorder or other attached masters
+    Detach,
+    Status,
+    /// asks for an `IpcMessage::Heartbeat` every `interval` (`None` or zero turns them off again)
+    ///
+    /// Answered by the link itself, every master gets its own heartbeats.
+    SetHeartbeat(Option<Duration>),
 }
 
-#[derive(Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+impl IpcCommand {
+    #[must_use]
+    pub fn name(&self) -> &'static str {
+        match self {
+            IpcCommand::Init { .. } => "Init",
+            IpcCommand::Configure(_) => "Configure",
+            IpcCommand::Encoders => "Encoders",
+            IpcCommand::Adapter => "Adapter",
+            IpcCommand::StartRecording => "StartRecording",
+            IpcCommand::StopRecording => "StopRecording",
+            IpcCommand::IsRecording => "IsRecording",
+            IpcCommand::PauseRecording => "PauseRecording",
+            IpcCommand::ResumeRecording => "ResumeRecording",
+            IpcCommand::IsPaused => "IsPaused",
+            IpcCommand::SplitRecording => "SplitRecording",
+            IpcCommand::AddMarker(_) => "AddMarker",
+            IpcCommand::AddOutput(_) => "AddOutput",
+            IpcCommand::RemoveOutput(_) => "RemoveOutput",
+            IpcCommand::Outputs => "Outputs",
+            IpcCommand::StartOutput(_) => "StartOutput",
+            IpcCommand::StopOutput(_) => "StopOutput",
+            IpcCommand::IsOutputActive(_) => "IsOutputActive",
+            IpcCommand::StartReplayBuffer => "StartReplayBuffer",
+            IpcCommand::SaveReplay => "SaveReplay",
+            IpcCommand::StopReplayBuffer => "StopReplayBuffer",
+            IpcCommand::Remux { .. } => "Remux",
+            IpcCommand::Shutdown => "Shutdown",
+            IpcCommand::Exit => "Exit",
+            IpcCommand::Detach => "Detach",
+            IpcCommand::Status => "Status",
+            IpcCommand::SetHeartbeat(_) => "SetHeartbeat",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub enum IpcResponse {
     Ok,
-    Encoders { available: Vec<Encoder>, selected: Encoder },
+    Encoders {
+        available: Vec<Encoder>,
+        selected: Encoder,
+    },
     Adapter(Adapter),
     Recording(bool),
-    Err(String),
+    Output(OutputId),
+    Outputs(Vec<OutputId>),
+    Path(String),
+    Marker(Marker),
+    Status(Status),
+    Err(intprocess_recorder::Error),
+    /// the request could not be decoded - `IpcLinkMaster::wait` turns this into `Error::Protocol`
+    ProtocolError(String),
+}
+
+/// Correlates an `IpcResponse` with the `IpcCommand` it answers.
+pub type RequestId = u64;
+
+/// Envelope around every `IpcCommand` sent to the recorder.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Request {
+    pub id: RequestId,
+    pub command: IpcCommand,
+}
+
+/// Envelope around every `IpcResponse` sent back by the recorder. Carries the `id` of the `Request` it answers.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Response {
+    pub id: RequestId,
+    pub response: IpcResponse,
+}
+
+/// Deadlines for the answer to each kind of `IpcCommand`.
+///
+/// Commands that have to wait on libobs (e.g. `Init` loading all modules) get a lot more time than simple queries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Timeouts {
+    pub handshake: Duration,
+    pub init: Duration,
+    /// `Configure` and `AddOutput`
+    pub configure: Duration,
+    /// `StartRecording`, `StopRecording`, `PauseRecording`, `ResumeRecording`, `StartOutput`, `StopOutput`,
+    /// `RemoveOutput` (which stops the output), `StartReplayBuffer` and `StopReplayBuffer`
+    pub recording: Duration,
+    /// `Encoders`, `Adapter`, `IsRecording`, `IsPaused`, `Outputs`, `IsOutputActive`, `SaveReplay` and
+    /// `SplitRecording` (which only request the file), `AddMarker`, `Remux` (which only starts remuxing), `Status`,
+    /// `SetHeartbeat` and `Detach`
+    pub query: Duration,
+    /// `Shutdown` and `Exit`
+    pub shutdown: Duration,
+}
+
+impl Timeouts {
+    #[must_use]
+    pub fn for_command(&self, cmd: &IpcCommand) -> Duration {
+        match cmd {
+            IpcCommand::Init { .. } => self.init,
+            IpcCommand::Configure(_) | IpcCommand::AddOutput(_) => self.configure,
+            IpcCommand::StartRecording
+            | IpcCommand::StopRecording
+            | IpcCommand::PauseRecording
+            | IpcCommand::ResumeRecording
+            | IpcCommand::StartOutput(_)
+            | IpcCommand::StopOutput(_)
+            | IpcCommand::RemoveOutput(_)
+            | IpcCommand::StartReplayBuffer
+            | IpcCommand::StopReplayBuffer => self.recording,
+            IpcCommand::Encoders
+            | IpcCommand::Adapter
+            | IpcCommand::IsRecording
+            | IpcCommand::IsPaused
+            | IpcCommand::Outputs
+            | IpcCommand::IsOutputActive(_)
+            | IpcCommand::SaveReplay
+            | IpcCommand::SplitRecording
+            | IpcCommand::AddMarker(_)
+            | IpcCommand::Remux { .. }
+            | IpcCommand::Status
+            | IpcCommand::SetHeartbeat(_)
+            | IpcCommand::Detach => self.query,
+            IpcCommand::Shutdown | IpcCommand::Exit => self.shutdown,
+        }
+    }
+}
+
+impl Default for Timeouts {
+    fn default() -> Self {
+        Self {
+            handshake: Duration::from_secs(5),
+            init: Duration::from_secs(30),
+            configure: Duration::from_secs(10),
+            // stopping waits up to 3s before force stopping the output
+            recording: Duration::from_secs(10),
+            query: Duration::from_secs(2),
+            shutdown: Duration::from_secs(10),
+        }
+    }
+}
+
+/// Everything the recorder sends after the handshake.
+#[derive(Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum IpcMessage {
+    Response(Response),
+    /// sent unsolicited whenever something happens inside libobs
+    Event(RecorderEvent),
+    /// log output of the recorder and libobs - stdout is reserved for `IpcMessage`s
+    Log(LogRecord),
+    /// sent instead of a `Response` if a request was too broken to even find out its id
+    ProtocolError(String),
+    /// sent periodically after `IpcCommand::SetHeartbeat` - `None` if the recorder is not initialized
+    Heartbeat(Option<Status>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl From<log::Level> for LogLevel {
+    fn from(level: log::Level) -> Self {
+        match level {
+            log::Level::Error => Self::Error,
+            log::Level::Warn => Self::Warn,
+            log::Level::Info => Self::Info,
+            log::Level::Debug => Self::Debug,
+            log::Level::Trace => Self::Trace,
+        }
+    }
+}
+
+impl From<LogLevel> for log::Level {
+    fn from(level: LogLevel) -> Self {
+        match level {
+            LogLevel::Error => Self::Error,
+            LogLevel::Warn => Self::Warn,
+            LogLevel::Info => Self::Info,
+            LogLevel::Debug => Self::Debug,
+            LogLevel::Trace => Self::Trace,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct LogRecord {
+    pub level: LogLevel,
+    /// the `log` target in the recorder (e.g. `libobs` for messages coming from libobs itself)
+    pub target: String,
+    pub message: String,
+}
+
+#[derive(Debug)]
+enum Incoming {
+    Response(Response),
+    /// anything that is not an `IpcMessage` (e.g. the `Hello` or stray output)
+    Line(String),
+}
+
+type Subscribers = Arc<Mutex<Vec<mpsc::Sender<RecorderEvent>>>>;
+
+/// Where the reader thread delivers everything apart from responses.
+#[derive(Clone, Default)]
+struct Sinks {
+    subscribers: Subscribers,
+    journal: JournalSlot,
+    liveness: Liveness,
 }
 
 #[derive(Debug)]
+struct InFlight {
+    command: &'static str,
+    timeout: Duration,
+    deadline: Instant,
+    // `Some` if the response has already been read from the stream
+    response: Option<IpcResponse>,
+}
+
 pub struct IpcLinkMaster {
-    tx: BufWriter<ChildStdin>,
-    rx: BufReader<ChildStdout>,
-    buffer: String,
-    child_process: Child,
+    // `None` if the link is attached to a daemon it did not spawn
+    executable: Option<PathBuf>,
+    transport: Transport,
+    framing: Framing,
+    tx: BufWriter<Writer>,
+    // messages read from the child stdout by a separate thread, so reading can time out
+    // and events get delivered even if nobody is waiting for a response
+    rx: mpsc::Receiver<Incoming>,
+    sinks: Sinks,
+    // shared with the watchdog, which kills the process if it stops responding
+    child_process: Option<Arc<Mutex<Child>>>,
+    watchdog: Option<WatchdogThread>,
+    timeouts: Timeouts,
+    next_id: RequestId,
+    // requests that have been submitted but not yet collected with `wait`
+    in_flight: HashMap<RequestId, InFlight>,
+    // `close` has already run, so there is nothing left to do on drop
+    closed: bool,
+    // the recorder has answered `Exit`, so only its process is left to reap
+    exiting: bool,
 }
 
 impl IpcLinkMaster {
-    pub fn new(executable: impl AsRef<Path>) -> io::Result<Self> {
+    pub fn new(executable: impl AsRef<Path>) -> Result<Self, Error> {
+        Self::with_timeouts(executable, Timeouts::default())
+    }
+
+    pub fn with_timeouts(executable: impl AsRef<Path>, timeouts: Timeouts) -> Result<Self, Error> {
+        Self::with_transport(executable, Transport::Stdio, timeouts)
+    }
+
+    /// Spawns the recorder process and talks to it over `transport`.
+    pub fn with_transport(
+        executable: impl AsRef<Path>,
+        transport: Transport,
+        timeouts: Timeouts,
+    ) -> Result<Self, Error> {
+        Self::with_framing(executable, transport, Framing::default(), timeouts)
+    }
+
+    /// Like `with_transport` but encodes all messages after the handshake with `framing`.
+    pub fn with_framing(
+        executable: impl AsRef<Path>,
+        transport: Transport,
+        framing: Framing,
+        timeouts: Timeouts,
+    ) -> Result<Self, Error> {
         let executable = executable.as_ref().canonicalize()?;
+        let sinks = Sinks::default();
+        let deadline = Instant::now() + timeouts.handshake;
+        let (child_process, tx, rx) = spawn_child(&executable, &transport, framing, sinks.clone(), deadline)?;
+
+        let mut link = Self {
+            executable: Some(executable),
+            transport,
+            framing,
+            tx,
+            rx,
+            sinks,
+            child_process: Some(Arc::new(Mutex::new(child_process))),
+            watchdog: None,
+            timeouts,
+            next_id: 0,
+            in_flight: HashMap::new(),
+            closed: false,
+            exiting: false,
+        };
+        // on error the link gets dropped which makes sure the child process gets terminated
+        link.handshake()?;
+
+        Ok(link)
+    }
+
+    /// Attaches to a recorder daemon (see `IpcLinkSlave::daemon`) that listens on the local socket `name`.
+    ///
+    /// The link does not own the recorder: dropping it only sends `IpcCommand::Detach`,
+    /// and `Shutdown`/`Exit` are refused by the daemon as long as other masters are attached.
+    pub fn connect(name: impl Into<String>, timeouts: Timeouts) -> Result<Self, Error> {
+        Self::connect_with_framing(name, Framing::default(), timeouts)
+    }
 
-        let mut child_process = Command::new(executable.as_os_str())
-            .stdin(Stdio::piped())
-            .stdout(Stdio::piped())
-            .current_dir(executable.parent().unwrap_or_else(|| Path::new(&Component::RootDir)))
-            .spawn()?;
+    /// Like `connect` but encodes all messages after the handshake with `framing`.
+    ///
+    /// Every master attached to a daemon picks its own framing.
+    pub fn connect_with_framing(name: impl Into<String>, framing: Framing, timeouts: Timeouts) -> Result<Self, Error> {
+        let name = name.into();
+        let sinks = Sinks::default();
+        let deadline = Instant::now() + timeouts.handshake;
+        let (tx, rx) = attach(&name, framing, sinks.clone(), deadline)?;
 
-        Ok(Self {
-            tx: BufWriter::new(child_process.stdin.take().unwrap()),
-            rx: BufReader::new(child_process.stdout.take().unwrap()),
-            buffer: String::with_capacity(512),
-            child_process,
-        })
+        let mut link = Self {
+            executable: None,
+            transport: Transport::LocalSocket(name),
+            framing,
+            tx,
+            rx,
+            sinks,
+            child_process: None,
+            watchdog: None,
+            timeouts,
+            next_id: 0,
+            in_flight: HashMap::new(),
+            closed: false,
+            exiting: false,
+        };
+        link.handshake()?;
+
+        Ok(link)
+    }
+
+    /// Returns `false` if the link is attached to a daemon (see `connect`).
+    #[must_use]
+    pub fn owns_recorder(&self) -> bool {
+        self.child_process.is_some()
+    }
+
+    #[must_use]
+    pub fn framing(&self) -> Framing {
+        self.framing
     }
 
-    pub fn send(&mut self, cmd: IpcCommand) -> IpcResponse {
-        if let Err(e) = serde_json::to_writer(&mut self.tx, &cmd) {
-            return IpcResponse::Err(format!("{e:?}"));
+    fn handshake(&mut self) -> Result<(), Error> {
+        let hello = Hello::with_framing(self.framing);
+        Framing::JsonLines.write(&mut self.tx, &hello)?;
+
+        let deadline = Instant::now() + self.timeouts.handshake;
+        let answer = loop {
+            let line = match self.rx.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                Ok(Incoming::Line(line)) => line,
+                Ok(Incoming::Response(response)) => {
+                    log::warn!("discarding response to request {} during handshake", response.id);
+                    continue;
+                }
+                Err(RecvTimeoutError::Timeout) => {
+                    return Err(Error::Handshake(format!(
+                        "no answer within {:?}",
+                        self.timeouts.handshake
+                    )))
+                }
+                Err(RecvTimeoutError::Disconnected) => {
+                    return Err(Error::Handshake(format!(
+                        "recorder exited ({})",
+                        ExitStatusDisplay(self.wait_for_exit())
+                    )))
+                }
+            };
+            match serde_json::from_str::<Hello>(&line) {
+                Ok(answer) => break answer,
+                Err(_) => log_stray_line(&line),
+            }
+        };
+
+        if !hello.is_compatible(&answer) {
+            return Err(Error::VersionMismatch { master: hello, slave: answer });
         }
-        if let Err(e) = self.tx.write(b"\n") {
-            return IpcResponse::Err(format!("{e:?}"));
+        if answer.framing != hello.framing {
+            return Err(Error::Handshake(format!(
+                "recorder answered with {} framing instead of {}",
+                answer.framing, hello.framing
+            )));
         }
-        if let Err(e) = self.tx.flush() {
-            return IpcResponse::Err(format!("{e:?}"));
+        Ok(())
+    }
+
+    #[must_use]
+    pub fn timeouts(&self) -> &Timeouts {
+        &self.timeouts
+    }
+
+    /// Only affects commands submitted after this call.
+    pub fn set_timeouts(&mut self, timeouts: Timeouts) {
+        self.timeouts = timeouts;
+    }
+
+    /// Sends `cmd` and blocks until the matching response arrives or the timeout for the command runs out.
+    pub fn send(&mut self, cmd: IpcCommand) -> Result<IpcResponse, Error> {
+        let id = self.submit(cmd)?;
+        self.wait(id)
+    }
+
+    /// Sends `cmd` without waiting for the response.
+    ///
+    /// Multiple commands can be in flight at the same time. Collect each response with `wait` and the returned id.
+    /// The deadline for the response starts counting now.
+    pub fn submit(&mut self, cmd: IpcCommand) -> Result<RequestId, Error> {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let timeout = self.timeouts.for_command(&cmd);
+        let command = cmd.name();
+        let request = Request { id, command: cmd };
+        // journaled first, the reader thread might journal the response before `write` returns
+        self.sinks.journal.record(|| JournalEntry::Command(request.clone()));
+        if let Err(e) = self.framing.write(&mut self.tx, &request) {
+            return Err(match self.child().map(|mut child_process| child_process.try_wait()) {
+                Some(Ok(Some(status))) => Error::ChildExited(Some(status)),
+                Some(_) => Error::Io(e),
+                None => Error::ChildExited(None),
+            });
         }
 
+        let deadline = Instant::now() + timeout;
+        self.in_flight.insert(
+            id,
+            InFlight {
+                command,
+                timeout,
+                deadline,
+                response: None,
+            },
+        );
+        self.update_busy();
+
+        Ok(id)
+    }
+
+    /// Blocks until the response to the request `id` arrives.
+    ///
+    /// Responses to other in-flight requests that arrive in the meantime are kept until they are waited for.
+    /// If the deadline of the request passes the request is abandoned and a late response will be discarded.
+    pub fn wait(&mut self, id: RequestId) -> Result<IpcResponse, Error> {
+        let result = self.collect(id);
+        self.update_busy();
+        result
+    }
+
+    fn collect(&mut self, id: RequestId) -> Result<IpcResponse, Error> {
+        let deadline = match self.in_flight.get(&id) {
+            Some(request) => request.deadline,
+            None => return Err(Error::UnknownRequest(id)),
+        };
+
         loop {
-            let line = match self.read_line() {
-                Ok(line) => line,
-                Err(e) => return IpcResponse::Err(format!("failed to read from recorder: {e}")),
-            };
-            match serde_json::from_str::<IpcResponse>(line) {
-                Ok(response) => return response,
-                // trim newlines from the end because log::info!() adds one
-                Err(_) => log::info!("[rec]: {}", line.trim_end()),
+            if let Some(response) = self.in_flight.get_mut(&id).and_then(|r| r.response.take()) {
+                let command = self.in_flight.remove(&id).map(|request| request.command);
+                if matches!(response, IpcResponse::Ok) && command == Some(IpcCommand::Exit.name()) {
+                    self.exiting = true;
+                }
+                return match response {
+                    IpcResponse::ProtocolError(e) => Err(Error::Protocol(e)),
+                    response => Ok(response),
+                };
+            }
+
+            match self.rx.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                Ok(incoming) => self.dispatch(incoming),
+                Err(RecvTimeoutError::Timeout) => {
+                    let request = self.in_flight.remove(&id).unwrap();
+                    self.sinks.journal.record(|| JournalEntry::Timeout {
+                        id,
+                        command: request.command.to_string(),
+                    });
+                    return Err(Error::Timeout {
+                        command: request.command,
+                        timeout: request.timeout,
+                    });
+                }
+                Err(RecvTimeoutError::Disconnected) => {
+                    self.in_flight.remove(&id);
+                    let status = self.wait_for_exit();
+                    self.sinks.journal.record(|| JournalEntry::Exited {
+                        code: status.and_then(|status| status.code()),
+                    });
+                    return Err(Error::ChildExited(status));
+                }
             }
         }
     }
 
     pub fn drain_logs(&mut self) {
-        while let Ok(line) = self.read_line() {
-            // trim newlines from the end because log::info!() adds one
-            log::info!("[rec]: {}", line.trim_end());
+        let deadline = Instant::now() + self.timeouts.shutdown;
+        while let Ok(incoming) = self.rx.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+            self.dispatch(incoming);
         }
     }
 
-    fn read_line(&mut self) -> Result<&str, String> {
-        self.buffer.clear();
+    /// Returns a receiver for all `RecorderEvent`s sent by the recorder from now on.
+    ///
+    /// Events are delivered by a background thread, independent of any calls to `send`.
+    /// The subscription ends when the receiver is dropped.
+    pub fn subscribe(&self) -> mpsc::Receiver<RecorderEvent> {
+        let (tx, rx) = mpsc::channel();
+        self.sinks
+            .subscribers
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(tx);
+        rx
+    }
+
+    /// Delivers `event` to all subscribers as if it had been sent by the recorder.
+    pub fn notify(&self, event: RecorderEvent) {
+        broadcast(&self.sinks.subscribers, &event);
+    }
+
+    /// Starts (or with `None` stops) watching the recorder for signs of life, see `Watchdog`.
+    ///
+    /// The watchdog keeps running across restarts.
+    pub fn set_watchdog(&mut self, watchdog: Option<Watchdog>) -> Result<(), Error> {
+        if watchdog.is_some_and(|watchdog| watchdog.interval.is_zero()) {
+            return Err(Error::Io(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "the heartbeat interval must not be zero",
+            )));
+        }
 
-        match self.rx.read_line(&mut self.buffer) {
-            Ok(0) => Err("EOF".into()),
-            Err(e) => Err(format!("{e:?}")),
-            Ok(_) => Ok(&self.buffer),
+        self.watchdog = None;
+        self.send(IpcCommand::SetHeartbeat(watchdog.map(|watchdog| watchdog.interval)))?;
+        if let Some(watchdog) = watchdog {
+            self.sinks.liveness.reset();
+            self.watchdog = Some(WatchdogThread::spawn(
+                watchdog,
+                self.sinks.liveness.clone(),
+                self.sinks.subscribers.clone(),
+                self.child_process.clone(),
+            )?);
         }
+        Ok(())
     }
-}
 
-impl Drop for IpcLinkMaster {
-    fn drop(&mut self) {
-        use wait_timeout::ChildExt;
+    /// Returns what the recorder reported with its latest heartbeat (`None` without a watchdog or before `Init`).
+    #[must_use]
+    pub fn last_status(&self) -> Option<Status> {
+        self.sinks.liveness.last_status()
+    }
+
+    /// Starts writing every command, response, event and log message to a new journal at `path` (see `journal`).
+    ///
+    /// Replaces the journal that is currently written, if any.
+    pub fn journal_to(&mut self, path: impl AsRef<Path>) -> io::Result<()> {
+        self.sinks.journal.set(Some(Journal::create(path)?));
+        Ok(())
+    }
 
-        // the normal self.send function waits indefinitely for an answer that might not come if the subprocess
-        // has already been stopped with IpcCommand::Exit
-        _ = serde_json::to_writer(&mut self.tx, &IpcCommand::StopRecording);
-        _ = self.tx.write(b"\n");
-        _ = serde_json::to_writer(&mut self.tx, &IpcCommand::Shutdown);
-        _ = self.tx.write(b"\n");
-        _ = serde_json::to_writer(&mut self.tx, &IpcCommand::Exit);
-        _ = self.tx.write(b"\n");
-        _ = self.tx.flush();
+    pub fn stop_journal(&mut self) {
+        self.sinks.journal.set(None);
+    }
 
-        match self.child_process.wait_timeout(Duration::from_secs(3)) {
-            Ok(Some(status)) if status.success() => { /* process exited successfully */ }
-            _ => _ = self.child_process.kill(),
+    /// Returns the exit status if the recorder process has already exited.
+    ///
+    /// Always `None` for a link attached to a daemon.
+    pub fn exit_status(&mut self) -> io::Result<Option<ExitStatus>> {
+        match self.child() {
+            Some(mut child_process) => child_process.try_wait(),
+            None => Ok(None),
         }
     }
+
+    /// Replaces the recorder process with a freshly spawned one (e.g. after it crashed).
+    ///
+    /// Subscriptions and timeouts are kept, requests that are still in flight are abandoned.
+    /// The new process starts out uninitialized, so `IpcCommand::Init` has to be sent again.
+    /// Returns the exit status of the old process.
+    ///
+    /// A link attached to a daemon reconnects to it instead.
+    pub fn restart(&mut self) -> Result<Option<ExitStatus>, Error> {
+        // the old process might still be running if it only stopped responding
+        let status = match self.child().as_mut() {
+            Some(child_process) => match child_process.try_wait()? {
+                Some(status) => Some(status),
+                None => {
+                    _ = child_process.kill();
+                    child_process.wait().ok()
+                }
+            },
+            None => None,
+        };
+
+        let deadline = Instant::now() + self.timeouts.handshake;
+        let (tx, rx) = match (&self.executable, &self.transport) {
+            (Some(executable), transport) => {
+                let (child_process, tx, rx) =
+                    spawn_child(executable, transport, self.framing, self.sinks.clone(), deadline)?;
+                // replaced in place, so the watchdog keeps watching the new process
+                if let Some(mut old) = self.child() {
+                    *old = child_process;
+                }
+                (tx, rx)
+            }
+            (None, Transport::LocalSocket(name)) => attach(name, self.framing, self.sinks.clone(), deadline)?,
+            (None, Transport::Stdio) => unreachable!("only local sockets can be attached to"),
+        };
+        self.tx = tx;
+        self.rx = rx;
+        self.in_flight.clear();
+        self.exiting = false;
+        self.sinks.liveness.set_busy_until(None);
+        self.handshake()?;
+        self.sinks.journal.record(|| JournalEntry::Restarted);
+
+        if let Some(watchdog) = self.watchdog.as_ref().map(|thread| thread.watchdog) {
+            self.sinks.liveness.reset();
+            self.send(IpcCommand::SetHeartbeat(Some(watchdog.interval)))?;
+        }
+
+        Ok(status)
+    }
+
+    pub fn kill(&mut self) -> io::Result<()> {
+        match self.child() {
+            Some(mut child_process) => child_process.kill(),
+            None => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "the recorder daemon is not owned by this link",
+            )),
+        }
+    }
+
+    fn dispatch(&mut self, incoming: Incoming) {
+        let response = match incoming {
+            Incoming::Response(response) => response,
+            Incoming::Line(line) => {
+                log_stray_line(&line);
+                return;
+            }
+        };
+
+        match self.in_flight.get_mut(&response.id) {
+            Some(request) => request.response = Some(response.response),
+            None => log::warn!("discarding response to unknown or timed out request {}", response.id),
+        }
+    }
+
+    /// The stdout of the child has been closed, which means it exited or is about to.
+    fn wait_for_exit(&mut self) -> Option<ExitStatus> {
+        use wait_timeout::ChildExt;
+
+        let mut child_process = self.child()?;
+        child_process.wait_timeout(Duration::from_secs(1)).ok().flatten()
+    }
+
+    /// Tells the watchdog until when the recorder might be busy answering requests.
+    fn update_busy(&self) {
+        let deadline = self.in_flight.values().map(|request| request.deadline).max();
+        self.sinks.liveness.set_busy_until(deadline);
+    }
+
+    fn child(&self) -> Option<MutexGuard<'_, Child>> {
+        let child_process = self.child_process.as_ref()?;
+        Some(child_process.lock().unwrap_or_else(|e| e.into_inner()))
+    }
 }
 
-pub struct IpcLinkSlave<'a> {
-    tx: BufWriter<StdoutLock<'a>>,
-    rx: BufReader<StdinLock<'a>>,
-    buffer: String,
+impl fmt::Debug for IpcLinkMaster {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IpcLinkMaster")
+            .field("executable", &self.executable)
+            .field("transport", &self.transport)
+            .field("framing", &self.framing)
+            .field("child_process", &self.child_process)
+            .field("watchdog", &self.watchdog)
+            .field("timeouts", &self.timeouts)
+            .field("next_id", &self.next_id)
+            .finish_non_exhaustive()
+    }
 }
 
-impl IpcLinkSlave<'_> {
-    #[must_use]
-    pub fn new() -> Self {
-        Self {
-            tx: BufWriter::new(io::stdout().lock()),
-            rx: BufReader::new(io::stdin().lock()),
-            buffer: String::with_capacity(512),
+impl Drop for IpcLinkMaster {
+    fn drop(&mut self) {
+        if self.closed {
+            return;
+        }
+        let report = self.close_in_place(close::DROP_TIMEOUT);
+        if !report.is_clean() {
+            log::warn!("recorder did not close cleanly: {report}");
         }
     }
+}
 
-    pub fn respond(&mut self, mut handler: impl FnMut(IpcCommand) -> Option<IpcResponse>) {
-        loop {
-            let cmd = serde_json::from_str(self.read_line()).unwrap();
+/// Spawns the recorder process and connects to it over `transport`.
+///
+/// Connecting to a local socket is retried until `deadline` since the recorder needs some time to start listening.
+fn spawn_child(
+    executable: &Path,
+    transport: &Transport,
+    framing: Framing,
+    sinks: Sinks,
+    deadline: Instant,
+) -> Result<(Child, BufWriter<Writer>, mpsc::Receiver<Incoming>), Error> {
+    let stdio = || match transport {
+        Transport::Stdio => Stdio::piped(),
+        // anything printed to stdout can't interfere with the protocol, so it does not need to be read
+        Transport::LocalSocket(_) => Stdio::null(),
+    };
+    let mut child_process = Command::new(executable.as_os_str())
+        .args(transport.to_args())
+        .stdin(stdio())
+        .stdout(stdio())
+        .current_dir(executable.parent().unwrap_or_else(|| Path::new(&Component::RootDir)))
+        .spawn()?;
 
-            let Some(response) = handler(cmd) else { break };
-            _ = serde_json::to_writer::<_, IpcResponse>(&mut self.tx, &response);
-            _ = self.tx.write(b"\n");
-            _ = self.tx.flush();
+    let connection = match transport {
+        Transport::Stdio => Ok(transport::child_stdio(&mut child_process)),
+        Transport::LocalSocket(name) => {
+            transport::connect(name, deadline, || matches!(child_process.try_wait(), Ok(None)))
+                .map_err(|e| Error::Handshake(format!("could not connect to recorder at {name}: {e}")))
         }
+    };
 
-        // Send one last IpcResponse::Ok because the other side is waiting for a response to IpcCommand::Exit
-        _ = serde_json::to_writer::<_, IpcResponse>(&mut self.tx, &IpcResponse::Ok);
-        _ = self.tx.write(b"\n");
-        _ = self.tx.flush();
+    let (reader, writer) = match connection {
+        Ok(connection) => connection,
+        Err(e) => {
+            _ = child_process.kill();
+            return Err(e);
+        }
+    };
+    match spawn_reader(reader, framing, sinks) {
+        Ok(rx) => Ok((child_process, BufWriter::new(writer), rx)),
+        Err(e) => {
+            _ = child_process.kill();
+            Err(e.into())
+        }
     }
+}
 
-    fn read_line(&mut self) -> &str {
-        self.buffer.clear();
-        self.rx.read_line(&mut self.buffer).unwrap();
-        &self.buffer
-    }
+/// Connects to a recorder daemon that is already running.
+fn attach(
+    name: &str,
+    framing: Framing,
+    sinks: Sinks,
+    deadline: Instant,
+) -> Result<(BufWriter<Writer>, mpsc::Receiver<Incoming>), Error> {
+    let (reader, writer) = transport::connect(name, deadline, || true)
+        .map_err(|e| Error::Handshake(format!("could not connect to recorder at {name}: {e}")))?;
+    Ok((BufWriter::new(writer), spawn_reader(reader, framing, sinks)?))
 }
 
-impl Default for IpcLinkSlave<'_> {
-    fn default() -> Self {
-        Self::new()
+/// Reads everything the recorder sends on a separate thread.
+///
+/// Starts out reading JSON lines and switches to `framing` once the `Hello` of the recorder has gone by.
+fn spawn_reader(reader: Reader, framing: Framing, sinks: Sinks) -> io::Result<mpsc::Receiver<Incoming>> {
+    let Sinks { subscribers, journal, liveness } = sinks;
+    let (tx, rx) = mpsc::channel();
+
+    thread::Builder::new().name("ipc-link reader".into()).spawn(move || {
+        let mut reader = BufReader::new(reader);
+        let mut current = Framing::JsonLines;
+        let mut frame = Vec::with_capacity(512);
+        loop {
+            match current.read(&mut reader, &mut frame) {
+                Ok(true) => {}
+                Ok(false) => break, // EOF - drops tx which tells the master that the child is gone
+                Err(e) => {
+                    if e.kind() == io::ErrorKind::InvalidData {
+                        log::error!("corrupted stream from recorder: {e}");
+                    }
+                    break;
+                }
+            }
+            let incoming = match current.decode::<IpcMessage>(&frame) {
+                Ok(IpcMessage::Response(response)) => {
+                    journal.record(|| JournalEntry::Response(response.clone()));
+                    liveness.responded();
+                    Incoming::Response(response)
+                }
+                Ok(IpcMessage::Event(event)) => {
+                    journal.record(|| JournalEntry::Event(event.clone()));
+                    broadcast(&subscribers, &event);
+                    continue;
+                }
+                Ok(IpcMessage::Log(record)) => {
+                    journal.record(|| JournalEntry::Log(record.clone()));
+                    forward_log(record);
+                    continue;
+                }
+                Ok(IpcMessage::ProtocolError(e)) => {
+                    log::error!("recorder could not read a request: {e}");
+                    continue;
+                }
+                Ok(IpcMessage::Heartbeat(status)) => {
+                    liveness.heartbeat(status);
+                    continue;
+                }
+                Err(e) if current == Framing::MessagePack => {
+                    log::warn!("discarding malformed message from recorder: {e}");
+                    continue;
+                }
+                Err(_) => {
+                    let line = String::from_utf8_lossy(&frame).into_owned();
+                    if current != framing && Framing::JsonLines.decode::<Hello>(&frame).is_ok() {
+                        current = framing;
+                    }
+                    Incoming::Line(line)
+                }
+            };
+            if tx.send(incoming).is_err() {
+                break;
+            }
+        }
+    })?;
+
+    Ok(rx)
+}
+
+fn broadcast(subscribers: &Subscribers, event: &RecorderEvent) {
+    let mut subscribers = subscribers.lock().unwrap_or_else(|e| e.into_inner());
+    subscribers.retain(|subscriber| subscriber.send(event.clone()).is_ok());
+}
+
+fn forward_log(record: LogRecord) {
+    log::log!(target: &record.target, record.level.into(), "{}", record.message);
+}
+
+/// Only `IpcMessage`s should ever be written to stdout, anything else was printed by something that bypassed `log`.
+fn log_stray_line(line: &str) {
+    // trim newlines from the end because log::warn!() adds one
+    log::warn!("unexpected output from recorder: {}", line.trim_end());
+}
+
+struct ExitStatusDisplay(Option<ExitStatus>);
+
+impl fmt::Display for ExitStatusDisplay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Some(status) => write!(f, "{status}"),
+            None => f.write_str("exit status unknown"),
+        }
     }
 }
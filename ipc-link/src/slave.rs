@@ -0,0 +1,397 @@
+//! Recorder side of the link.
+
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, BufReader, BufWriter},
+    sync::{
+        mpsc::{self, RecvTimeoutError},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use intprocess_recorder::events::RecorderEvent;
+
+use crate::transport::{self, Reader, Writer};
+use crate::{Framing, Hello, IpcCommand, IpcMessage, IpcResponse, LogRecord, Request, RequestId, Response, Transport};
+
+/// Identifies one master connected to the recorder.
+type HostId = u64;
+
+/// Writers of all connected masters.
+type Hosts = Arc<Mutex<HashMap<HostId, Host>>>;
+
+struct Host {
+    tx: BufWriter<Writer>,
+    // negotiated in the handshake, every master picks its own
+    framing: Framing,
+}
+
+impl Host {
+    fn write(&mut self, message: &IpcMessage) -> io::Result<()> {
+        self.framing.write(&mut self.tx, message)
+    }
+}
+
+/// The part of a `Request` that is needed to answer it with `IpcResponse::ProtocolError`.
+///
+/// Decodes as long as the id is intact, even if the command is garbage or unknown to this version.
+#[derive(serde::Deserialize)]
+struct Envelope {
+    id: RequestId,
+    #[serde(rename = "command")]
+    _command: serde::de::IgnoredAny,
+}
+
+/// Heartbeat schedule of one master.
+struct Heartbeat {
+    interval: Duration,
+    due: Instant,
+}
+
+enum HostMessage {
+    Request(HostId, Request),
+    Disconnected(HostId),
+}
+
+pub struct IpcLinkSlave {
+    // shared with all EventSenders and the logger which write from other threads
+    hosts: Hosts,
+    // requests from all masters, read by one thread per connection
+    rx: mpsc::Receiver<HostMessage>,
+    daemon: bool,
+}
+
+impl IpcLinkSlave {
+    /// Talks to the master over stdin/stdout.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::single(Box::new(io::stdin()), Box::new(io::stdout()))
+    }
+
+    /// Talks to the master over `transport` - blocks until the master has connected.
+    pub fn with_transport(transport: &Transport) -> io::Result<Self> {
+        match transport {
+            Transport::Stdio => Ok(Self::new()),
+            Transport::LocalSocket(name) => {
+                let listener = transport::listen(name)?;
+                let (reader, writer) = transport::accept(&listener)?;
+                Ok(Self::single(reader, writer))
+            }
+        }
+    }
+
+    /// Keeps listening on the local socket `transport` and serves every master that connects, sharing one recorder.
+    ///
+    /// Masters attach and detach at any time without affecting each other:
+    /// - only the first `Init` initializes the recorder, every later one is answered with `Ok`
+    /// - `Shutdown` and `Exit` are refused with `Error::InUse` as long as other masters are attached
+    /// - `Detach` (or a closed connection) just removes the master
+    ///
+    /// Events and logs are sent to all attached masters.
+    pub fn daemon(transport: &Transport) -> io::Result<Self> {
+        let Transport::LocalSocket(name) = transport else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "daemon mode requires a local socket",
+            ));
+        };
+
+        let listener = transport::listen(name)?;
+        let hosts = Hosts::default();
+        let (tx, rx) = mpsc::channel();
+
+        let accepted_hosts = hosts.clone();
+        thread::Builder::new().name("ipc-link daemon".into()).spawn(move || {
+            for id in 0.. {
+                match transport::accept(&listener) {
+                    Ok((reader, writer)) => {
+                        if let Err(e) = spawn_connection(id, reader, writer, accepted_hosts.clone(), tx.clone()) {
+                            log::error!("failed to serve master {id}: {e}");
+                        }
+                    }
+                    Err(e) => log::error!("failed to accept master: {e}"),
+                }
+            }
+        })?;
+
+        Ok(Self { hosts, rx, daemon: true })
+    }
+
+    fn single(reader: Reader, writer: Writer) -> Self {
+        let hosts = Hosts::default();
+        let (tx, rx) = mpsc::channel();
+        if let Err(e) = spawn_connection(0, reader, writer, hosts.clone(), tx) {
+            // rx is disconnected, so respond returns immediately
+            log::error!("failed to serve master: {e}");
+        }
+
+        Self { hosts, rx, daemon: false }
+    }
+
+    /// Forwards everything logged through the `log` crate in this process (including libobs) to the master.
+    ///
+    /// With `Transport::Stdio` nothing else should write to stdout since it is reserved for the protocol.
+    pub fn install_logger(&self, level: log::LevelFilter) -> Result<(), log::SetLoggerError> {
+        log::set_boxed_logger(Box::new(SlaveLogger { hosts: self.hosts.clone() }))?;
+        log::set_max_level(level);
+        Ok(())
+    }
+
+    /// Returns a handle for sending `RecorderEvent`s to the master from any thread.
+    #[must_use]
+    pub fn event_sender(&self) -> EventSender {
+        EventSender { hosts: self.hosts.clone() }
+    }
+
+    /// Answers requests with `handler` until it returns `None` for `IpcCommand::Exit`.
+    ///
+    /// Also returns when the master disconnects, unless this is a daemon.
+    ///
+    /// Heartbeats are sent in between requests with the answer of `handler` to `IpcCommand::Status`,
+    /// so they stop as long as `handler` is stuck.
+    pub fn respond(&mut self, mut handler: impl FnMut(IpcCommand) -> Option<IpcResponse>) {
+        let mut initialized = false;
+        let mut heartbeats = HashMap::<HostId, Heartbeat>::new();
+
+        loop {
+            let message = match heartbeats.values().map(|heartbeat| heartbeat.due).min() {
+                Some(due) => match self.rx.recv_timeout(due.saturating_duration_since(Instant::now())) {
+                    Ok(message) => Some(message),
+                    Err(RecvTimeoutError::Timeout) => None,
+                    Err(RecvTimeoutError::Disconnected) => return,
+                },
+                None => match self.rx.recv() {
+                    Ok(message) => Some(message),
+                    Err(_) => return,
+                },
+            };
+            self.send_heartbeats(&mut heartbeats, &mut handler);
+
+            let (host, request) = match message {
+                None => continue,
+                Some(HostMessage::Request(host, request)) => (host, request),
+                Some(HostMessage::Disconnected(host)) if self.daemon => {
+                    log::debug!("master {host} detached");
+                    heartbeats.remove(&host);
+                    continue;
+                }
+                Some(HostMessage::Disconnected(_)) => return,
+            };
+
+            let attached = self.hosts.lock().unwrap_or_else(|e| e.into_inner()).len();
+            let response = match request.command {
+                IpcCommand::Detach => {
+                    self.reply(host, request.id, IpcResponse::Ok);
+                    self.hosts.lock().unwrap_or_else(|e| e.into_inner()).remove(&host);
+                    heartbeats.remove(&host);
+                    if self.daemon {
+                        continue;
+                    }
+                    return;
+                }
+                IpcCommand::SetHeartbeat(Some(interval)) if !interval.is_zero() => {
+                    let due = Instant::now() + interval;
+                    heartbeats.insert(host, Heartbeat { interval, due });
+                    Some(IpcResponse::Ok)
+                }
+                IpcCommand::SetHeartbeat(_) => {
+                    heartbeats.remove(&host);
+                    Some(IpcResponse::Ok)
+                }
+                IpcCommand::Init { .. } if self.daemon && initialized => Some(IpcResponse::Ok),
+                IpcCommand::Shutdown | IpcCommand::Exit if attached > 1 => {
+                    Some(IpcResponse::Err(intprocess_recorder::Error::InUse))
+                }
+                command => {
+                    let init = matches!(command, IpcCommand::Init { .. });
+                    let response = handler(command);
+                    initialized |= init && response == Some(IpcResponse::Ok);
+                    response
+                }
+            };
+
+            let Some(response) = response else {
+                // Send one last IpcResponse::Ok because the other side is waiting for a response to IpcCommand::Exit
+                self.reply(host, request.id, IpcResponse::Ok);
+                return;
+            };
+            self.reply(host, request.id, response);
+        }
+    }
+
+    /// Sends a heartbeat to every master whose heartbeat is due.
+    fn send_heartbeats(
+        &self,
+        heartbeats: &mut HashMap<HostId, Heartbeat>,
+        handler: &mut impl FnMut(IpcCommand) -> Option<IpcResponse>,
+    ) {
+        let now = Instant::now();
+        if heartbeats.values().all(|heartbeat| heartbeat.due > now) {
+            return;
+        }
+
+        let status = match handler(IpcCommand::Status) {
+            Some(IpcResponse::Status(status)) => Some(status),
+            _ => None,
+        };
+        for (host, heartbeat) in heartbeats.iter_mut().filter(|(_, heartbeat)| heartbeat.due <= now) {
+            heartbeat.due = now + heartbeat.interval;
+            send_to(&self.hosts, *host, &IpcMessage::Heartbeat(status));
+        }
+    }
+
+    fn reply(&self, host: HostId, id: u64, response: IpcResponse) {
+        let mut hosts = self.hosts.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(host) = hosts.get_mut(&host) {
+            _ = host.write(&IpcMessage::Response(Response { id, response }));
+        }
+    }
+}
+
+impl Default for IpcLinkSlave {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Reads the requests of one master on a separate thread and forwards them to `IpcLinkSlave::respond`.
+///
+/// Requests that can't be decoded are answered with `IpcResponse::ProtocolError` right away.
+fn spawn_connection(
+    id: HostId,
+    reader: Reader,
+    writer: Writer,
+    hosts: Hosts,
+    tx: mpsc::Sender<HostMessage>,
+) -> io::Result<()> {
+    thread::Builder::new()
+        .name(format!("ipc-link master {id}"))
+        .spawn(move || {
+            let mut rx = BufReader::new(reader);
+            let mut writer = BufWriter::new(writer);
+            let mut frame = Vec::with_capacity(512);
+
+            if let Some(framing) = handshake(&mut rx, &mut writer, &mut frame) {
+                hosts
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .insert(id, Host { tx: writer, framing });
+
+                let mut detached = false;
+                while !detached {
+                    match framing.read(&mut rx, &mut frame) {
+                        Ok(true) => {}
+                        Ok(false) => break, // EOF - the master is gone
+                        Err(e) => {
+                            // the stream is out of sync, so there is no telling where the next request starts
+                            if e.kind() == io::ErrorKind::InvalidData {
+                                log::error!("corrupted stream from master {id}: {e}");
+                                send_to(&hosts, id, &IpcMessage::ProtocolError(e.to_string()));
+                            }
+                            break;
+                        }
+                    }
+                    match framing.decode::<Request>(&frame) {
+                        Ok(request) => {
+                            // respond removes the writer after answering
+                            detached = matches!(request.command, IpcCommand::Detach);
+                            if tx.send(HostMessage::Request(id, request)).is_err() {
+                                break;
+                            }
+                        }
+                        Err(e) => {
+                            log::error!("invalid request from master {id}: {e}");
+                            let message = match framing.decode::<Envelope>(&frame) {
+                                Ok(envelope) => IpcMessage::Response(Response {
+                                    id: envelope.id,
+                                    response: IpcResponse::ProtocolError(e),
+                                }),
+                                Err(_) => IpcMessage::ProtocolError(e),
+                            };
+                            send_to(&hosts, id, &message);
+                        }
+                    }
+                }
+
+                if !detached {
+                    hosts.lock().unwrap_or_else(|e| e.into_inner()).remove(&id);
+                }
+            }
+
+            _ = tx.send(HostMessage::Disconnected(id));
+        })?;
+
+    Ok(())
+}
+
+/// Answers the `Hello` of the master with our own and returns the framing it asked for if both sides are compatible.
+///
+/// The answer is always sent, even on a mismatch, so the master can report which versions disagree.
+fn handshake(rx: &mut impl BufRead, tx: &mut BufWriter<Writer>, frame: &mut Vec<u8>) -> Option<Framing> {
+    let master = match Framing::JsonLines.read(rx, frame) {
+        Ok(false) | Err(_) => return None,
+        Ok(true) => Framing::JsonLines.decode::<Hello>(frame),
+    };
+
+    // every framing is supported, so the one the master asked for is confirmed
+    let hello = Hello::with_framing(master.as_ref().map_or(Framing::default(), |master| master.framing));
+    _ = Framing::JsonLines.write(tx, &hello);
+
+    match master {
+        Ok(master) if master.is_compatible(&hello) => Some(master.framing),
+        Ok(master) => {
+            log::error!("incompatible master ({master}) - this recorder is ({hello})");
+            None
+        }
+        Err(e) => {
+            log::error!("invalid handshake from master: {e}");
+            None
+        }
+    }
+}
+
+fn send_to(hosts: &Hosts, id: HostId, message: &IpcMessage) {
+    if let Some(host) = hosts.lock().unwrap_or_else(|e| e.into_inner()).get_mut(&id) {
+        _ = host.write(message);
+    }
+}
+
+/// Writes `message` to every attached master and drops the ones that can't be written to anymore.
+fn broadcast(hosts: &Hosts, message: &IpcMessage) {
+    let mut hosts = hosts.lock().unwrap_or_else(|e| e.into_inner());
+    hosts.retain(|_, host| host.write(message).is_ok());
+}
+
+#[derive(Clone)]
+pub struct EventSender {
+    hosts: Hosts,
+}
+
+impl EventSender {
+    pub fn send(&self, event: RecorderEvent) {
+        broadcast(&self.hosts, &IpcMessage::Event(event));
+    }
+}
+
+struct SlaveLogger {
+    hosts: Hosts,
+}
+
+impl log::Log for SlaveLogger {
+    fn enabled(&self, _: &log::Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::Record) {
+        let record = LogRecord {
+            level: record.level().into(),
+            target: record.target().to_string(),
+            message: record.args().to_string(),
+        };
+        broadcast(&self.hosts, &IpcMessage::Log(record));
+    }
+
+    fn flush(&self) {}
+}
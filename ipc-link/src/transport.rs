@@ -0,0 +1,160 @@
+//! Channels the master and the recorder process can exchange messages over.
+
+use std::{
+    fmt,
+    io::{self, Read, Write},
+    process::Child,
+    str::FromStr,
+    sync::atomic::{AtomicU32, Ordering},
+    thread,
+    time::{Duration, Instant},
+};
+
+use interprocess::local_socket::{prelude::*, ListenerOptions, Name};
+
+pub(crate) use interprocess::local_socket::Listener;
+
+/// Command line argument of the recorder process that selects the transport.
+pub const TRANSPORT_ARG: &str = "--transport";
+
+/// Command line argument that makes the recorder process serve multiple masters on a `Transport::LocalSocket`.
+pub const DAEMON_ARG: &str = "--daemon";
+
+pub(crate) type Reader = Box<dyn Read + Send>;
+pub(crate) type Writer = Box<dyn Write + Send>;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum Transport {
+    /// stdin/stdout of the recorder process
+    #[default]
+    Stdio,
+    /// Unix domain socket (file path) or Windows named pipe (pipe name) that the recorder process listens on
+    ///
+    /// Keeps the protocol separate from everything libobs, plugins or C runtimes print to stdout.
+    LocalSocket(String),
+}
+
+impl Transport {
+    /// Creates a `LocalSocket` with a name that is unique to this process.
+    #[must_use]
+    pub fn local_socket() -> Self {
+        static COUNTER: AtomicU32 = AtomicU32::new(0);
+
+        let name = format!(
+            "libobs-recorder-{}-{}",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        );
+        #[cfg(not(windows))]
+        let name = std::env::temp_dir()
+            .join(format!("{name}.sock"))
+            .to_string_lossy()
+            .into_owned();
+
+        Self::LocalSocket(name)
+    }
+
+    /// Arguments that tell the recorder process to use this transport.
+    #[must_use]
+    pub fn to_args(&self) -> Vec<String> {
+        match self {
+            // no arguments to stay compatible with recorders that predate transports
+            Transport::Stdio => Vec::new(),
+            transport => vec![TRANSPORT_ARG.to_string(), transport.to_string()],
+        }
+    }
+
+    /// Reads the transport selected by the master from the command line arguments of the recorder process.
+    ///
+    /// Defaults to `Transport::Stdio` if there is no `--transport` argument.
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> io::Result<Self> {
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            if arg == TRANSPORT_ARG {
+                let value = args
+                    .next()
+                    .ok_or_else(|| invalid_input(format!("missing value for {TRANSPORT_ARG}")))?;
+                return value.parse();
+            }
+        }
+        Ok(Self::Stdio)
+    }
+}
+
+impl fmt::Display for Transport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Transport::Stdio => f.write_str("stdio"),
+            Transport::LocalSocket(name) => write!(f, "socket:{name}"),
+        }
+    }
+}
+
+impl FromStr for Transport {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            _ if s == "stdio" => Ok(Self::Stdio),
+            Some(("socket", name)) if !name.is_empty() => Ok(Self::LocalSocket(name.to_string())),
+            _ => Err(invalid_input(format!("invalid transport: {s}"))),
+        }
+    }
+}
+
+/// Master side of the stdio transport.
+pub(crate) fn child_stdio(child_process: &mut Child) -> (Reader, Writer) {
+    let stdout = child_process
+        .stdout
+        .take()
+        .expect("stdout of the recorder should be piped");
+    let stdin = child_process
+        .stdin
+        .take()
+        .expect("stdin of the recorder should be piped");
+    (Box::new(stdout), Box::new(stdin))
+}
+
+/// Master side of the local socket transport.
+///
+/// Retries until `deadline` because the recorder process might not be listening yet.
+/// Gives up early once `alive` returns false.
+pub(crate) fn connect(name: &str, deadline: Instant, mut alive: impl FnMut() -> bool) -> io::Result<(Reader, Writer)> {
+    let name = socket_name(name)?;
+    loop {
+        match LocalSocketStream::connect(name.borrow()) {
+            Ok(stream) => {
+                let (rx, tx) = stream.split();
+                return Ok((Box::new(rx), Box::new(tx)));
+            }
+            Err(e) if Instant::now() >= deadline || !alive() => return Err(e),
+            Err(_) => thread::sleep(Duration::from_millis(10)),
+        }
+    }
+}
+
+/// Recorder side of the local socket transport.
+pub(crate) fn listen(name: &str) -> io::Result<Listener> {
+    // try_overwrite removes a socket file that has been left behind by a crashed recorder
+    ListenerOptions::new()
+        .name(socket_name(name)?)
+        .try_overwrite(true)
+        .create_sync()
+}
+
+/// Waits for the next master to connect.
+pub(crate) fn accept(listener: &Listener) -> io::Result<(Reader, Writer)> {
+    let (rx, tx) = listener.accept()?.split();
+    Ok((Box::new(rx), Box::new(tx)))
+}
+
+fn socket_name(name: &str) -> io::Result<Name<'_>> {
+    #[cfg(windows)]
+    return name.to_ns_name::<interprocess::local_socket::GenericNamespaced>();
+    #[cfg(not(windows))]
+    return name.to_fs_name::<interprocess::local_socket::GenericFilePath>();
+}
+
+fn invalid_input(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message)
+}
@@ -0,0 +1,184 @@
+//! Notices a recorder that is still running but has stopped doing anything (e.g. a wedged libobs graphics thread).
+
+use std::{
+    process::Child,
+    sync::{
+        mpsc::{self, RecvTimeoutError},
+        Arc, Mutex, MutexGuard,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use intprocess_recorder::events::RecorderEvent;
+use intprocess_recorder::status::Status;
+
+use crate::{broadcast, Subscribers};
+
+/// Makes the recorder send a heartbeat every `interval` and reacts once `missed_heartbeats` in a row did not arrive.
+///
+/// A heartbeat only counts if the frame counter of libobs went up since the previous one,
+/// so a recorder whose graphics thread is stuck misses heartbeats even though it keeps sending them.
+/// Time spent waiting for a response does not count either, the timeout of the command covers that.
+/// Any response from the recorder counts as a heartbeat.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Watchdog {
+    pub interval: Duration,
+    pub missed_heartbeats: u32,
+    pub action: WatchdogAction,
+}
+
+impl Watchdog {
+    #[must_use]
+    pub fn new(interval: Duration, missed_heartbeats: u32, action: WatchdogAction) -> Self {
+        Self {
+            interval,
+            missed_heartbeats,
+            action,
+        }
+    }
+}
+
+impl Default for Watchdog {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(1), 5, WatchdogAction::Notify)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchdogAction {
+    /// only sends `RecorderEvent::Unresponsive` to all subscribers
+    Notify,
+    /// kills the recorder process and sends `RecorderEvent::Unresponsive`
+    ///
+    /// The next command fails with `Error::ChildExited` and the link can be restarted.
+    /// A link attached to a daemon can't kill it and only notifies.
+    Kill,
+}
+
+/// When the recorder was last seen alive, shared between the master, its reader thread and the watchdog thread.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Liveness(Arc<Mutex<State>>);
+
+#[derive(Debug)]
+struct State {
+    last_seen: Instant,
+    // deadline of the request in flight that is due last
+    busy_until: Option<Instant>,
+    last_status: Option<Status>,
+    // the silence has already been reported
+    reported: bool,
+}
+
+impl Default for State {
+    fn default() -> Self {
+        Self {
+            last_seen: Instant::now(),
+            busy_until: None,
+            last_status: None,
+            reported: false,
+        }
+    }
+}
+
+impl State {
+    fn alive(&mut self) {
+        self.last_seen = Instant::now();
+        self.reported = false;
+    }
+}
+
+impl Liveness {
+    /// Starts counting missed heartbeats from now on.
+    pub(crate) fn reset(&self) {
+        *self.state() = State::default();
+    }
+
+    pub(crate) fn responded(&self) {
+        self.state().alive();
+    }
+
+    pub(crate) fn heartbeat(&self, status: Option<Status>) {
+        let mut state = self.state();
+        // an uninitialized recorder does not render anything, so only an initialized one can be stuck
+        let rendering = match (status, state.last_status) {
+            (Some(status), Some(previous)) => status.frames.rendered != previous.frames.rendered,
+            _ => true,
+        };
+        state.last_status = status;
+        if rendering {
+            state.alive();
+        }
+    }
+
+    pub(crate) fn set_busy_until(&self, deadline: Option<Instant>) {
+        self.state().busy_until = deadline;
+    }
+
+    pub(crate) fn last_status(&self) -> Option<Status> {
+        self.state().last_status
+    }
+
+    /// Returns the number of heartbeats missed in a row if that has not been reported yet and marks it as reported.
+    fn report(&self, interval: Duration, missed_heartbeats: u32) -> Option<u32> {
+        let mut state = self.state();
+        let now = Instant::now();
+        let since = match state.busy_until {
+            Some(deadline) if deadline > now => return None,
+            Some(deadline) => deadline.max(state.last_seen),
+            None => state.last_seen,
+        };
+        let missed =
+            u32::try_from(now.duration_since(since).as_nanos() / interval.as_nanos().max(1)).unwrap_or(u32::MAX);
+        if state.reported || missed < missed_heartbeats.max(1) {
+            return None;
+        }
+        state.reported = true;
+        Some(missed)
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Stops the watchdog thread when dropped.
+#[derive(Debug)]
+pub(crate) struct WatchdogThread {
+    pub(crate) watchdog: Watchdog,
+    _stop: mpsc::Sender<()>,
+}
+
+impl WatchdogThread {
+    pub(crate) fn spawn(
+        watchdog: Watchdog,
+        liveness: Liveness,
+        subscribers: Subscribers,
+        // `None` for a link attached to a daemon
+        child_process: Option<Arc<Mutex<Child>>>,
+    ) -> std::io::Result<Self> {
+        let (stop, stopped) = mpsc::channel();
+
+        thread::Builder::new().name("ipc-link watchdog".into()).spawn(move || {
+            // check often enough to react within a fraction of the interval
+            let tick = watchdog.interval / 4;
+            while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(tick) {
+                let Some(missed_heartbeats) = liveness.report(watchdog.interval, watchdog.missed_heartbeats) else {
+                    continue;
+                };
+
+                let killed = match (&watchdog.action, &child_process) {
+                    (WatchdogAction::Kill, Some(child_process)) => {
+                        let mut child_process = child_process.lock().unwrap_or_else(|e| e.into_inner());
+                        child_process.kill().is_ok()
+                    }
+                    _ => false,
+                };
+                log::error!("recorder missed {missed_heartbeats} heartbeats (killed: {killed})");
+                broadcast(&subscribers, &RecorderEvent::Unresponsive { missed_heartbeats, killed });
+            }
+        })?;
+
+        Ok(Self { watchdog, _stop: stop })
+    }
+}
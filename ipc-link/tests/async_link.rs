@@ -0,0 +1,160 @@
+use std::time::{Duration, Instant};
+
+use intprocess_recorder::events::{RecorderEvent, StopReason};
+use ipc_link::{AsyncIpcLinkMaster, Error, IpcCommand, IpcResponse, StageOutcome, Timeouts};
+
+const FAKE_SLAVE: &str = env!("CARGO_BIN_EXE_fake_slave");
+
+fn init(script: &str) -> IpcCommand {
+    IpcCommand::Init {
+        libobs_data_path: Some(script.into()),
+        plugin_bin_path: None,
+        plugin_data_path: None,
+    }
+}
+
+#[tokio::test]
+async fn request_response() {
+    let link = AsyncIpcLinkMaster::new(FAKE_SLAVE).await.unwrap();
+
+    assert_eq!(link.send(init("")).await.unwrap(), IpcResponse::Ok);
+    assert_eq!(
+        link.send(IpcCommand::IsRecording).await.unwrap(),
+        IpcResponse::Recording(false)
+    );
+    assert_eq!(link.send(IpcCommand::Exit).await.unwrap(), IpcResponse::Ok);
+}
+
+#[tokio::test]
+async fn concurrent_requests_are_matched_by_id() {
+    let link = AsyncIpcLinkMaster::new(FAKE_SLAVE).await.unwrap();
+
+    let (first, second) = tokio::join!(link.send(init("delay:50")), link.send(IpcCommand::IsRecording));
+    assert_eq!(first.unwrap(), IpcResponse::Ok);
+    assert_eq!(second.unwrap(), IpcResponse::Recording(false));
+}
+
+#[tokio::test]
+async fn cancelled_request_does_not_desynchronize() {
+    let link = AsyncIpcLinkMaster::new(FAKE_SLAVE).await.unwrap();
+
+    let cancelled = tokio::time::timeout(Duration::from_millis(50), link.send(init("delay:300"))).await;
+    assert!(cancelled.is_err());
+
+    // the late answer to Init must not be mistaken for the answer to IsRecording
+    assert_eq!(
+        link.send(IpcCommand::IsRecording).await.unwrap(),
+        IpcResponse::Recording(false)
+    );
+    assert_eq!(link.send(IpcCommand::Exit).await.unwrap(), IpcResponse::Ok);
+}
+
+#[tokio::test]
+async fn hanging_recorder_times_out() {
+    let timeouts = Timeouts {
+        init: Duration::from_millis(200),
+        ..Timeouts::default()
+    };
+    let link = AsyncIpcLinkMaster::with_timeouts(FAKE_SLAVE, timeouts).await.unwrap();
+
+    let result = link.send(init("hang")).await;
+    assert!(
+        matches!(result, Err(Error::Timeout { command: "Init", .. })),
+        "{result:?}"
+    );
+    assert!(link.exit_status().await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn crashed_recorder_reports_exit_status() {
+    let link = AsyncIpcLinkMaster::new(FAKE_SLAVE).await.unwrap();
+
+    let result = link.send(init("crash:3")).await;
+    assert!(
+        matches!(result, Err(Error::ChildExited(Some(status))) if status.code() == Some(3)),
+        "{result:?}"
+    );
+}
+
+#[tokio::test]
+async fn events_are_streamed() {
+    let link = AsyncIpcLinkMaster::new(FAKE_SLAVE).await.unwrap();
+    let mut events = link.events();
+
+    link.send(IpcCommand::StartRecording).await.unwrap();
+    link.send(IpcCommand::StopRecording).await.unwrap();
+
+    assert_eq!(events.next().await, Some(RecorderEvent::RecordingStarted));
+    assert_eq!(
+        events.next().await,
+        Some(RecorderEvent::RecordingStopped {
+            reason: StopReason::Success,
+            error: None
+        })
+    );
+
+    link.send(IpcCommand::Exit).await.unwrap();
+    assert_eq!(events.next().await, None);
+}
+
+#[tokio::test]
+async fn close_waits_for_the_recording_to_stop() {
+    let link = AsyncIpcLinkMaster::new(FAKE_SLAVE).await.unwrap();
+    link.send(init("stop:300")).await.unwrap();
+    link.send(IpcCommand::StartRecording).await.unwrap();
+
+    let started = Instant::now();
+    let report = link.close(Duration::from_secs(5)).await;
+    assert!(started.elapsed() >= Duration::from_millis(300));
+    assert_eq!(report.stop_recording, StageOutcome::Done);
+    assert_eq!(report.shutdown, StageOutcome::Done);
+    assert_eq!(report.exit, StageOutcome::Done);
+    assert!(report.exit_status.unwrap().success());
+    assert!(report.is_clean(), "{report}");
+}
+
+#[tokio::test]
+async fn close_kills_after_deadline() {
+    let link = AsyncIpcLinkMaster::new(FAKE_SLAVE).await.unwrap();
+    link.send(init("stop:5000")).await.unwrap();
+    link.send(IpcCommand::StartRecording).await.unwrap();
+
+    let started = Instant::now();
+    let report = link.close(Duration::from_millis(300)).await;
+    assert!(started.elapsed() < Duration::from_secs(2));
+    assert_eq!(report.stop_recording, StageOutcome::TimedOut);
+    assert_eq!(report.shutdown, StageOutcome::Skipped);
+    assert_eq!(report.exit, StageOutcome::Skipped);
+    assert!(report.killed);
+}
+
+#[tokio::test]
+async fn close_after_exit_only_waits_for_the_process() {
+    let link = AsyncIpcLinkMaster::new(FAKE_SLAVE).await.unwrap();
+    link.send(IpcCommand::Exit).await.unwrap();
+
+    let report = link.close(Duration::from_secs(5)).await;
+    assert_eq!(report.stop_recording, StageOutcome::NotNeeded);
+    assert_eq!(report.shutdown, StageOutcome::NotNeeded);
+    assert!(report.is_clean(), "{report}");
+}
+
+#[tokio::test]
+async fn dropping_lets_the_recording_finish() {
+    let link = AsyncIpcLinkMaster::new(FAKE_SLAVE).await.unwrap();
+    link.send(init("stop:300")).await.unwrap();
+    link.send(IpcCommand::StartRecording).await.unwrap();
+    let mut events = link.events();
+
+    drop(link);
+    let timeout = Duration::from_secs(5);
+    assert_eq!(
+        tokio::time::timeout(timeout, events.next()).await,
+        Ok(Some(RecorderEvent::RecordingStopped {
+            reason: StopReason::Success,
+            error: None
+        }))
+    );
+    // and exits afterwards
+    assert_eq!(tokio::time::timeout(timeout, events.next()).await, Ok(None));
+}
@@ -0,0 +1,112 @@
+use std::process::{Child, Command};
+use std::time::Duration;
+
+use intprocess_recorder::events::RecorderEvent;
+use ipc_link::{Error, IpcCommand, IpcLinkMaster, IpcResponse, Timeouts, Transport, DAEMON_ARG};
+
+const FAKE_SLAVE: &str = env!("CARGO_BIN_EXE_fake_slave");
+
+struct Daemon {
+    name: String,
+    process: Child,
+}
+
+impl Daemon {
+    fn start() -> Self {
+        let transport = Transport::local_socket();
+        let process = Command::new(FAKE_SLAVE)
+            .arg(DAEMON_ARG)
+            .args(transport.to_args())
+            .spawn()
+            .unwrap();
+        let Transport::LocalSocket(name) = transport else {
+            unreachable!()
+        };
+        Self { name, process }
+    }
+
+    fn attach(&self) -> IpcLinkMaster {
+        IpcLinkMaster::connect(&self.name, Timeouts::default()).unwrap()
+    }
+}
+
+impl Drop for Daemon {
+    fn drop(&mut self) {
+        _ = self.process.kill();
+        _ = self.process.wait();
+    }
+}
+
+fn init() -> IpcCommand {
+    IpcCommand::Init {
+        libobs_data_path: None,
+        plugin_bin_path: None,
+        plugin_data_path: None,
+    }
+}
+
+#[test]
+fn masters_share_one_recorder() {
+    let daemon = Daemon::start();
+    let mut first = daemon.attach();
+    let mut second = daemon.attach();
+    assert!(!first.owns_recorder());
+
+    assert_eq!(first.send(init()).unwrap(), IpcResponse::Ok);
+    // only the first Init initializes the shared recorder
+    assert_eq!(second.send(init()).unwrap(), IpcResponse::Ok);
+
+    let events = second.subscribe();
+    assert_eq!(first.send(IpcCommand::StartRecording).unwrap(), IpcResponse::Ok);
+    assert_eq!(
+        events.recv_timeout(Duration::from_secs(5)).unwrap(),
+        RecorderEvent::RecordingStarted
+    );
+}
+
+#[test]
+fn shutdown_is_refused_while_others_are_attached() {
+    let mut daemon = Daemon::start();
+    let mut first = daemon.attach();
+    let second = daemon.attach();
+
+    assert_eq!(
+        first.send(IpcCommand::Shutdown).unwrap(),
+        IpcResponse::Err(intprocess_recorder::Error::InUse)
+    );
+    assert_eq!(
+        first.send(IpcCommand::Exit).unwrap(),
+        IpcResponse::Err(intprocess_recorder::Error::InUse)
+    );
+
+    // dropping an attached link only detaches it
+    drop(second);
+    assert_eq!(
+        first.send(IpcCommand::IsRecording).unwrap(),
+        IpcResponse::Recording(false)
+    );
+
+    // the last master may stop the daemon
+    assert_eq!(first.send(IpcCommand::Shutdown).unwrap(), IpcResponse::Ok);
+    assert_eq!(first.send(IpcCommand::Exit).unwrap(), IpcResponse::Ok);
+    assert!(daemon.process.wait().unwrap().success());
+    assert!(matches!(
+        first.send(IpcCommand::IsRecording),
+        Err(Error::ChildExited(None))
+    ));
+}
+
+#[test]
+fn daemon_outlives_its_masters() {
+    let daemon = Daemon::start();
+
+    let mut first = daemon.attach();
+    assert_eq!(first.send(init()).unwrap(), IpcResponse::Ok);
+    drop(first);
+
+    let mut second = daemon.attach();
+    assert_eq!(
+        second.send(IpcCommand::IsRecording).unwrap(),
+        IpcResponse::Recording(false)
+    );
+}
@@ -0,0 +1,211 @@
+use std::io::{BufRead, BufReader, Read, Write};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+use std::time::Duration;
+
+use intprocess_recorder::events::RecorderEvent;
+use intprocess_recorder::settings::{Encoder, RecorderSettings, StdResolution, Window};
+use ipc_link::{Framing, Hello, IpcCommand, IpcLinkMaster, IpcMessage, IpcResponse, Response, Timeouts, Transport};
+
+const FAKE_SLAVE: &str = env!("CARGO_BIN_EXE_fake_slave");
+
+fn settings(encoder: Encoder) -> RecorderSettings {
+    let mut settings = RecorderSettings::new(
+        Window::new("window", Some("class".into()), Some("process.exe".into())),
+        StdResolution::_1920x1080p,
+        StdResolution::_1280x720p,
+        "./output.mp4",
+    );
+    settings.set_encoder(encoder);
+    settings
+}
+
+#[test]
+fn message_pack_round_trip() {
+    for transport in [Transport::Stdio, Transport::local_socket()] {
+        let mut link =
+            IpcLinkMaster::with_framing(FAKE_SLAVE, transport, Framing::MessagePack, Timeouts::default()).unwrap();
+        assert_eq!(link.framing(), Framing::MessagePack);
+        let events = link.subscribe();
+
+        assert_eq!(
+            link.send(IpcCommand::Configure(settings(Encoder::OBS_X264))).unwrap(),
+            IpcResponse::Ok
+        );
+        assert_eq!(
+            link.send(IpcCommand::Configure(settings(Encoder::JIM_NVENC))).unwrap(),
+            IpcResponse::Err(intprocess_recorder::Error::EncoderUnavailable(Encoder::JIM_NVENC))
+        );
+        assert_eq!(
+            link.send(IpcCommand::Encoders).unwrap(),
+            IpcResponse::Encoders {
+                available: vec![Encoder::OBS_X264],
+                selected: Encoder::OBS_X264
+            }
+        );
+        assert_eq!(link.send(IpcCommand::StartRecording).unwrap(), IpcResponse::Ok);
+        assert_eq!(
+            events.recv_timeout(Duration::from_secs(5)).unwrap(),
+            RecorderEvent::RecordingStarted
+        );
+        assert_eq!(link.send(IpcCommand::Exit).unwrap(), IpcResponse::Ok);
+    }
+}
+
+#[test]
+fn framing_survives_a_restart() {
+    let mut link =
+        IpcLinkMaster::with_framing(FAKE_SLAVE, Transport::Stdio, Framing::MessagePack, Timeouts::default()).unwrap();
+
+    link.kill().unwrap();
+    link.restart().unwrap();
+    assert_eq!(
+        link.send(IpcCommand::IsRecording).unwrap(),
+        IpcResponse::Recording(false)
+    );
+}
+
+/// Talks to the fake slave by hand, so it can be fed frames `IpcLinkMaster` would never send.
+struct RawLink {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    framing: Framing,
+}
+
+impl RawLink {
+    fn new(framing: Framing) -> Self {
+        let mut child = Command::new(FAKE_SLAVE)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let mut stdin = child.stdin.take().unwrap();
+        let mut stdout = BufReader::new(child.stdout.take().unwrap());
+
+        serde_json::to_writer(&mut stdin, &Hello::with_framing(framing)).unwrap();
+        stdin.write_all(b"\n").unwrap();
+        let mut line = String::new();
+        stdout.read_line(&mut line).unwrap();
+        assert_eq!(serde_json::from_str::<Hello>(&line).unwrap().framing, framing);
+
+        Self { child, stdin, stdout, framing }
+    }
+
+    fn send_frame(&mut self, payload: &[u8]) {
+        match self.framing {
+            Framing::JsonLines => {
+                self.stdin.write_all(payload).unwrap();
+                self.stdin.write_all(b"\n").unwrap();
+            }
+            Framing::MessagePack => {
+                self.stdin.write_all(&(payload.len() as u32).to_le_bytes()).unwrap();
+                self.stdin.write_all(payload).unwrap();
+            }
+        }
+        self.stdin.flush().unwrap();
+    }
+
+    /// Returns the next message that is not a log record.
+    fn receive(&mut self) -> Option<IpcMessage> {
+        loop {
+            let message = match self.framing {
+                Framing::JsonLines => {
+                    let mut line = String::new();
+                    if self.stdout.read_line(&mut line).ok()? == 0 {
+                        return None;
+                    }
+                    serde_json::from_str(&line).unwrap()
+                }
+                Framing::MessagePack => {
+                    let mut len = [0; 4];
+                    self.stdout.read_exact(&mut len).ok()?;
+                    let mut payload = vec![0; u32::from_le_bytes(len) as usize];
+                    self.stdout.read_exact(&mut payload).unwrap();
+                    rmp_serde::from_slice(&payload).unwrap()
+                }
+            };
+            match message {
+                IpcMessage::Log(_) => continue,
+                message => return Some(message),
+            }
+        }
+    }
+
+    fn request(&mut self, id: u64, command: IpcCommand) -> IpcResponse {
+        let request = ipc_link::Request { id, command };
+        let payload = match self.framing {
+            Framing::JsonLines => serde_json::to_vec(&request).unwrap(),
+            Framing::MessagePack => rmp_serde::to_vec(&request).unwrap(),
+        };
+        self.send_frame(&payload);
+        match self.receive() {
+            Some(IpcMessage::Response(response)) if response.id == id => response.response,
+            other => panic!("unexpected answer: {other:?}"),
+        }
+    }
+}
+
+impl Drop for RawLink {
+    fn drop(&mut self) {
+        _ = self.child.kill();
+        _ = self.child.wait();
+    }
+}
+
+#[derive(serde::Serialize)]
+struct UnknownRequest {
+    id: u64,
+    command: &'static str,
+}
+
+#[test]
+fn malformed_requests_get_a_protocol_error() {
+    for framing in [Framing::JsonLines, Framing::MessagePack] {
+        let mut link = RawLink::new(framing);
+
+        // the id is intact, so the error can be matched to the request
+        let unknown = UnknownRequest { id: 7, command: "SelfDestruct" };
+        let payload = match framing {
+            Framing::JsonLines => serde_json::to_vec(&unknown).unwrap(),
+            Framing::MessagePack => rmp_serde::to_vec(&unknown).unwrap(),
+        };
+        link.send_frame(&payload);
+        assert!(
+            matches!(
+                link.receive(),
+                Some(IpcMessage::Response(Response {
+                    id: 7,
+                    response: IpcResponse::ProtocolError(_)
+                }))
+            ),
+            "{framing}"
+        );
+
+        // not even an id
+        link.send_frame(&[0xc1, 0xff, 0x00, b'{']);
+        assert!(
+            matches!(link.receive(), Some(IpcMessage::ProtocolError(_))),
+            "{framing}"
+        );
+
+        // the connection is still usable
+        assert_eq!(
+            link.request(8, IpcCommand::IsRecording),
+            IpcResponse::Recording(false),
+            "{framing}"
+        );
+    }
+}
+
+#[test]
+fn oversized_frame_ends_the_connection() {
+    let mut link = RawLink::new(Framing::MessagePack);
+
+    link.stdin.write_all(&u32::MAX.to_le_bytes()).unwrap();
+    link.stdin.flush().unwrap();
+
+    assert!(matches!(link.receive(), Some(IpcMessage::ProtocolError(_))));
+    // the slave can't tell where the next frame starts, so it gives up on the master
+    assert!(link.receive().is_none());
+    assert!(link.child.wait().unwrap().success());
+}
@@ -0,0 +1,176 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use intprocess_recorder::events::RecorderEvent;
+use intprocess_recorder::settings::Encoder;
+use ipc_link::journal::{self, Mismatch, Outcome};
+use ipc_link::{IpcCommand, IpcLinkMaster, IpcResponse, JournalEntry, Timeouts};
+
+const FAKE_SLAVE: &str = env!("CARGO_BIN_EXE_fake_slave");
+
+fn init(script: &str) -> IpcCommand {
+    IpcCommand::Init {
+        libobs_data_path: Some(script.into()),
+        plugin_bin_path: None,
+        plugin_data_path: None,
+    }
+}
+
+fn journal_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("ipc-link-{}-{name}.jsonl", std::process::id()))
+}
+
+fn record_session(path: &PathBuf) {
+    let mut link = IpcLinkMaster::new(FAKE_SLAVE).unwrap();
+    link.journal_to(path).unwrap();
+
+    link.send(init("")).unwrap();
+    link.send(IpcCommand::StartRecording).unwrap();
+    link.send(IpcCommand::Encoders).unwrap();
+    link.send(IpcCommand::StopRecording).unwrap();
+    link.send(IpcCommand::IsRecording).unwrap();
+    link.send(IpcCommand::Exit).unwrap();
+}
+
+#[test]
+fn everything_gets_journaled() {
+    let path = journal_path("session");
+    record_session(&path);
+    let records = journal::read(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let commands: Vec<_> = records
+        .iter()
+        .filter_map(|r| match &r.entry {
+            JournalEntry::Command(request) => Some((request.id, request.command.name())),
+            _ => None,
+        })
+        .collect();
+    // the recorder has been told to exit, so dropping the link sends nothing on top
+    assert_eq!(
+        commands,
+        [
+            (0, "Init"),
+            (1, "StartRecording"),
+            (2, "Encoders"),
+            (3, "StopRecording"),
+            (4, "IsRecording"),
+            (5, "Exit")
+        ]
+    );
+
+    let responses = records
+        .iter()
+        .filter(|r| matches!(r.entry, JournalEntry::Response(_)))
+        .count();
+    assert_eq!(responses, 6);
+    assert!(records
+        .iter()
+        .any(|r| r.entry == JournalEntry::Event(RecorderEvent::RecordingStarted)));
+    assert!(records
+        .iter()
+        .any(|r| matches!(&r.entry, JournalEntry::Log(log) if log.target == "libobs")));
+    assert!(records.windows(2).all(|w| w[0].elapsed_ms <= w[1].elapsed_ms));
+}
+
+#[test]
+fn replaying_a_journal_against_the_same_recorder_matches() {
+    let path = journal_path("replay");
+    record_session(&path);
+    let records = journal::read(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let mut link = IpcLinkMaster::new(FAKE_SLAVE).unwrap();
+    assert_eq!(journal::replay(&records, &mut link, false).unwrap(), []);
+}
+
+#[test]
+fn replay_reports_different_answers() {
+    let path = journal_path("diff");
+    record_session(&path);
+    let mut records = journal::read(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    // pretend the recorder that wrote the journal had an NVIDIA GPU
+    let nvidia = IpcResponse::Encoders {
+        available: vec![Encoder::JIM_NVENC, Encoder::OBS_X264],
+        selected: Encoder::JIM_NVENC,
+    };
+    for record in &mut records {
+        if let JournalEntry::Response(response) = &mut record.entry {
+            if response.id == 2 {
+                response.response = nvidia.clone();
+            }
+        }
+    }
+
+    let mut link = IpcLinkMaster::new(FAKE_SLAVE).unwrap();
+    assert_eq!(
+        journal::replay(&records, &mut link, false).unwrap(),
+        [Mismatch {
+            id: 2,
+            command: "Encoders",
+            expected: Outcome::Response(nvidia),
+            found: Outcome::Response(IpcResponse::Encoders {
+                available: vec![Encoder::OBS_X264],
+                selected: Encoder::OBS_X264,
+            }),
+        }]
+    );
+}
+
+#[test]
+fn timeouts_get_journaled_and_replayed() {
+    let path = journal_path("timeout");
+    let timeouts = Timeouts {
+        init: Duration::from_millis(200),
+        ..Timeouts::default()
+    };
+    {
+        let mut link = IpcLinkMaster::with_timeouts(FAKE_SLAVE, timeouts).unwrap();
+        link.journal_to(&path).unwrap();
+        assert!(link.send(init("delay:1000")).is_err());
+        link.send(IpcCommand::IsRecording).unwrap();
+    }
+    let records = journal::read(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert!(records
+        .iter()
+        .any(|r| matches!(&r.entry, JournalEntry::Timeout { id: 0, command } if command == "Init")));
+
+    let mut link = IpcLinkMaster::with_timeouts(FAKE_SLAVE, timeouts).unwrap();
+    assert_eq!(journal::replay(&records, &mut link, false).unwrap(), []);
+}
+
+#[test]
+fn exits_get_journaled_and_replayed() {
+    let path = journal_path("exit");
+    {
+        let mut link = IpcLinkMaster::new(FAKE_SLAVE).unwrap();
+        link.journal_to(&path).unwrap();
+        assert!(link.send(init("crash:3")).is_err());
+    }
+    let records = journal::read(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert!(records
+        .iter()
+        .any(|r| r.entry == JournalEntry::Exited { code: Some(3) }));
+
+    let mut link = IpcLinkMaster::new(FAKE_SLAVE).unwrap();
+    assert_eq!(journal::replay(&records, &mut link, false).unwrap(), []);
+
+    // a recorder that does not crash anymore gets noticed
+    let mut link = IpcLinkMaster::new(FAKE_SLAVE).unwrap();
+    let mut fixed = records.clone();
+    for record in &mut fixed {
+        if let JournalEntry::Command(request) = &mut record.entry {
+            request.command = init("");
+        }
+    }
+    let mismatches = journal::replay(&fixed, &mut link, false).unwrap();
+    assert_eq!(mismatches.len(), 1);
+    assert_eq!(mismatches[0].expected, Outcome::Exited);
+    assert_eq!(mismatches[0].found, Outcome::Response(IpcResponse::Ok));
+}
@@ -0,0 +1,225 @@
+use std::sync::Mutex;
+use std::time::Duration;
+
+use intprocess_recorder::events::{RecorderEvent, StopReason};
+use intprocess_recorder::settings::{Encoder, RecorderSettings, StdResolution, Window};
+use ipc_link::{Error, IpcCommand, IpcLinkMaster, IpcResponse, Timeouts, Transport};
+
+const FAKE_SLAVE: &str = env!("CARGO_BIN_EXE_fake_slave");
+
+fn init(script: &str) -> IpcCommand {
+    IpcCommand::Init {
+        libobs_data_path: Some(script.into()),
+        plugin_bin_path: None,
+        plugin_data_path: None,
+    }
+}
+
+fn short_timeouts() -> Timeouts {
+    Timeouts {
+        init: Duration::from_millis(200),
+        query: Duration::from_secs(5),
+        ..Timeouts::default()
+    }
+}
+
+#[test]
+fn request_response() {
+    let mut link = IpcLinkMaster::new(FAKE_SLAVE).unwrap();
+
+    assert_eq!(link.send(init("")).unwrap(), IpcResponse::Ok);
+    assert_eq!(
+        link.send(IpcCommand::IsRecording).unwrap(),
+        IpcResponse::Recording(false)
+    );
+    assert_eq!(link.send(IpcCommand::Exit).unwrap(), IpcResponse::Ok);
+}
+
+#[test]
+fn recorder_errors_keep_their_type() {
+    let mut link = IpcLinkMaster::new(FAKE_SLAVE).unwrap();
+
+    let mut settings = RecorderSettings::new(
+        Window::new("window", None, None),
+        StdResolution::_1920x1080p,
+        StdResolution::_1920x1080p,
+        "./output.mp4",
+    );
+    settings.set_encoder(Encoder::JIM_NVENC);
+    assert_eq!(
+        link.send(IpcCommand::Configure(settings)).unwrap(),
+        IpcResponse::Err(intprocess_recorder::Error::EncoderUnavailable(Encoder::JIM_NVENC))
+    );
+}
+
+#[test]
+fn pipelined_requests_are_matched_by_id() {
+    let mut link = IpcLinkMaster::new(FAKE_SLAVE).unwrap();
+
+    let first = link.submit(init("delay:50")).unwrap();
+    let second = link.submit(IpcCommand::IsRecording).unwrap();
+
+    assert_eq!(link.wait(second).unwrap(), IpcResponse::Recording(false));
+    assert_eq!(link.wait(first).unwrap(), IpcResponse::Ok);
+    assert!(matches!(link.wait(first), Err(Error::UnknownRequest(id)) if id == first));
+}
+
+#[test]
+fn slow_command_times_out_without_desynchronizing() {
+    let mut link = IpcLinkMaster::with_timeouts(FAKE_SLAVE, short_timeouts()).unwrap();
+
+    let result = link.send(init("delay:1000"));
+    assert!(
+        matches!(result, Err(Error::Timeout { command: "Init", .. })),
+        "{result:?}"
+    );
+
+    // the late answer to Init must not be mistaken for the answer to IsRecording
+    assert_eq!(
+        link.send(IpcCommand::IsRecording).unwrap(),
+        IpcResponse::Recording(false)
+    );
+}
+
+#[test]
+fn hanging_recorder_times_out() {
+    let mut link = IpcLinkMaster::with_timeouts(FAKE_SLAVE, short_timeouts()).unwrap();
+
+    let result = link.send(init("hang"));
+    assert!(
+        matches!(result, Err(Error::Timeout { command: "Init", .. })),
+        "{result:?}"
+    );
+    assert!(link.exit_status().unwrap().is_none());
+}
+
+#[test]
+fn crashed_recorder_reports_exit_status() {
+    let mut link = IpcLinkMaster::new(FAKE_SLAVE).unwrap();
+
+    match link.send(init("crash:3")) {
+        Err(Error::ChildExited(Some(status))) => assert_eq!(status.code(), Some(3)),
+        result => panic!("expected ChildExited, got {result:?}"),
+    }
+    assert!(matches!(link.send(IpcCommand::IsRecording), Err(Error::ChildExited(_))));
+}
+
+#[test]
+fn restart_replaces_crashed_recorder_and_keeps_subscribers() {
+    let mut link = IpcLinkMaster::new(FAKE_SLAVE).unwrap();
+    let events = link.subscribe();
+
+    assert!(matches!(link.send(init("crash:3")), Err(Error::ChildExited(_))));
+    let status = link.restart().unwrap();
+    assert_eq!(status.and_then(|status| status.code()), Some(3));
+
+    assert_eq!(link.send(init("")).unwrap(), IpcResponse::Ok);
+    assert_eq!(link.send(IpcCommand::StartRecording).unwrap(), IpcResponse::Ok);
+    assert_eq!(
+        events.recv_timeout(Duration::from_secs(5)).unwrap(),
+        RecorderEvent::RecordingStarted
+    );
+
+    let restarted = RecorderEvent::Restarted {
+        exit_code: Some(3),
+        restarts: 1,
+        interrupted_recording: None,
+    };
+    link.notify(restarted.clone());
+    assert_eq!(events.recv_timeout(Duration::from_secs(5)).unwrap(), restarted);
+}
+
+#[test]
+fn events_are_delivered_to_all_subscribers() {
+    let mut link = IpcLinkMaster::new(FAKE_SLAVE).unwrap();
+    let first = link.subscribe();
+    let second = link.subscribe();
+
+    assert_eq!(link.send(IpcCommand::StartRecording).unwrap(), IpcResponse::Ok);
+    assert_eq!(link.send(IpcCommand::StopRecording).unwrap(), IpcResponse::Ok);
+
+    let stopped = RecorderEvent::RecordingStopped {
+        reason: StopReason::Success,
+        error: None,
+    };
+    for events in [first, second] {
+        assert_eq!(
+            events.recv_timeout(Duration::from_secs(5)).unwrap(),
+            RecorderEvent::RecordingStarted
+        );
+        assert_eq!(events.recv_timeout(Duration::from_secs(5)).unwrap(), stopped);
+    }
+}
+
+static LOGS: Mutex<Vec<(log::Level, String, String)>> = Mutex::new(Vec::new());
+
+struct TestLogger;
+
+impl log::Log for TestLogger {
+    fn enabled(&self, _: &log::Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::Record) {
+        let entry = (record.level(), record.target().to_string(), record.args().to_string());
+        LOGS.lock().unwrap().push(entry);
+    }
+
+    fn flush(&self) {}
+}
+
+#[test]
+fn logs_are_forwarded_and_never_mistaken_for_responses() {
+    _ = log::set_logger(&TestLogger);
+    log::set_max_level(log::LevelFilter::Trace);
+
+    let mut link = IpcLinkMaster::new(FAKE_SLAVE).unwrap();
+    let response = link.send(IpcCommand::Encoders).unwrap();
+    assert_eq!(
+        response,
+        IpcResponse::Encoders {
+            available: vec![Encoder::OBS_X264],
+            selected: Encoder::OBS_X264
+        }
+    );
+
+    let logs = LOGS.lock().unwrap();
+    let forwarded = (
+        log::Level::Warn,
+        "libobs".to_string(),
+        r#"{"Response":{"id":0,"response":"Ok"}}"#.to_string(),
+    );
+    assert!(logs.contains(&forwarded), "{logs:?}");
+}
+
+#[test]
+fn local_socket_transport() {
+    let mut link = IpcLinkMaster::with_transport(FAKE_SLAVE, Transport::local_socket(), Timeouts::default()).unwrap();
+    let events = link.subscribe();
+
+    assert_eq!(link.send(init("")).unwrap(), IpcResponse::Ok);
+    assert_eq!(link.send(IpcCommand::StartRecording).unwrap(), IpcResponse::Ok);
+    assert_eq!(
+        events.recv_timeout(Duration::from_secs(5)).unwrap(),
+        RecorderEvent::RecordingStarted
+    );
+
+    // the recorder has to listen on the same socket again after a restart
+    assert!(matches!(link.send(init("crash:3")), Err(Error::ChildExited(_))));
+    link.restart().unwrap();
+    assert_eq!(
+        link.send(IpcCommand::IsRecording).unwrap(),
+        IpcResponse::Recording(false)
+    );
+    assert_eq!(link.send(IpcCommand::Exit).unwrap(), IpcResponse::Ok);
+}
+
+#[test]
+fn transport_arguments_round_trip() {
+    for transport in [Transport::Stdio, Transport::local_socket()] {
+        let args = transport.to_args();
+        assert_eq!(Transport::from_args(args).unwrap(), transport);
+    }
+    assert!(Transport::from_args(["--transport".to_string()]).is_err());
+    assert!(Transport::from_args(["--transport".to_string(), "carrier-pigeon".to_string()]).is_err());
+}
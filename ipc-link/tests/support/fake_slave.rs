@@ -0,0 +1,92 @@
+//! Stand-in for `extprocess_recorder` that speaks the ipc-link protocol without touching libobs.
+//! Uses the transport passed by the master and supports `--daemon` just like the real recorder.
+//!
+//! The behaviour is selected through the `libobs_data_path` of `IpcCommand::Init`:
+//! - `delay:<ms>` answers after sleeping for the given time
+//! - `hang` never answers
+//! - `crash:<code>` exits immediately with the given exit code
+//! - `stop:<ms>` makes `StopRecording` report the stop only after the given time, like libobs finalising a file
+//!
+//! `StartRecording` and `StopRecording` emit the matching events before answering.
+//! `Encoders` logs a message that looks like a response frame before answering.
+//! `Configure` only accepts `Encoder::OBS_X264` (or no encoder).
+
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::{env, process, thread, time::Duration};
+
+use intprocess_recorder::events::{RecorderEvent, StopReason};
+use intprocess_recorder::settings::Encoder;
+use intprocess_recorder::Error;
+use ipc_link::{IpcCommand, IpcLinkSlave, IpcResponse, Transport, DAEMON_ARG};
+
+fn main() {
+    let transport = Transport::from_args(env::args().skip(1)).unwrap();
+    let mut link = if env::args().any(|arg| arg == DAEMON_ARG) {
+        IpcLinkSlave::daemon(&transport).unwrap()
+    } else {
+        IpcLinkSlave::with_transport(&transport).unwrap()
+    };
+    link.install_logger(log::LevelFilter::Trace).unwrap();
+    let events = link.event_sender();
+    let recording = Arc::new(AtomicBool::new(false));
+    let stop_delay = AtomicU64::new(0);
+
+    link.respond(|cmd| match cmd {
+        IpcCommand::Init { libobs_data_path, .. } => {
+            match libobs_data_path.as_deref().and_then(|script| script.split_once(':')) {
+                Some(("delay", ms)) => thread::sleep(Duration::from_millis(ms.parse().unwrap())),
+                Some(("crash", code)) => process::exit(code.parse().unwrap()),
+                Some(("stop", ms)) => stop_delay.store(ms.parse().unwrap(), Ordering::Relaxed),
+                _ if libobs_data_path.as_deref() == Some("hang") => loop {
+                    thread::park();
+                },
+                _ => {}
+            }
+            Some(IpcResponse::Ok)
+        }
+        IpcCommand::Configure(settings) => match settings.get_encoder() {
+            Some(&encoder) if encoder != Encoder::OBS_X264 => {
+                Some(IpcResponse::Err(Error::EncoderUnavailable(encoder)))
+            }
+            _ => Some(IpcResponse::Ok),
+        },
+        IpcCommand::StartRecording => {
+            recording.store(true, Ordering::Relaxed);
+            events.send(RecorderEvent::RecordingStarted);
+            Some(IpcResponse::Ok)
+        }
+        IpcCommand::StopRecording => {
+            let stopped = {
+                let (events, recording) = (events.clone(), recording.clone());
+                move || {
+                    recording.store(false, Ordering::Relaxed);
+                    events.send(RecorderEvent::RecordingStopped {
+                        reason: StopReason::Success,
+                        error: None,
+                    });
+                }
+            };
+            match stop_delay.load(Ordering::Relaxed) {
+                0 => stopped(),
+                ms => {
+                    _ = thread::spawn(move || {
+                        thread::sleep(Duration::from_millis(ms));
+                        stopped();
+                    })
+                }
+            }
+            Some(IpcResponse::Ok)
+        }
+        IpcCommand::Encoders => {
+            log::warn!(target: "libobs", r#"{{"Response":{{"id":0,"response":"Ok"}}}}"#);
+            Some(IpcResponse::Encoders {
+                available: vec![Encoder::OBS_X264],
+                selected: Encoder::OBS_X264,
+            })
+        }
+        IpcCommand::IsRecording => Some(IpcResponse::Recording(recording.load(Ordering::Relaxed))),
+        IpcCommand::Exit => None,
+        _ => Some(IpcResponse::Ok),
+    });
+}
@@ -9,6 +9,7 @@ fn main() {
 
     #[cfg(feature = "bindgen")]
     bindgen::builder()
+        // media-io/media-remux.h is left out, src/media_remux.rs binds it by hand
         .header(format!("libobs_headers_{}/obs.h", build_helper::VERSION))
         .blocklist_function("_+.*")
         .derive_copy(true)
@@ -9,3 +9,7 @@
 include!(env!("LIBOBS_BINDINGS_FILE"));
 
 pub const VERSION: &str = env!("LIBOBS_BINDINGS_VERSION");
+
+// media-remux.h is not included by obs.h and therefore not part of the generated bindings
+mod media_remux;
+pub use media_remux::*;
@@ -0,0 +1,24 @@
+//! Hand-written bindings for `media-io/media-remux.h`, which bindgen does not see because obs.h does not include it.
+//!
+//! The functions are the same in every supported libobs version.
+
+#[repr(C)]
+#[derive(Debug)]
+pub struct media_remux_job {
+    _unused: [u8; 0],
+}
+pub type media_remux_job_t = *mut media_remux_job;
+pub type media_remux_progress_callback = unsafe extern "C" fn(data: *mut ::std::os::raw::c_void, percent: f32) -> bool;
+extern "C" {
+    pub fn media_remux_job_create(
+        job: *mut media_remux_job_t,
+        in_filename: *const ::std::os::raw::c_char,
+        out_filename: *const ::std::os::raw::c_char,
+    ) -> bool;
+    pub fn media_remux_job_process(
+        job: media_remux_job_t,
+        callback: ::std::option::Option<media_remux_progress_callback>,
+        data: *mut ::std::os::raw::c_void,
+    ) -> bool;
+    pub fn media_remux_job_destroy(job: media_remux_job_t);
+}
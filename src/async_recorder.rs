@@ -0,0 +1,157 @@
+use std::{env, path, process, time};
+
+use ipc_link::{AsyncIpcLinkMaster, IpcCommand, IpcResponse};
+
+pub use ipc_link::EventStream;
+
+use crate::{settings, CloseReport, Error, Result, Timeouts, EXECUTABLE};
+
+/// Async version of [`Recorder`](crate::Recorder) for use inside a tokio runtime.
+///
+/// All methods take `&self` and are cancellation-safe: dropping a future before it completes leaves the recorder
+/// in a usable state (the command might still be executed by the recorder process though).
+#[derive(Debug)]
+pub struct AsyncRecorder {
+    recorder: AsyncIpcLinkMaster,
+}
+
+impl AsyncRecorder {
+    pub async fn new() -> Result<Self> {
+        Self::new_with_paths(None::<&str>, None, None, None).await
+    }
+
+    pub async fn new_with_paths(
+        executable_path: Option<impl AsRef<path::Path>>,
+        libobs_data_path: Option<&str>,
+        plugin_bin_path: Option<&str>,
+        plugin_data_path: Option<&str>,
+    ) -> Result<Self> {
+        let rec = match &executable_path {
+            Some(p) => AsyncIpcLinkMaster::new(p).await,
+            None => {
+                let exe_path = env::current_exe().map_err(Error::Io)?;
+                let pwd = exe_path
+                    .parent()
+                    .expect("current exe should always have a parent directory");
+                AsyncIpcLinkMaster::new(pwd.join(EXECUTABLE)).await
+            }
+        }
+        .map_err(Error::from)?;
+
+        let cmd = IpcCommand::Init {
+            libobs_data_path: libobs_data_path.map(ToString::to_string),
+            plugin_bin_path: plugin_bin_path.map(ToString::to_string),
+            plugin_data_path: plugin_data_path.map(ToString::to_string),
+        };
+
+        match rec.send(cmd).await.map_err(Error::from)? {
+            IpcResponse::Ok => Ok(Self { recorder: rec }),
+            IpcResponse::Err(e) => Err(Box::new(Error::Recorder(e))),
+            _ => Err(Box::new(Error::ShouldNeverHappenNotifyMe)),
+        }
+    }
+
+    pub fn set_timeouts(&self, timeouts: Timeouts) {
+        self.recorder.set_timeouts(timeouts);
+    }
+
+    /// Returns the exit status if the recorder process has exited (e.g. crashed).
+    ///
+    /// An `AsyncRecorder` with an exited process can not be used anymore and has to be recreated.
+    pub async fn exit_status(&self) -> Result<Option<process::ExitStatus>> {
+        Ok(self.recorder.exit_status().await.map_err(Error::Io)?)
+    }
+
+    /// Returns a `Stream` of all events (recording started/stopped, encoder errors, ...) from now on.
+    ///
+    /// Every call creates a new subscription which ends when the stream is dropped or the recorder exits.
+    pub fn events(&self) -> EventStream {
+        self.recorder.events()
+    }
+
+    pub async fn configure(&self, settings: &settings::RecorderSettings) -> Result<()> {
+        match self.send(IpcCommand::Configure(settings.clone())).await? {
+            IpcResponse::Ok => Ok(()),
+            IpcResponse::Err(e) => Err(Box::new(Error::Recorder(e))),
+            _ => Err(Box::new(Error::ShouldNeverHappenNotifyMe)),
+        }
+    }
+
+    pub async fn available_encoders(&self) -> Result<Vec<settings::Encoder>> {
+        match self.send(IpcCommand::Encoders).await? {
+            IpcResponse::Encoders { available, .. } => Ok(available),
+            IpcResponse::Err(e) => Err(Box::new(Error::Recorder(e))),
+            _ => Err(Box::new(Error::ShouldNeverHappenNotifyMe)),
+        }
+    }
+
+    pub async fn selected_encoder(&self) -> Result<settings::Encoder> {
+        match self.send(IpcCommand::Encoders).await? {
+            IpcResponse::Encoders { selected, .. } => Ok(selected),
+            IpcResponse::Err(e) => Err(Box::new(Error::Recorder(e))),
+            _ => Err(Box::new(Error::ShouldNeverHappenNotifyMe)),
+        }
+    }
+
+    pub async fn adapter_info(&self) -> Result<settings::Adapter> {
+        match self.send(IpcCommand::Adapter).await? {
+            IpcResponse::Adapter(adapter) => Ok(adapter),
+            IpcResponse::Err(e) => Err(Box::new(Error::Recorder(e))),
+            _ => Err(Box::new(Error::ShouldNeverHappenNotifyMe)),
+        }
+    }
+
+    /// Starts recording and returns the file it records to, with the placeholders of the output path filled in.
+    pub async fn start_recording(&self) -> Result<String> {
+        match self.send(IpcCommand::StartRecording).await? {
+            IpcResponse::Path(path) => Ok(path),
+            IpcResponse::Err(e) => Err(Box::new(Error::Recorder(e))),
+            _ => Err(Box::new(Error::ShouldNeverHappenNotifyMe)),
+        }
+    }
+
+    pub async fn stop_recording(&self) -> Result<()> {
+        match self.send(IpcCommand::StopRecording).await? {
+            IpcResponse::Ok => Ok(()),
+            IpcResponse::Err(e) => Err(Box::new(Error::Recorder(e))),
+            _ => Err(Box::new(Error::ShouldNeverHappenNotifyMe)),
+        }
+    }
+
+    pub async fn is_recording(&self) -> Result<bool> {
+        match self.send(IpcCommand::IsRecording).await? {
+            IpcResponse::Recording(recording) => Ok(recording),
+            IpcResponse::Err(e) => Err(Box::new(Error::Recorder(e))),
+            _ => Err(Box::new(Error::ShouldNeverHappenNotifyMe)),
+        }
+    }
+
+    /// Shuts down libobs and lets the recorder process exit.
+    ///
+    /// Unlike [`Recorder::shutdown`](crate::Recorder::shutdown) the recorder is not handed back on failure,
+    /// it gets dropped which closes it like `close` does.
+    pub async fn shutdown(self) -> Result<()> {
+        match self.send(IpcCommand::Shutdown).await? {
+            IpcResponse::Ok => { /* OK continue */ }
+            IpcResponse::Err(e) => return Err(Box::new(Error::Recorder(e))),
+            _ => return Err(Box::new(Error::ShouldNeverHappenNotifyMe)),
+        }
+        match self.send(IpcCommand::Exit).await? {
+            IpcResponse::Ok => Ok(()),
+            IpcResponse::Err(e) => Err(Box::new(Error::Recorder(e))),
+            _ => Err(Box::new(Error::ShouldNeverHappenNotifyMe)),
+        }
+    }
+
+    /// Stops the recording, waits for libobs to finalise the file, shuts libobs down and lets the recorder exit.
+    ///
+    /// The recorder is only killed once `timeout` has passed, the report says how far it got.
+    /// Dropping the recorder does the same on a task of the runtime with a timeout of 10 seconds.
+    pub async fn close(self, timeout: time::Duration) -> CloseReport {
+        self.recorder.close(timeout).await
+    }
+
+    async fn send(&self, cmd: IpcCommand) -> Result<IpcResponse> {
+        Ok(self.recorder.send(cmd).await.map_err(Error::from)?)
+    }
+}
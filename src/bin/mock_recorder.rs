@@ -0,0 +1,244 @@
+//! Stand-in for `extprocess_recorder` that runs the real recorder logic against an in-memory libobs.
+//! Only built with the `mock` feature, so host-side code can be tested without libobs, DLLs or a window to record.
+//!
+//! The behaviour is scripted through the `libobs_data_path` of `IpcCommand::Init`, a `;` separated list of:
+//! - `adapter=<name>` replaces the graphics adapter (the vendor is derived from the name)
+//! - `encoders=<id>,<id>,...` replaces the registered encoder types (e.g. `jim_nvenc,obs_x264`)
+//! - `fail=<what>` makes libobs fail at `startup`, `reset_video`, `output_start[:<error>]`,
+//!   `output_stop[:<error>]` or `encoder:<id>`
+//! - `delay=<command>:<ms>` answers the command after sleeping for the given time
+//! - `hang=<command>` never answers the command
+//! - `crash=<command>:<code>` exits with the given code when receiving the command
+//! - `freeze=<command>` stops rendering frames when receiving the command, like a wedged graphics thread
+//!
+//! `<command>` is the name of an `IpcCommand`, e.g. `delay=Configure:500;crash=StartRecording:3`.
+//! Until it freezes, libobs renders one frame for every `Status` (and therefore every heartbeat).
+
+use std::{env, process, thread, time::Duration};
+
+use intprocess_recorder::backend::{Backend, Failure, FakeBackend};
+use intprocess_recorder::{Error, InpRecorder};
+use ipc_link::{IpcCommand, IpcLinkSlave, IpcResponse, Transport, DAEMON_ARG};
+
+#[derive(Default)]
+struct Script {
+    fake: FakeBackend,
+    delays: Vec<(String, Duration)>,
+    hangs: Vec<String>,
+    crashes: Vec<(String, i32)>,
+    freeze: Option<String>,
+    frozen: bool,
+}
+
+impl Script {
+    fn parse(script: &str) -> Result<Self, String> {
+        let mut parsed = Self::default();
+        for directive in script.split(';').filter(|d| !d.is_empty()) {
+            let (key, value) = directive
+                .split_once('=')
+                .ok_or_else(|| format!("invalid directive '{directive}'"))?;
+            match key {
+                "adapter" => parsed.fake.set_adapter(value),
+                "encoders" => parsed.fake.set_encoder_types(&value.split(',').collect::<Vec<_>>()),
+                "fail" => parsed.fake.fail(match value.split_once(':') {
+                    _ if value == "startup" => Failure::Startup,
+                    _ if value == "reset_video" => Failure::ResetVideo,
+                    _ if value == "output_start" => Failure::OutputStart(None),
+                    Some(("output_start", error)) => Failure::OutputStart(Some(error.into())),
+                    _ if value == "output_stop" => Failure::OutputStop(None),
+                    Some(("output_stop", error)) => Failure::OutputStop(Some(error.into())),
+                    Some(("encoder", id)) => Failure::EncoderCreation(id.into()),
+                    _ => return Err(format!("unknown failure '{value}'")),
+                }),
+                "delay" => {
+                    let (command, ms) = value.split_once(':').ok_or("delay needs <command>:<ms>")?;
+                    let ms = ms.parse().map_err(|_| format!("invalid delay '{ms}'"))?;
+                    parsed.delays.push((command.into(), Duration::from_millis(ms)));
+                }
+                "hang" => parsed.hangs.push(value.into()),
+                "freeze" => parsed.freeze = Some(value.into()),
+                "crash" => {
+                    let (command, code) = value.split_once(':').ok_or("crash needs <command>:<code>")?;
+                    let code = code.parse().map_err(|_| format!("invalid exit code '{code}'"))?;
+                    parsed.crashes.push((command.into(), code));
+                }
+                _ => return Err(format!("unknown directive '{key}'")),
+            }
+        }
+        Ok(parsed)
+    }
+
+    /// Runs the scripted behaviour for `command` before it gets handled.
+    fn before(&mut self, command: &str) {
+        if let Some((_, code)) = self.crashes.iter().find(|(c, _)| c == command) {
+            process::exit(*code);
+        }
+        if self.hangs.iter().any(|c| c == command) {
+            loop {
+                thread::park();
+            }
+        }
+        if let Some((_, delay)) = self.delays.iter().find(|(c, _)| c == command) {
+            thread::sleep(*delay);
+        }
+        self.frozen |= self.freeze.as_deref() == Some(command);
+        if command == "Status" && !self.frozen {
+            self.fake.render_frames(1);
+        }
+    }
+}
+
+fn main() {
+    let transport = match Transport::from_args(env::args().skip(1)) {
+        Ok(transport) => transport,
+        Err(e) => {
+            eprintln!("{e}");
+            process::exit(2);
+        }
+    };
+    let link = if env::args().any(|arg| arg == DAEMON_ARG) {
+        IpcLinkSlave::daemon(&transport)
+    } else {
+        IpcLinkSlave::with_transport(&transport)
+    };
+    let mut link = match link {
+        Ok(link) => link,
+        Err(e) => {
+            eprintln!("failed to open {transport}: {e}");
+            process::exit(1);
+        }
+    };
+    _ = link.install_logger(log::LevelFilter::Debug);
+    let events = link.event_sender();
+
+    let mut script: Option<Script> = None;
+    let mut recorder: Option<InpRecorder<FakeBackend>> = None;
+    let mut shut_down = false;
+
+    link.respond(|cmd| {
+        if let IpcCommand::Init { libobs_data_path, .. } = &cmd {
+            if script.is_none() {
+                match Script::parse(libobs_data_path.as_deref().unwrap_or_default()) {
+                    Ok(parsed) => script = Some(parsed),
+                    Err(e) => {
+                        eprintln!("invalid script: {e}");
+                        process::exit(2);
+                    }
+                }
+            }
+        }
+        if let Some(script) = &mut script {
+            script.before(cmd.name());
+        }
+
+        match cmd {
+            IpcCommand::Init { .. } if shut_down => Some(IpcResponse::Err(Error::AlreadyShutDown)),
+            IpcCommand::Init { .. } if recorder.is_some() => Some(IpcResponse::Err(Error::AlreadyInitialized)),
+            IpcCommand::Init { .. } => {
+                let fake = &script.as_ref().expect("script is parsed on init").fake;
+                match InpRecorder::fake(fake) {
+                    Ok(mut rec) => {
+                        let events = events.clone();
+                        rec.set_event_handler(move |event| events.send(event));
+                        recorder = Some(rec);
+                        Some(IpcResponse::Ok)
+                    }
+                    Err(e) => Some(IpcResponse::Err(e)),
+                }
+            }
+            IpcCommand::Shutdown => {
+                let Some(fake) = script.as_ref().map(|s| s.fake.clone()) else {
+                    return Some(IpcResponse::Err(Error::NotInitialized));
+                };
+                if let Some(mut recorder) = recorder.take() {
+                    recorder.stop_recording();
+                }
+                if !shut_down {
+                    fake.shutdown();
+                    shut_down = true;
+                }
+                Some(IpcResponse::Ok)
+            }
+            IpcCommand::Exit => {
+                if recorder.is_some() {
+                    Some(IpcResponse::Err(Error::NotShutDown))
+                } else {
+                    None
+                }
+            }
+            // answered by the link itself
+            IpcCommand::Detach | IpcCommand::SetHeartbeat(_) => Some(IpcResponse::Ok),
+            cmd => {
+                let Some(recorder) = recorder.as_mut() else {
+                    return Some(IpcResponse::Err(Error::NotInitialized));
+                };
+                Some(handle(recorder, cmd))
+            }
+        }
+    });
+}
+
+fn handle(recorder: &mut InpRecorder<FakeBackend>, cmd: IpcCommand) -> IpcResponse {
+    let result = match cmd {
+        IpcCommand::Configure(settings) => recorder.configure(&settings),
+        IpcCommand::Encoders => {
+            return IpcResponse::Encoders {
+                available: recorder.get_available_encoders(),
+                selected: recorder.selected_encoder(),
+            }
+        }
+        IpcCommand::Adapter => return IpcResponse::Adapter(recorder.get_adapter_info()),
+        IpcCommand::StartRecording => {
+            return recorder
+                .start_recording()
+                .map_or_else(IpcResponse::Err, IpcResponse::Path)
+        }
+        IpcCommand::StopRecording => {
+            if recorder.is_recording() {
+                recorder.stop_recording();
+            }
+            Ok(())
+        }
+        IpcCommand::IsRecording => return IpcResponse::Recording(recorder.is_recording()),
+        IpcCommand::PauseRecording => recorder.pause_recording(),
+        IpcCommand::ResumeRecording => recorder.resume_recording(),
+        IpcCommand::IsPaused => return IpcResponse::Recording(recorder.is_paused()),
+        IpcCommand::SplitRecording => recorder.split_recording(),
+        IpcCommand::AddMarker(label) => {
+            return recorder
+                .add_marker(&label)
+                .map_or_else(IpcResponse::Err, IpcResponse::Marker)
+        }
+        IpcCommand::Remux { input, output } => recorder.start_remux(&input, &output),
+        IpcCommand::AddOutput(kind) => {
+            return recorder
+                .add_output(&kind)
+                .map_or_else(IpcResponse::Err, IpcResponse::Output)
+        }
+        IpcCommand::RemoveOutput(id) => recorder.remove_output(id),
+        IpcCommand::Outputs => return IpcResponse::Outputs(recorder.outputs()),
+        IpcCommand::StartOutput(id) => recorder.start_output(id),
+        IpcCommand::StopOutput(id) => recorder.stop_output(id),
+        IpcCommand::IsOutputActive(id) => {
+            return recorder
+                .is_output_active(id)
+                .map_or_else(IpcResponse::Err, IpcResponse::Recording)
+        }
+        IpcCommand::StartReplayBuffer => {
+            return recorder
+                .start_replay_buffer()
+                .map_or_else(IpcResponse::Err, IpcResponse::Output)
+        }
+        IpcCommand::SaveReplay => recorder.save_replay(),
+        IpcCommand::StopReplayBuffer => {
+            recorder.stop_replay_buffer();
+            Ok(())
+        }
+        IpcCommand::Status => return IpcResponse::Status(recorder.status()),
+        _ => unreachable!("handled by the caller"),
+    };
+    match result {
+        Ok(()) => IpcResponse::Ok,
+        Err(e) => IpcResponse::Err(e),
+    }
+}
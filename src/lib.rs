@@ -1,9 +1,25 @@
-use std::{env, error, fmt, io, path};
+use std::{env, error, fmt, io, path, process, sync::mpsc, time};
 
 use ipc_link::{IpcCommand, IpcLinkMaster, IpcResponse};
+use supervisor::Supervisor;
 
+pub use intprocess_recorder::events;
+pub use intprocess_recorder::marker;
+pub use intprocess_recorder::output;
 pub use intprocess_recorder::settings;
+pub use intprocess_recorder::status;
+pub use intprocess_recorder::Error as RecorderError;
 pub use intprocess_recorder::InpRecorder as SingletonRecorder;
+pub use intprocess_recorder::ThreadedRecorder;
+pub use ipc_link::{CloseReport, Framing, Hello, StageOutcome, Timeouts, Transport, Watchdog, WatchdogAction};
+pub use supervisor::RestartPolicy;
+
+mod supervisor;
+
+#[cfg(feature = "async")]
+mod async_recorder;
+#[cfg(feature = "async")]
+pub use async_recorder::{AsyncRecorder, EventStream};
 
 #[cfg(target_family = "windows")]
 const EXECUTABLE: &str = "./libobs/extprocess_recorder.exe";
@@ -12,9 +28,26 @@ const EXECUTABLE: &str = "./libobs/extprocess_recorder";
 
 pub enum Error {
     Io(io::Error),
-    Recorder(String),
-    ShutdownFailed(Recorder, String),
-    ExitFailed(Recorder, String),
+    /// the recorder executable did not respond to the handshake (e.g. because it is outdated)
+    Handshake(String),
+    /// the recorder executable was built for a different protocol, crate or libobs version
+    VersionMismatch {
+        expected: Hello,
+        found: Hello,
+    },
+    /// the recorder did not answer in time - it might be stuck inside libobs
+    Timeout {
+        command: &'static str,
+        timeout: time::Duration,
+    },
+    /// the recorder process has exited - the `Recorder` has to be recreated
+    RecorderExited(Option<process::ExitStatus>),
+    /// the recorder could not decode a request - most likely a bug or a corrupted connection
+    Protocol(String),
+    /// the recorder process reported an error
+    Recorder(RecorderError),
+    ShutdownFailed(Recorder, RecorderError),
+    ExitFailed(Recorder, RecorderError),
     ShouldNeverHappenNotifyMe,
 }
 
@@ -22,7 +55,18 @@ impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Error::Io(e) => f.write_fmt(format_args!("{e:?}")),
-            Error::Recorder(e) | Error::ShutdownFailed(_, e) | Error::ExitFailed(_, e) => f.write_str(e),
+            Error::Handshake(e) => write!(f, "handshake with recorder failed: {e}"),
+            Error::VersionMismatch { expected, found } => {
+                write!(
+                    f,
+                    "recorder version mismatch - expected ({expected}) but found ({found})"
+                )
+            }
+            Error::Timeout { command, timeout } => write!(f, "recorder did not answer {command} within {timeout:?}"),
+            Error::RecorderExited(Some(status)) => write!(f, "recorder exited ({status})"),
+            Error::RecorderExited(None) => f.write_str("recorder exited"),
+            Error::Protocol(e) => write!(f, "recorder rejected the request: {e}"),
+            Error::Recorder(e) | Error::ShutdownFailed(_, e) | Error::ExitFailed(_, e) => write!(f, "{e}"),
             Error::ShouldNeverHappenNotifyMe => f.write_str("This error should never happen - please notify me"),
         }
     }
@@ -38,16 +82,37 @@ impl error::Error for Error {
     fn source(&self) -> Option<&(dyn error::Error + 'static)> {
         match self {
             Error::Io(e) => Some(e),
+            Error::Recorder(e) | Error::ShutdownFailed(_, e) | Error::ExitFailed(_, e) => Some(e),
             _ => None,
         }
     }
 }
 
+impl From<ipc_link::Error> for Error {
+    fn from(e: ipc_link::Error) -> Self {
+        match e {
+            ipc_link::Error::Io(e) => Error::Io(e),
+            ipc_link::Error::Handshake(e) => Error::Handshake(e),
+            ipc_link::Error::VersionMismatch { master, slave } => {
+                Error::VersionMismatch { expected: master, found: slave }
+            }
+            ipc_link::Error::Timeout { command, timeout } => Error::Timeout { command, timeout },
+            ipc_link::Error::ChildExited(status) => Error::RecorderExited(status),
+            ipc_link::Error::UnknownRequest(_) => Error::ShouldNeverHappenNotifyMe,
+            ipc_link::Error::Protocol(e) => Error::Protocol(e),
+        }
+    }
+}
+
 pub type Result<T> = std::result::Result<T, Box<Error>>;
 
 #[derive(Debug)]
 pub struct Recorder {
     recorder: IpcLinkMaster,
+    // state that gets replayed to a new recorder process after a restart
+    init: IpcCommand,
+    settings: Option<settings::RecorderSettings>,
+    supervisor: Supervisor,
 }
 
 impl Recorder {
@@ -61,41 +126,164 @@ impl Recorder {
         plugin_bin_path: Option<&str>,
         plugin_data_path: Option<&str>,
     ) -> Result<Self> {
-        let mut rec = match &executable_path {
-            Some(p) => IpcLinkMaster::new(p),
+        Self::new_with_transport(
+            executable_path,
+            Transport::Stdio,
+            libobs_data_path,
+            plugin_bin_path,
+            plugin_data_path,
+        )
+    }
+
+    /// Like `new_with_paths` but talks to the recorder process over `transport` instead of its stdin/stdout.
+    ///
+    /// Use `Transport::local_socket()` if libobs plugins print to stdout.
+    pub fn new_with_transport(
+        executable_path: Option<impl AsRef<path::Path>>,
+        transport: Transport,
+        libobs_data_path: Option<&str>,
+        plugin_bin_path: Option<&str>,
+        plugin_data_path: Option<&str>,
+    ) -> Result<Self> {
+        Self::new_with_framing(
+            executable_path,
+            transport,
+            Framing::default(),
+            libobs_data_path,
+            plugin_bin_path,
+            plugin_data_path,
+        )
+    }
+
+    /// Like `new_with_transport` but encodes the messages exchanged with the recorder process with `framing`.
+    ///
+    /// `Framing::MessagePack` is cheaper than the default JSON lines, which are easier to read when debugging.
+    pub fn new_with_framing(
+        executable_path: Option<impl AsRef<path::Path>>,
+        transport: Transport,
+        framing: Framing,
+        libobs_data_path: Option<&str>,
+        plugin_bin_path: Option<&str>,
+        plugin_data_path: Option<&str>,
+    ) -> Result<Self> {
+        let executable = match &executable_path {
+            Some(p) => p.as_ref().to_path_buf(),
             None => {
                 let exe_path = env::current_exe().map_err(Error::Io)?;
                 let pwd = exe_path
                     .parent()
                     .expect("current exe should always have a parent directory");
-                IpcLinkMaster::new(pwd.join(EXECUTABLE))
+                pwd.join(EXECUTABLE)
             }
-        }
-        .map_err(Error::Io)?;
+        };
+        let rec =
+            IpcLinkMaster::with_framing(executable, transport, framing, Timeouts::default()).map_err(Error::from)?;
 
         let cmd = IpcCommand::Init {
             libobs_data_path: libobs_data_path.map(ToString::to_string),
             plugin_bin_path: plugin_bin_path.map(ToString::to_string),
             plugin_data_path: plugin_data_path.map(ToString::to_string),
         };
+        Self::init(rec, cmd)
+    }
+
+    /// Attaches to a recorder daemon that is already running, e.g. to share one libobs instance between processes.
+    ///
+    /// The daemon has to be started with `extprocess_recorder --daemon --transport socket:<addr>`.
+    /// It is not owned by any attached `Recorder`:
+    /// - libobs gets initialized (with the default paths) by the first `Recorder` that attaches
+    /// - `shutdown` fails with `RecorderError::InUse` as long as other `Recorder`s are attached
+    /// - dropping an attached `Recorder` only detaches, the daemon and a running recording keep going
+    pub fn connect(addr: impl Into<String>) -> Result<Self> {
+        let rec = IpcLinkMaster::connect(addr, Timeouts::default()).map_err(Error::from)?;
+
+        let cmd = IpcCommand::Init {
+            libobs_data_path: None,
+            plugin_bin_path: None,
+            plugin_data_path: None,
+        };
+        Self::init(rec, cmd)
+    }
 
-        match rec.send(cmd) {
-            IpcResponse::Ok => Ok(Self { recorder: rec }),
+    fn init(mut rec: IpcLinkMaster, cmd: IpcCommand) -> Result<Self> {
+        match rec.send(cmd.clone()).map_err(Error::from)? {
+            IpcResponse::Ok => Ok(Self {
+                supervisor: Supervisor::new(&rec),
+                recorder: rec,
+                init: cmd,
+                settings: None,
+            }),
             IpcResponse::Err(e) => Err(Box::new(Error::Recorder(e))),
             _ => Err(Box::new(Error::ShouldNeverHappenNotifyMe)),
         }
     }
 
+    pub fn set_timeouts(&mut self, timeouts: Timeouts) {
+        self.recorder.set_timeouts(timeouts);
+    }
+
+    /// Opts into automatic recovery from a crashed recorder process.
+    ///
+    /// When the recorder process exits unexpectedly a new one is spawned with the paths from `new_with_paths`
+    /// and the settings from the last successful `configure`. An attached `Recorder` reconnects to the daemon instead.
+    /// Subscribers get a `RecorderEvent::Restarted` and a recording that was interrupted by the crash is renamed
+    /// to `<name>.interrupted.<ext>` so it can be salvaged.
+    /// The call that noticed the crash is retried on the new process, except for `start_recording`, which returns
+    /// `Error::RecorderExited` to not silently start a new recording.
+    pub fn set_restart_policy(&mut self, policy: RestartPolicy) {
+        self.supervisor.set_policy(policy);
+    }
+
+    /// Watches the recorder process in the background (or stops watching it with `None`), see `Watchdog`.
+    ///
+    /// Subscribers get a `RecorderEvent::Unresponsive` once it stops responding or libobs stops rendering.
+    /// With `WatchdogAction::Kill` and a restart policy the next call replaces the killed process.
+    pub fn set_watchdog(&mut self, watchdog: Option<Watchdog>) -> Result<()> {
+        Ok(self.recorder.set_watchdog(watchdog).map_err(Error::from)?)
+    }
+
+    /// Writes every command, response, event and log message from now on to a JSONL file at `path`.
+    ///
+    /// Attach the file to bug reports - it can be replayed against a recorder with `ipc_replay`.
+    pub fn journal_to(&mut self, path: impl AsRef<path::Path>) -> Result<()> {
+        Ok(self.recorder.journal_to(path).map_err(Error::Io)?)
+    }
+
+    /// Returns `true` if this `Recorder` is attached to a daemon (see `connect`) instead of owning its recorder process.
+    #[must_use]
+    pub fn is_attached(&self) -> bool {
+        !self.recorder.owns_recorder()
+    }
+
+    /// Returns the exit status if the recorder process has exited (e.g. crashed).
+    ///
+    /// A `Recorder` with an exited process can not be used anymore and has to be recreated.
+    /// Always `None` for a `Recorder` attached to a daemon.
+    pub fn exit_status(&mut self) -> Result<Option<process::ExitStatus>> {
+        Ok(self.recorder.exit_status().map_err(Error::Io)?)
+    }
+
+    /// Returns a receiver for all events (recording started/stopped, encoder errors, ...) from now on.
+    ///
+    /// Events arrive independently of any other calls, so there is no need to poll `is_recording`.
+    /// Every call creates a new subscription which ends when the receiver is dropped.
+    pub fn subscribe(&self) -> mpsc::Receiver<events::RecorderEvent> {
+        self.recorder.subscribe()
+    }
+
     pub fn configure(&mut self, settings: &settings::RecorderSettings) -> Result<()> {
-        match self.recorder.send(IpcCommand::Configure(settings.clone())) {
-            IpcResponse::Ok => Ok(()),
+        match self.send(IpcCommand::Configure(settings.clone()))? {
+            IpcResponse::Ok => {
+                self.settings = Some(settings.clone());
+                Ok(())
+            }
             IpcResponse::Err(e) => Err(Box::new(Error::Recorder(e))),
             _ => Err(Box::new(Error::ShouldNeverHappenNotifyMe)),
         }
     }
 
     pub fn available_encoders(&mut self) -> Result<Vec<settings::Encoder>> {
-        match self.recorder.send(IpcCommand::Encoders) {
+        match self.send(IpcCommand::Encoders)? {
             IpcResponse::Encoders { available, .. } => Ok(available),
             IpcResponse::Err(e) => Err(Box::new(Error::Recorder(e))),
             _ => Err(Box::new(Error::ShouldNeverHappenNotifyMe)),
@@ -103,7 +291,7 @@ impl Recorder {
     }
 
     pub fn selected_encoder(&mut self) -> Result<settings::Encoder> {
-        match self.recorder.send(IpcCommand::Encoders) {
+        match self.send(IpcCommand::Encoders)? {
             IpcResponse::Encoders { selected, .. } => Ok(selected),
             IpcResponse::Err(e) => Err(Box::new(Error::Recorder(e))),
             _ => Err(Box::new(Error::ShouldNeverHappenNotifyMe)),
@@ -111,44 +299,206 @@ impl Recorder {
     }
 
     pub fn adapter_info(&mut self) -> Result<settings::Adapter> {
-        match self.recorder.send(IpcCommand::Adapter) {
+        match self.send(IpcCommand::Adapter)? {
             IpcResponse::Adapter(adapter) => Ok(adapter),
             IpcResponse::Err(e) => Err(Box::new(Error::Recorder(e))),
             _ => Err(Box::new(Error::ShouldNeverHappenNotifyMe)),
         }
     }
 
-    pub fn start_recording(&mut self) -> Result<()> {
-        match self.recorder.send(IpcCommand::StartRecording) {
-            IpcResponse::Ok => Ok(()),
+    /// Starts recording and returns the file it records to, with the placeholders of the output path filled in.
+    pub fn start_recording(&mut self) -> Result<String> {
+        match self.send(IpcCommand::StartRecording)? {
+            IpcResponse::Path(path) => {
+                self.supervisor.recording_started(&path);
+                Ok(path)
+            }
             IpcResponse::Err(e) => Err(Box::new(Error::Recorder(e))),
             _ => Err(Box::new(Error::ShouldNeverHappenNotifyMe)),
         }
     }
 
     pub fn stop_recording(&mut self) -> Result<()> {
-        match self.recorder.send(IpcCommand::StopRecording) {
-            IpcResponse::Ok => Ok(()),
+        match self.send(IpcCommand::StopRecording)? {
+            IpcResponse::Ok => {
+                self.supervisor.recording_stopped();
+                Ok(())
+            }
             IpcResponse::Err(e) => Err(Box::new(Error::Recorder(e))),
             _ => Err(Box::new(Error::ShouldNeverHappenNotifyMe)),
         }
     }
 
     pub fn is_recording(&mut self) -> Result<bool> {
-        match self.recorder.send(IpcCommand::StopRecording) {
+        match self.send(IpcCommand::IsRecording)? {
             IpcResponse::Recording(recording) => Ok(recording),
             IpcResponse::Err(e) => Err(Box::new(Error::Recorder(e))),
             _ => Err(Box::new(Error::ShouldNeverHappenNotifyMe)),
         }
     }
 
+    /// Pauses the recording without ending the file, nothing gets written until it is resumed.
+    ///
+    /// Fails while any other output (e.g. the replay buffer) is running, since pausing pauses the shared encoders.
+    pub fn pause_recording(&mut self) -> Result<()> {
+        match self.send(IpcCommand::PauseRecording)? {
+            IpcResponse::Ok => Ok(()),
+            IpcResponse::Err(e) => Err(Box::new(Error::Recorder(e))),
+            _ => Err(Box::new(Error::ShouldNeverHappenNotifyMe)),
+        }
+    }
+
+    pub fn resume_recording(&mut self) -> Result<()> {
+        match self.send(IpcCommand::ResumeRecording)? {
+            IpcResponse::Ok => Ok(()),
+            IpcResponse::Err(e) => Err(Box::new(Error::Recorder(e))),
+            _ => Err(Box::new(Error::ShouldNeverHappenNotifyMe)),
+        }
+    }
+
+    pub fn is_paused(&mut self) -> Result<bool> {
+        match self.send(IpcCommand::IsPaused)? {
+            IpcResponse::Recording(paused) => Ok(paused),
+            IpcResponse::Err(e) => Err(Box::new(Error::Recorder(e))),
+            _ => Err(Box::new(Error::ShouldNeverHappenNotifyMe)),
+        }
+    }
+
+    /// Ends the current file of the recording and continues in a new one, splitting has to be configured with
+    /// `RecorderSettings::set_split`.
+    ///
+    /// The finished file is reported as `RecorderEvent::SegmentFinished`.
+    pub fn split_recording(&mut self) -> Result<()> {
+        match self.send(IpcCommand::SplitRecording)? {
+            IpcResponse::Ok => Ok(()),
+            IpcResponse::Err(e) => Err(Box::new(Error::Recorder(e))),
+            _ => Err(Box::new(Error::ShouldNeverHappenNotifyMe)),
+        }
+    }
+
+    /// Marks the current moment of the recording with `label`, see `InpRecorder::add_marker`.
+    pub fn add_marker(&mut self, label: &str) -> Result<marker::Marker> {
+        match self.send(IpcCommand::AddMarker(label.to_string()))? {
+            IpcResponse::Marker(marker) => Ok(marker),
+            IpcResponse::Err(e) => Err(Box::new(Error::Recorder(e))),
+            _ => Err(Box::new(Error::ShouldNeverHappenNotifyMe)),
+        }
+    }
+
+    /// Starts remuxing `input` into `output`, e.g. to turn an MKV into an MP4, without encoding them again.
+    ///
+    /// Returns once the recorder has started remuxing, the progress and the result arrive as
+    /// `RecorderEvent::RemuxProgress` and `RecorderEvent::RemuxFinished`.
+    pub fn start_remux(&mut self, input: &str, output: &str) -> Result<()> {
+        let cmd = IpcCommand::Remux {
+            input: input.to_string(),
+            output: output.to_string(),
+        };
+        match self.send(cmd)? {
+            IpcResponse::Ok => Ok(()),
+            IpcResponse::Err(e) => Err(Box::new(Error::Recorder(e))),
+            _ => Err(Box::new(Error::ShouldNeverHappenNotifyMe)),
+        }
+    }
+
+    /// Creates another output next to the main one that shares its encoders, e.g. a second recording.
+    ///
+    /// Added outputs are not recreated when the recorder process gets restarted.
+    pub fn add_output(&mut self, kind: &output::OutputKind) -> Result<output::OutputId> {
+        match self.send(IpcCommand::AddOutput(kind.clone()))? {
+            IpcResponse::Output(id) => Ok(id),
+            IpcResponse::Err(e) => Err(Box::new(Error::Recorder(e))),
+            _ => Err(Box::new(Error::ShouldNeverHappenNotifyMe)),
+        }
+    }
+
+    /// Stops the output if it is running and removes it.
+    pub fn remove_output(&mut self, id: output::OutputId) -> Result<()> {
+        match self.send(IpcCommand::RemoveOutput(id))? {
+            IpcResponse::Ok => Ok(()),
+            IpcResponse::Err(e) => Err(Box::new(Error::Recorder(e))),
+            _ => Err(Box::new(Error::ShouldNeverHappenNotifyMe)),
+        }
+    }
+
+    /// All outputs of the recorder, starting with `OutputId::MAIN`.
+    pub fn outputs(&mut self) -> Result<Vec<output::OutputId>> {
+        match self.send(IpcCommand::Outputs)? {
+            IpcResponse::Outputs(outputs) => Ok(outputs),
+            IpcResponse::Err(e) => Err(Box::new(Error::Recorder(e))),
+            _ => Err(Box::new(Error::ShouldNeverHappenNotifyMe)),
+        }
+    }
+
+    pub fn start_output(&mut self, id: output::OutputId) -> Result<()> {
+        match self.send(IpcCommand::StartOutput(id))? {
+            IpcResponse::Ok => Ok(()),
+            IpcResponse::Err(e) => Err(Box::new(Error::Recorder(e))),
+            _ => Err(Box::new(Error::ShouldNeverHappenNotifyMe)),
+        }
+    }
+
+    pub fn stop_output(&mut self, id: output::OutputId) -> Result<()> {
+        match self.send(IpcCommand::StopOutput(id))? {
+            IpcResponse::Ok => Ok(()),
+            IpcResponse::Err(e) => Err(Box::new(Error::Recorder(e))),
+            _ => Err(Box::new(Error::ShouldNeverHappenNotifyMe)),
+        }
+    }
+
+    pub fn is_output_active(&mut self, id: output::OutputId) -> Result<bool> {
+        match self.send(IpcCommand::IsOutputActive(id))? {
+            IpcResponse::Recording(active) => Ok(active),
+            IpcResponse::Err(e) => Err(Box::new(Error::Recorder(e))),
+            _ => Err(Box::new(Error::ShouldNeverHappenNotifyMe)),
+        }
+    }
+
+    /// Starts keeping the most recent part of the recording in memory, as configured with `set_replay_buffer`.
+    ///
+    /// Returns the id of the output that holds the replay buffer. Like added outputs it is not restarted along with
+    /// the recorder process.
+    pub fn start_replay_buffer(&mut self) -> Result<output::OutputId> {
+        match self.send(IpcCommand::StartReplayBuffer)? {
+            IpcResponse::Output(id) => Ok(id),
+            IpcResponse::Err(e) => Err(Box::new(Error::Recorder(e))),
+            _ => Err(Box::new(Error::ShouldNeverHappenNotifyMe)),
+        }
+    }
+
+    /// Writes what the replay buffer holds to a file, `RecorderEvent::ReplaySaved` reports its path once it is done.
+    pub fn save_replay(&mut self) -> Result<()> {
+        match self.send(IpcCommand::SaveReplay)? {
+            IpcResponse::Ok => Ok(()),
+            IpcResponse::Err(e) => Err(Box::new(Error::Recorder(e))),
+            _ => Err(Box::new(Error::ShouldNeverHappenNotifyMe)),
+        }
+    }
+
+    pub fn stop_replay_buffer(&mut self) -> Result<()> {
+        match self.send(IpcCommand::StopReplayBuffer)? {
+            IpcResponse::Ok => Ok(()),
+            IpcResponse::Err(e) => Err(Box::new(Error::Recorder(e))),
+            _ => Err(Box::new(Error::ShouldNeverHappenNotifyMe)),
+        }
+    }
+
+    /// Returns whether the recorder is recording or paused, how long it has been paused and its frame counters.
+    pub fn status(&mut self) -> Result<status::Status> {
+        match self.send(IpcCommand::Status)? {
+            IpcResponse::Status(status) => Ok(status),
+            IpcResponse::Err(e) => Err(Box::new(Error::Recorder(e))),
+            _ => Err(Box::new(Error::ShouldNeverHappenNotifyMe)),
+        }
+    }
+
     pub fn shutdown(mut self) -> Result<()> {
-        match self.recorder.send(IpcCommand::Shutdown) {
+        match self.send(IpcCommand::Shutdown)? {
             IpcResponse::Ok => { /* OK continue */ }
             IpcResponse::Err(e) => return Err(Box::new(Error::ShutdownFailed(self, e))),
             _ => return Err(Box::new(Error::ShouldNeverHappenNotifyMe)),
         }
-        match self.recorder.send(IpcCommand::Exit) {
+        match self.send(IpcCommand::Exit)? {
             IpcResponse::Ok => {
                 self.recorder.drain_logs();
                 Ok(())
@@ -157,4 +507,52 @@ impl Recorder {
             _ => Err(Box::new(Error::ShouldNeverHappenNotifyMe)),
         }
     }
+
+    /// Stops the recording, waits for libobs to finalise the file, shuts libobs down and lets the recorder exit.
+    ///
+    /// The recorder is only killed once `timeout` has passed, the report says how far it got.
+    /// Dropping the recorder does the same with a timeout of 10 seconds.
+    /// A recorder attached to a daemon only detaches from it.
+    pub fn close(self, timeout: time::Duration) -> CloseReport {
+        self.recorder.close(timeout)
+    }
+
+    fn send(&mut self, cmd: IpcCommand) -> Result<IpcResponse> {
+        match self.recorder.send(cmd.clone()) {
+            Err(ipc_link::Error::ChildExited(status)) if self.supervisor.should_restart(&cmd) => {
+                self.restart(status)?;
+                if let IpcCommand::StartRecording = cmd {
+                    return Err(Box::new(Error::RecorderExited(status)));
+                }
+                Ok(self.recorder.send(cmd).map_err(Error::from)?)
+            }
+            result => Ok(result.map_err(Error::from)?),
+        }
+    }
+
+    /// Replaces the exited recorder process and brings the new one into the state of the old one.
+    fn restart(&mut self, status: Option<process::ExitStatus>) -> Result<()> {
+        let restarts = self.supervisor.count_restart();
+        log::warn!("recorder exited ({status:?}) - restarting it (attempt {restarts})");
+
+        let status = self.recorder.restart().map_err(Error::from)?.or(status);
+        let interrupted_recording = self.supervisor.mark_interrupted_recording();
+
+        let mut replay = vec![self.init.clone()];
+        replay.extend(self.settings.clone().map(IpcCommand::Configure));
+        for cmd in replay {
+            match self.recorder.send(cmd).map_err(Error::from)? {
+                IpcResponse::Ok => { /* OK continue */ }
+                IpcResponse::Err(e) => return Err(Box::new(Error::Recorder(e))),
+                _ => return Err(Box::new(Error::ShouldNeverHappenNotifyMe)),
+            }
+        }
+
+        self.recorder.notify(events::RecorderEvent::Restarted {
+            exit_code: status.and_then(|status| status.code()),
+            restarts,
+            interrupted_recording,
+        });
+        Ok(())
+    }
 }
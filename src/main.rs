@@ -3,13 +3,41 @@
 // gets ignored on all other targets
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use intprocess_recorder::InpRecorder;
-use ipc_link::{IpcCommand, IpcLinkSlave, IpcResponse};
+use std::{env, process};
+
+use intprocess_recorder::{Error, InpRecorder};
+use ipc_link::{IpcCommand, IpcLinkSlave, IpcResponse, Transport, DAEMON_ARG};
 
 fn main() {
     let mut recorder = None;
 
-    IpcLinkSlave::new().respond(|cmd| match cmd {
+    // the master passes the transport it wants to use as argument
+    // a daemon (`--daemon --transport socket:<name>`) has to be started manually and serves every master that connects
+    let daemon = env::args().any(|arg| arg == DAEMON_ARG);
+    let transport = match Transport::from_args(env::args().skip(1)) {
+        Ok(transport) => transport,
+        Err(e) => {
+            eprintln!("{e}");
+            process::exit(2);
+        }
+    };
+    let link = if daemon {
+        IpcLinkSlave::daemon(&transport)
+    } else {
+        IpcLinkSlave::with_transport(&transport)
+    };
+    let mut link = match link {
+        Ok(link) => link,
+        Err(e) => {
+            eprintln!("failed to open {transport}: {e}");
+            process::exit(1);
+        }
+    };
+    // stdout might be reserved for the protocol, so everything gets logged through the link
+    _ = link.install_logger(log::LevelFilter::Debug);
+    let events = link.event_sender();
+
+    link.respond(|cmd| match cmd {
         IpcCommand::Init {
             libobs_data_path,
             plugin_bin_path,
@@ -20,26 +48,28 @@ fn main() {
                 plugin_bin_path.as_deref(),
                 plugin_data_path.as_deref(),
             ) {
-                return Some(IpcResponse::Err(e.to_string()));
+                return Some(IpcResponse::Err(e));
             }
 
             match InpRecorder::get_handle() {
-                Ok(rec) => {
+                Ok(mut rec) => {
+                    let events = events.clone();
+                    rec.set_event_handler(move |event| events.send(event));
                     recorder = Some(rec);
                     Some(IpcResponse::Ok)
                 }
-                Err(e) => Some(IpcResponse::Err(e.to_string())),
+                Err(e) => Some(IpcResponse::Err(e)),
             }
         }
         IpcCommand::Configure(settings) => {
             if let Some(recorder) = recorder.as_mut() {
                 if let Err(e) = recorder.configure(&settings) {
-                    Some(IpcResponse::Err(e.to_string()))
+                    Some(IpcResponse::Err(e))
                 } else {
                     Some(IpcResponse::Ok)
                 }
             } else {
-                Some(IpcResponse::Err("recorder not initialized".into()))
+                Some(IpcResponse::Err(Error::NotInitialized))
             }
         }
         IpcCommand::Encoders => {
@@ -49,25 +79,24 @@ fn main() {
                     selected: recorder.selected_encoder(),
                 })
             } else {
-                Some(IpcResponse::Err("recorder not initialized".into()))
+                Some(IpcResponse::Err(Error::NotInitialized))
             }
         }
         IpcCommand::Adapter => {
             if let Some(recorder) = recorder.as_mut() {
                 Some(IpcResponse::Adapter(recorder.get_adapter_info()))
             } else {
-                Some(IpcResponse::Err("recorder not initialized".into()))
+                Some(IpcResponse::Err(Error::NotInitialized))
             }
         }
         IpcCommand::StartRecording => {
             if let Some(recorder) = recorder.as_mut() {
-                if let Err(e) = recorder.start_recording() {
-                    Some(IpcResponse::Err(format!("failed to start recording: {e}")))
-                } else {
-                    Some(IpcResponse::Ok)
+                match recorder.start_recording() {
+                    Ok(path) => Some(IpcResponse::Path(path)),
+                    Err(e) => Some(IpcResponse::Err(e)),
                 }
             } else {
-                Some(IpcResponse::Err("recorder not initialized".into()))
+                Some(IpcResponse::Err(Error::NotInitialized))
             }
         }
         IpcCommand::StopRecording => {
@@ -77,14 +106,90 @@ fn main() {
                 }
                 Some(IpcResponse::Ok)
             } else {
-                Some(IpcResponse::Err("recorder not initialized".into()))
+                Some(IpcResponse::Err(Error::NotInitialized))
             }
         }
         IpcCommand::IsRecording => {
             if let Some(recorder) = recorder.as_mut() {
                 Some(IpcResponse::Recording(recorder.is_recording()))
             } else {
-                Some(IpcResponse::Err("recorder not initialized".into()))
+                Some(IpcResponse::Err(Error::NotInitialized))
+            }
+        }
+        IpcCommand::PauseRecording => {
+            if let Some(recorder) = recorder.as_mut() {
+                if let Err(e) = recorder.pause_recording() {
+                    Some(IpcResponse::Err(e))
+                } else {
+                    Some(IpcResponse::Ok)
+                }
+            } else {
+                Some(IpcResponse::Err(Error::NotInitialized))
+            }
+        }
+        IpcCommand::ResumeRecording => {
+            if let Some(recorder) = recorder.as_mut() {
+                if let Err(e) = recorder.resume_recording() {
+                    Some(IpcResponse::Err(e))
+                } else {
+                    Some(IpcResponse::Ok)
+                }
+            } else {
+                Some(IpcResponse::Err(Error::NotInitialized))
+            }
+        }
+        IpcCommand::IsPaused => {
+            if let Some(recorder) = recorder.as_mut() {
+                Some(IpcResponse::Recording(recorder.is_paused()))
+            } else {
+                Some(IpcResponse::Err(Error::NotInitialized))
+            }
+        }
+        IpcCommand::SplitRecording => {
+            if let Some(recorder) = recorder.as_mut() {
+                if let Err(e) = recorder.split_recording() {
+                    Some(IpcResponse::Err(e))
+                } else {
+                    Some(IpcResponse::Ok)
+                }
+            } else {
+                Some(IpcResponse::Err(Error::NotInitialized))
+            }
+        }
+        IpcCommand::AddMarker(label) => {
+            if let Some(recorder) = recorder.as_mut() {
+                match recorder.add_marker(&label) {
+                    Ok(marker) => Some(IpcResponse::Marker(marker)),
+                    Err(e) => Some(IpcResponse::Err(e)),
+                }
+            } else {
+                Some(IpcResponse::Err(Error::NotInitialized))
+            }
+        }
+        IpcCommand::Remux { input, output } => {
+            if let Some(recorder) = recorder.as_mut() {
+                if let Err(e) = recorder.start_remux(&input, &output) {
+                    Some(IpcResponse::Err(e))
+                } else {
+                    Some(IpcResponse::Ok)
+                }
+            } else {
+                Some(IpcResponse::Err(Error::NotInitialized))
+            }
+        }
+        IpcCommand::AddOutput(_)
+        | IpcCommand::RemoveOutput(_)
+        | IpcCommand::Outputs
+        | IpcCommand::StartOutput(_)
+        | IpcCommand::StopOutput(_)
+        | IpcCommand::IsOutputActive(_)
+        | IpcCommand::StartReplayBuffer
+        | IpcCommand::SaveReplay
+        | IpcCommand::StopReplayBuffer => {
+            if let Some(recorder) = recorder.as_mut() {
+                Some(handle_output(recorder, cmd))
+            } else {
+                Some(IpcResponse::Err(Error::NotInitialized))
             }
         }
         IpcCommand::Shutdown => {
@@ -94,17 +199,45 @@ fn main() {
             }
 
             if let Err(e) = InpRecorder::shutdown() {
-                Some(IpcResponse::Err(e.to_string()))
+                Some(IpcResponse::Err(e))
             } else {
                 Some(IpcResponse::Ok)
             }
         }
         IpcCommand::Exit => {
             if recorder.is_some() {
-                Some(IpcResponse::Err("recorder is not shut down".into()))
+                Some(IpcResponse::Err(Error::NotShutDown))
             } else {
                 None
             }
         }
+        IpcCommand::Status => {
+            if let Some(recorder) = recorder.as_mut() {
+                Some(IpcResponse::Status(recorder.status()))
+            } else {
+                Some(IpcResponse::Err(Error::NotInitialized))
+            }
+        }
+        // answered by the link itself
+        IpcCommand::Detach | IpcCommand::SetHeartbeat(_) => Some(IpcResponse::Ok),
     });
 }
+
+fn handle_output(recorder: &mut InpRecorder, cmd: IpcCommand) -> IpcResponse {
+    let result = match cmd {
+        IpcCommand::AddOutput(kind) => recorder.add_output(&kind).map(IpcResponse::Output),
+        IpcCommand::RemoveOutput(id) => recorder.remove_output(id).map(|()| IpcResponse::Ok),
+        IpcCommand::Outputs => Ok(IpcResponse::Outputs(recorder.outputs())),
+        IpcCommand::StartOutput(id) => recorder.start_output(id).map(|()| IpcResponse::Ok),
+        IpcCommand::StopOutput(id) => recorder.stop_output(id).map(|()| IpcResponse::Ok),
+        IpcCommand::IsOutputActive(id) => recorder.is_output_active(id).map(IpcResponse::Recording),
+        IpcCommand::StartReplayBuffer => recorder.start_replay_buffer().map(IpcResponse::Output),
+        IpcCommand::SaveReplay => recorder.save_replay().map(|()| IpcResponse::Ok),
+        IpcCommand::StopReplayBuffer => {
+            recorder.stop_replay_buffer();
+            Ok(IpcResponse::Ok)
+        }
+        _ => unreachable!("only output commands are handled here"),
+    };
+    result.unwrap_or_else(IpcResponse::Err)
+}
@@ -0,0 +1,117 @@
+use std::{path::Path, sync::mpsc};
+
+use ipc_link::{IpcCommand, IpcLinkMaster};
+
+use crate::events::RecorderEvent;
+
+/// What a `Recorder` does when the recorder process exits unexpectedly.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RestartPolicy {
+    /// every further call fails with `Error::RecorderExited` and the `Recorder` has to be recreated
+    #[default]
+    Never,
+    /// spawn a new recorder process, replay `Init` and the last `configure` and retry the failed call,
+    /// at most this many times over the lifetime of the `Recorder`
+    UpTo(u32),
+}
+
+/// Keeps track of everything needed to recover from a crashed recorder process.
+#[derive(Debug)]
+pub(crate) struct Supervisor {
+    policy: RestartPolicy,
+    restarts: u32,
+    // file the recording that is currently running writes to, follows the files of a split recording
+    recording: Option<String>,
+    // own subscription to notice recordings that stopped on their own (e.g. disk full)
+    events: mpsc::Receiver<RecorderEvent>,
+}
+
+impl Supervisor {
+    pub(crate) fn new(link: &IpcLinkMaster) -> Self {
+        Self {
+            policy: RestartPolicy::Never,
+            restarts: 0,
+            recording: None,
+            events: link.subscribe(),
+        }
+    }
+
+    pub(crate) fn set_policy(&mut self, policy: RestartPolicy) {
+        self.policy = policy;
+    }
+
+    /// Returns whether a recorder process that exited while executing `cmd` should be restarted.
+    pub(crate) fn should_restart(&self, cmd: &IpcCommand) -> bool {
+        let allowed = match self.policy {
+            RestartPolicy::Never => false,
+            RestartPolicy::UpTo(max_restarts) => self.restarts < max_restarts,
+        };
+        // no point in restarting a process that is supposed to go away anyway
+        allowed && !matches!(cmd, IpcCommand::Shutdown | IpcCommand::Exit)
+    }
+
+    /// Counts a restart attempt and returns the total number of attempts.
+    pub(crate) fn count_restart(&mut self) -> u32 {
+        self.restarts += 1;
+        self.restarts
+    }
+
+    pub(crate) fn recording_started(&mut self, output_path: &str) {
+        // events from earlier recordings must not reset the new one
+        self.update();
+        self.recording = Some(output_path.to_string());
+    }
+
+    pub(crate) fn recording_stopped(&mut self) {
+        self.recording = None;
+    }
+
+    /// Renames the file of the recording that was running when the recorder process exited.
+    ///
+    /// Returns the new path, which keeps the file from being overwritten by the next recording.
+    pub(crate) fn mark_interrupted_recording(&mut self) -> Option<String> {
+        self.update();
+        let path = self.recording.take()?;
+        mark_interrupted(Path::new(&path))
+    }
+
+    fn update(&mut self) {
+        for event in self.events.try_iter() {
+            match event {
+                RecorderEvent::RecordingStopped { .. } => self.recording = None,
+                RecorderEvent::SegmentFinished { next: Some(next), .. } if self.recording.is_some() => {
+                    self.recording = Some(next);
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Renames `video.mp4` to `video.interrupted.mp4` (or `video.interrupted-1.mp4`, ... if that already exists).
+fn mark_interrupted(path: &Path) -> Option<String> {
+    if !path.exists() {
+        return None;
+    }
+
+    let stem = path.file_stem()?.to_string_lossy();
+    let extension = path
+        .extension()
+        .map(|ext| format!(".{}", ext.to_string_lossy()))
+        .unwrap_or_default();
+
+    let target = (0..)
+        .map(|n| match n {
+            0 => path.with_file_name(format!("{stem}.interrupted{extension}")),
+            n => path.with_file_name(format!("{stem}.interrupted-{n}{extension}")),
+        })
+        .find(|candidate| !candidate.exists())?;
+
+    match std::fs::rename(path, &target) {
+        Ok(()) => Some(target.to_string_lossy().into_owned()),
+        Err(e) => {
+            log::warn!("failed to mark interrupted recording {}: {e}", path.display());
+            Some(path.to_string_lossy().into_owned())
+        }
+    }
+}
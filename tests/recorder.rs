@@ -0,0 +1,608 @@
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+use ipc_link::journal::{self, JournalEntry};
+use libobs_recorder::events::{RecorderEvent, StopReason};
+use libobs_recorder::output::{OutputId, OutputKind};
+use libobs_recorder::settings::{
+    Container, Encoder, RateControl, RecorderSettings, ReplayBuffer, Split, StdResolution, Window,
+};
+use libobs_recorder::{
+    Error, Framing, Recorder, RecorderError, RestartPolicy, StageOutcome, Timeouts, Transport, Watchdog, WatchdogAction,
+};
+
+const MOCK_RECORDER: &str = env!("CARGO_BIN_EXE_mock_recorder");
+
+fn recorder(script: &str) -> Recorder {
+    Recorder::new_with_paths(Some(MOCK_RECORDER), Some(script), None, None).unwrap()
+}
+
+fn settings() -> RecorderSettings {
+    let mut settings = RecorderSettings::new(
+        Window::new("Game", Some("GameClass".into()), Some("game.exe".into())),
+        StdResolution::_1920x1080p,
+        StdResolution::_1920x1080p,
+        "./recording.mp4",
+    );
+    settings.set_rate_control(RateControl::CQP(20));
+    settings
+}
+
+#[test]
+fn record() {
+    let mut recorder = recorder("");
+    let events = recorder.subscribe();
+
+    recorder.configure(&settings()).unwrap();
+    assert!(!recorder.is_recording().unwrap());
+
+    recorder.start_recording().unwrap();
+    assert!(recorder.is_recording().unwrap());
+    assert_eq!(
+        events.recv_timeout(Duration::from_secs(5)),
+        Ok(RecorderEvent::RecordingStarted)
+    );
+
+    recorder.stop_recording().unwrap();
+    assert!(!recorder.is_recording().unwrap());
+    assert_eq!(
+        events.recv_timeout(Duration::from_secs(5)),
+        Ok(RecorderEvent::RecordingStopped {
+            reason: StopReason::Success,
+            error: None
+        })
+    );
+
+    recorder.shutdown().unwrap();
+}
+
+#[test]
+fn encoders_and_adapter() {
+    let mut recorder = recorder("adapter=NVIDIA GeForce RTX 4080;encoders=obs_x264,h264_texture_amf,jim_nvenc");
+
+    assert_eq!(recorder.adapter_info().unwrap().name(), "NVIDIA GeForce RTX 4080");
+    assert_eq!(
+        recorder.available_encoders().unwrap(),
+        [Encoder::JIM_NVENC, Encoder::OBS_X264]
+    );
+    assert_eq!(recorder.selected_encoder().unwrap(), Encoder::JIM_NVENC);
+
+    let mut settings = settings();
+    settings.set_encoder(Encoder::AMD_AMF_H264);
+    match recorder.configure(&settings).map_err(|e| *e) {
+        Err(Error::Recorder(RecorderError::EncoderUnavailable(Encoder::AMD_AMF_H264))) => {}
+        other => panic!("unexpected result: {other:?}"),
+    }
+
+    settings.set_encoder(Encoder::OBS_X264);
+    recorder.configure(&settings).unwrap();
+    assert_eq!(recorder.selected_encoder().unwrap(), Encoder::OBS_X264);
+
+    recorder.shutdown().unwrap();
+}
+
+#[test]
+fn start_failure_is_reported() {
+    let mut recorder = recorder("fail=output_start:disk full");
+    recorder.configure(&settings()).unwrap();
+
+    match recorder.start_recording().map_err(|e| *e) {
+        Err(Error::Recorder(RecorderError::OutputStartFailed(Some(error)))) => assert_eq!(error, "disk full"),
+        other => panic!("unexpected result: {other:?}"),
+    }
+    assert!(!recorder.is_recording().unwrap());
+
+    recorder.shutdown().unwrap();
+}
+
+#[test]
+fn init_failure_is_reported() {
+    let result = Recorder::new_with_paths(Some(MOCK_RECORDER), Some("fail=startup"), None, None);
+    match result.map_err(|e| *e) {
+        Err(Error::Recorder(RecorderError::StartupFailed)) => {}
+        other => panic!("unexpected result: {other:?}"),
+    }
+}
+
+#[test]
+fn slow_recorder_times_out() {
+    let mut recorder = recorder("delay=Configure:1000");
+    recorder.set_timeouts(Timeouts {
+        configure: Duration::from_millis(100),
+        ..Timeouts::default()
+    });
+
+    match recorder.configure(&settings()).map_err(|e| *e) {
+        Err(Error::Timeout { command: "Configure", .. }) => {}
+        other => panic!("unexpected result: {other:?}"),
+    }
+    // the late answer to Configure must not be mistaken for the answer to IsRecording
+    assert!(!recorder.is_recording().unwrap());
+
+    recorder.shutdown().unwrap();
+}
+
+#[test]
+fn crashed_recorder_gets_restarted() {
+    let mut recorder = recorder("crash=StartRecording:3");
+    recorder.set_restart_policy(RestartPolicy::UpTo(1));
+    let events = recorder.subscribe();
+    recorder.configure(&settings()).unwrap();
+
+    // a recording is never silently restarted
+    match recorder.start_recording().map_err(|e| *e) {
+        Err(Error::RecorderExited(Some(status))) => assert_eq!(status.code(), Some(3)),
+        other => panic!("unexpected result: {other:?}"),
+    }
+    assert_eq!(
+        events.recv_timeout(Duration::from_secs(5)),
+        Ok(RecorderEvent::Restarted {
+            exit_code: Some(3),
+            restarts: 1,
+            interrupted_recording: None
+        })
+    );
+
+    // the new process has been initialized and configured
+    assert!(!recorder.is_recording().unwrap());
+    assert_eq!(recorder.selected_encoder().unwrap(), Encoder::OBS_X264);
+
+    recorder.shutdown().unwrap();
+}
+
+#[test]
+fn local_socket_transport() {
+    let mut recorder =
+        Recorder::new_with_transport(Some(MOCK_RECORDER), Transport::local_socket(), Some(""), None, None).unwrap();
+
+    recorder.configure(&settings()).unwrap();
+    recorder.start_recording().unwrap();
+    assert!(recorder.is_recording().unwrap());
+    recorder.stop_recording().unwrap();
+
+    recorder.shutdown().unwrap();
+}
+
+#[test]
+fn message_pack_framing() {
+    let mut recorder = Recorder::new_with_framing(
+        Some(MOCK_RECORDER),
+        Transport::Stdio,
+        Framing::MessagePack,
+        Some(""),
+        None,
+        None,
+    )
+    .unwrap();
+
+    recorder.configure(&settings()).unwrap();
+    recorder.start_recording().unwrap();
+    assert!(recorder.is_recording().unwrap());
+    recorder.stop_recording().unwrap();
+
+    recorder.shutdown().unwrap();
+}
+
+fn watchdog(action: WatchdogAction) -> Watchdog {
+    Watchdog::new(Duration::from_millis(50), 3, action)
+}
+
+/// Waits for the next `RecorderEvent::Unresponsive` and skips everything else.
+fn next_unresponsive(events: &mpsc::Receiver<RecorderEvent>, timeout: Duration) -> Option<RecorderEvent> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        match events.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+            Ok(event @ RecorderEvent::Unresponsive { .. }) => return Some(event),
+            Ok(_) => continue,
+            Err(_) => return None,
+        }
+    }
+}
+
+#[test]
+fn status_reports_frame_counters() {
+    let mut recorder = recorder("");
+    recorder.configure(&settings()).unwrap();
+
+    let idle = recorder.status().unwrap();
+    assert!(!idle.recording);
+    recorder.start_recording().unwrap();
+    let recording = recorder.status().unwrap();
+    assert!(recording.recording);
+    // the mock renders one frame per status query
+    assert!(recording.frames.rendered > idle.frames.rendered);
+    assert_eq!(recording.frames.output, 1);
+
+    recorder.shutdown().unwrap();
+}
+
+#[test]
+fn watchdog_notices_frozen_rendering() {
+    let mut recorder = recorder("freeze=StartRecording");
+    let events = recorder.subscribe();
+    recorder.configure(&settings()).unwrap();
+    recorder.set_watchdog(Some(watchdog(WatchdogAction::Notify))).unwrap();
+
+    // heartbeats keep coming, but the frame counter stands still
+    assert_eq!(next_unresponsive(&events, Duration::from_millis(500)), None);
+    recorder.start_recording().unwrap();
+    match next_unresponsive(&events, Duration::from_secs(5)) {
+        Some(RecorderEvent::Unresponsive {
+            missed_heartbeats,
+            killed: false,
+        }) => assert!(missed_heartbeats >= 3),
+        other => panic!("unexpected event: {other:?}"),
+    }
+    assert!(recorder.exit_status().unwrap().is_none());
+
+    recorder.shutdown().unwrap();
+}
+
+#[test]
+fn watchdog_kills_hanging_recorder() {
+    let mut recorder = recorder("hang=Status");
+    let events = recorder.subscribe();
+    recorder.set_watchdog(Some(watchdog(WatchdogAction::Kill))).unwrap();
+
+    assert!(matches!(
+        next_unresponsive(&events, Duration::from_secs(5)),
+        Some(RecorderEvent::Unresponsive { killed: true, .. })
+    ));
+    match recorder.is_recording().map_err(|e| *e) {
+        Err(Error::RecorderExited(_)) => {}
+        other => panic!("unexpected result: {other:?}"),
+    }
+}
+
+#[test]
+fn killed_recorder_gets_restarted() {
+    let mut recorder = recorder("freeze=StartRecording");
+    recorder.set_restart_policy(RestartPolicy::UpTo(1));
+    let events = recorder.subscribe();
+    recorder.configure(&settings()).unwrap();
+    recorder.set_watchdog(Some(watchdog(WatchdogAction::Kill))).unwrap();
+
+    recorder.start_recording().unwrap();
+    assert!(matches!(
+        next_unresponsive(&events, Duration::from_secs(5)),
+        Some(RecorderEvent::Unresponsive { killed: true, .. })
+    ));
+
+    assert!(!recorder.is_recording().unwrap());
+    assert!(events
+        .try_iter()
+        .any(|event| matches!(event, RecorderEvent::Restarted { restarts: 1, .. })));
+    // the new process gets heartbeats as well and renders just fine
+    assert_eq!(next_unresponsive(&events, Duration::from_millis(500)), None);
+
+    recorder.shutdown().unwrap();
+}
+
+#[test]
+fn slow_commands_do_not_count_as_missed_heartbeats() {
+    let mut recorder = recorder("delay=Configure:500");
+    let events = recorder.subscribe();
+    recorder.set_watchdog(Some(watchdog(WatchdogAction::Notify))).unwrap();
+
+    recorder.configure(&settings()).unwrap();
+    assert_eq!(next_unresponsive(&events, Duration::from_millis(300)), None);
+
+    recorder.shutdown().unwrap();
+}
+
+#[test]
+fn shutdown_leaves_nothing_to_close() {
+    let journal = std::env::temp_dir().join(format!("libobs-recorder-{}-shutdown.jsonl", std::process::id()));
+    let mut recorder = recorder("");
+    recorder.journal_to(&journal).unwrap();
+    recorder.configure(&settings()).unwrap();
+    recorder.start_recording().unwrap();
+    recorder.stop_recording().unwrap();
+    recorder.shutdown().unwrap();
+
+    let records = journal::read(&journal).unwrap();
+    std::fs::remove_file(&journal).unwrap();
+    let commands: Vec<_> = records
+        .iter()
+        .filter_map(|record| match &record.entry {
+            JournalEntry::Command(request) => Some(request.command.name()),
+            _ => None,
+        })
+        .collect();
+    // dropping the recorder does not try to close it again
+    assert_eq!(commands[commands.len() - 2..], ["Shutdown", "Exit"]);
+    assert!(!records
+        .iter()
+        .any(|record| matches!(record.entry, JournalEntry::Timeout { .. } | JournalEntry::Exited { .. })));
+}
+
+#[test]
+fn close_finalizes_recording() {
+    let mut recorder = recorder("delay=StopRecording:200");
+    recorder.configure(&settings()).unwrap();
+    recorder.start_recording().unwrap();
+
+    let report = recorder.close(Duration::from_secs(5));
+    assert_eq!(report.stop_recording, StageOutcome::Done);
+    assert_eq!(report.shutdown, StageOutcome::Done);
+    assert_eq!(report.exit, StageOutcome::Done);
+    assert!(!report.killed);
+    assert!(report.exit_status.unwrap().success());
+    assert!(report.is_clean());
+}
+
+#[test]
+fn close_without_recording() {
+    let report = recorder("").close(Duration::from_secs(5));
+    assert_eq!(report.stop_recording, StageOutcome::NotNeeded);
+    assert!(report.is_clean(), "{report}");
+}
+
+#[test]
+fn close_reports_unfinished_file() {
+    let mut recorder = recorder("fail=output_stop:could not write index");
+    recorder.configure(&settings()).unwrap();
+    recorder.start_recording().unwrap();
+
+    let report = recorder.close(Duration::from_secs(5));
+    assert_eq!(
+        report.stop_recording,
+        StageOutcome::Failed("Error: could not write index".into())
+    );
+    // libobs still gets shut down properly
+    assert_eq!(report.shutdown, StageOutcome::Done);
+    assert_eq!(report.exit, StageOutcome::Done);
+    assert!(!report.killed);
+    assert!(!report.is_clean());
+}
+
+#[test]
+fn close_kills_after_deadline() {
+    let mut recorder = recorder("hang=StopRecording");
+    recorder.configure(&settings()).unwrap();
+    recorder.start_recording().unwrap();
+
+    let started = Instant::now();
+    let report = recorder.close(Duration::from_millis(300));
+    assert!(started.elapsed() < Duration::from_secs(2));
+    assert_eq!(report.stop_recording, StageOutcome::TimedOut);
+    assert_eq!(report.shutdown, StageOutcome::Skipped);
+    assert_eq!(report.exit, StageOutcome::Skipped);
+    assert!(report.killed);
+    assert!(!report.is_clean());
+}
+
+#[test]
+fn close_kills_recorder_stuck_in_shutdown() {
+    let mut recorder = recorder("hang=Shutdown");
+    recorder.configure(&settings()).unwrap();
+
+    let report = recorder.close(Duration::from_millis(300));
+    assert_eq!(report.stop_recording, StageOutcome::NotNeeded);
+    assert_eq!(report.shutdown, StageOutcome::TimedOut);
+    assert_eq!(report.exit, StageOutcome::Skipped);
+    assert!(report.killed);
+}
+
+#[test]
+fn additional_output() {
+    let mut recorder = recorder("");
+    let events = recorder.subscribe();
+    recorder.configure(&settings()).unwrap();
+
+    let id = recorder
+        .add_output(&OutputKind::Recording { path: "./second.mp4".into() })
+        .unwrap();
+    assert_eq!(recorder.outputs().unwrap(), [OutputId::MAIN, id]);
+
+    recorder.start_output(id).unwrap();
+    assert!(recorder.is_output_active(id).unwrap());
+    assert!(!recorder.is_recording().unwrap());
+    assert_eq!(
+        events.recv_timeout(Duration::from_secs(5)),
+        Ok(RecorderEvent::OutputStarted { output: id })
+    );
+
+    recorder.remove_output(id).unwrap();
+    assert_eq!(
+        events.recv_timeout(Duration::from_secs(5)),
+        Ok(RecorderEvent::OutputStopped {
+            output: id,
+            reason: StopReason::Success,
+            error: None
+        })
+    );
+    match recorder.is_output_active(id).map_err(|e| *e) {
+        Err(Error::Recorder(RecorderError::UnknownOutput(unknown))) => assert_eq!(unknown, id),
+        other => panic!("unexpected result: {other:?}"),
+    }
+
+    recorder.shutdown().unwrap();
+}
+
+#[test]
+fn replay_buffer() {
+    let mut recorder = recorder("");
+    let events = recorder.subscribe();
+
+    let mut settings = settings();
+    settings.set_replay_buffer(ReplayBuffer::new(30, 500));
+    recorder.configure(&settings).unwrap();
+
+    let id = recorder.start_replay_buffer().unwrap();
+    assert!(recorder.is_output_active(id).unwrap());
+    assert_eq!(
+        events.recv_timeout(Duration::from_secs(5)),
+        Ok(RecorderEvent::OutputStarted { output: id })
+    );
+
+    recorder.save_replay().unwrap();
+    match events.recv_timeout(Duration::from_secs(5)) {
+        Ok(RecorderEvent::ReplaySaved { path }) => assert!(path.starts_with("./recording_replay_"), "{path}"),
+        other => panic!("unexpected event: {other:?}"),
+    }
+
+    recorder.stop_replay_buffer().unwrap();
+    match recorder.save_replay().map_err(|e| *e) {
+        Err(Error::Recorder(RecorderError::ReplayBufferNotActive)) => {}
+        other => panic!("unexpected result: {other:?}"),
+    }
+
+    recorder.shutdown().unwrap();
+}
+
+#[test]
+fn pause_and_resume() {
+    let mut recorder = recorder("");
+    recorder.configure(&settings()).unwrap();
+    recorder.start_recording().unwrap();
+
+    recorder.pause_recording().unwrap();
+    assert!(recorder.is_paused().unwrap());
+    assert!(recorder.status().unwrap().paused);
+
+    recorder.resume_recording().unwrap();
+    assert!(!recorder.is_paused().unwrap());
+    assert!(!recorder.status().unwrap().paused);
+
+    recorder.stop_recording().unwrap();
+    match recorder.pause_recording().map_err(|e| *e) {
+        Err(Error::Recorder(RecorderError::NotRecording)) => {}
+        other => panic!("unexpected result: {other:?}"),
+    }
+
+    recorder.shutdown().unwrap();
+}
+
+#[test]
+fn split_recording() {
+    let mut recorder = recorder("");
+    let events = recorder.subscribe();
+
+    let mut settings = settings();
+    settings.set_split(Split::new(600, 0));
+    recorder.configure(&settings).unwrap();
+    recorder.start_recording().unwrap();
+    assert_eq!(
+        events.recv_timeout(Duration::from_secs(5)),
+        Ok(RecorderEvent::RecordingStarted)
+    );
+
+    recorder.split_recording().unwrap();
+    let next = match events.recv_timeout(Duration::from_secs(5)) {
+        Ok(RecorderEvent::SegmentFinished { path, next: Some(next) }) => {
+            assert_eq!(path, "./recording.mp4");
+            assert!(next.starts_with("./recording_"), "{next}");
+            next
+        }
+        other => panic!("unexpected event: {other:?}"),
+    };
+
+    recorder.stop_recording().unwrap();
+    assert_eq!(
+        events.recv_timeout(Duration::from_secs(5)),
+        Ok(RecorderEvent::SegmentFinished { path: next, next: None })
+    );
+
+    recorder.configure(&self::settings()).unwrap();
+    recorder.start_recording().unwrap();
+    match recorder.split_recording().map_err(|e| *e) {
+        Err(Error::Recorder(RecorderError::SplitNotConfigured)) => {}
+        other => panic!("unexpected result: {other:?}"),
+    }
+
+    recorder.shutdown().unwrap();
+}
+
+#[test]
+fn output_path_template() {
+    let mut recorder = recorder("");
+
+    let mut settings = settings();
+    settings.set_output_path("./{process}_{counter}.mp4");
+    recorder.configure(&settings).unwrap();
+    assert_eq!(recorder.start_recording().unwrap(), "./game_001.mp4");
+    recorder.stop_recording().unwrap();
+    assert_eq!(recorder.start_recording().unwrap(), "./game_002.mp4");
+    recorder.stop_recording().unwrap();
+
+    settings.set_output_path("./{unknown}.mp4");
+    match recorder.configure(&settings).map_err(|e| *e) {
+        Err(Error::Recorder(RecorderError::InvalidPathTemplate(path))) => assert_eq!(path, "./{unknown}.mp4"),
+        other => panic!("unexpected result: {other:?}"),
+    }
+
+    recorder.shutdown().unwrap();
+}
+
+#[test]
+fn remux() {
+    let mut recorder = recorder("");
+    let events = recorder.subscribe();
+
+    let mut settings = settings();
+    settings.set_output_path("./recording");
+    settings.set_container(Container::MKV);
+    settings.set_remux_on_stop(true);
+    recorder.configure(&settings).unwrap();
+    assert_eq!(recorder.start_recording().unwrap(), "./recording.mkv");
+    recorder.stop_recording().unwrap();
+    loop {
+        match events.recv_timeout(Duration::from_secs(5)) {
+            Ok(RecorderEvent::RemuxFinished { input, output, success }) => {
+                assert_eq!(input, "./recording.mkv");
+                assert_eq!(output, "./recording.mp4");
+                assert!(success);
+                break;
+            }
+            Ok(_) => {}
+            Err(e) => panic!("no remux event: {e}"),
+        }
+    }
+
+    match recorder.start_remux("./missing.mkv", "./missing.mp4").map_err(|e| *e) {
+        Err(Error::Recorder(RecorderError::FileNotFound(path))) => assert_eq!(path, "./missing.mkv"),
+        other => panic!("unexpected result: {other:?}"),
+    }
+
+    recorder.shutdown().unwrap();
+}
+
+#[test]
+fn markers() {
+    let mut recorder = recorder("");
+    recorder.configure(&settings()).unwrap();
+    match recorder.add_marker("kill").map_err(|e| *e) {
+        Err(Error::Recorder(RecorderError::NotRecording)) => {}
+        other => panic!("unexpected result: {other:?}"),
+    }
+
+    recorder.start_recording().unwrap();
+    let kill = recorder.add_marker("kill").unwrap();
+    let baron = recorder.add_marker("baron").unwrap();
+    assert_eq!(kill.label, "kill");
+    assert!(baron.time >= kill.time);
+    recorder.stop_recording().unwrap();
+
+    recorder.shutdown().unwrap();
+}
+
+#[test]
+fn container_mismatch_is_reported() {
+    let mut recorder = recorder("");
+
+    let mut settings = settings();
+    settings.set_container(Container::MKV);
+    match recorder.configure(&settings).map_err(|e| *e) {
+        Err(Error::Recorder(RecorderError::ExtensionMismatch { path, container })) => {
+            assert_eq!(path, "./recording.mp4");
+            assert_eq!(container, Container::MKV);
+        }
+        other => panic!("unexpected result: {other:?}"),
+    }
+
+    settings.set_output_path("./recording");
+    recorder.configure(&settings).unwrap();
+
+    recorder.shutdown().unwrap();
+}